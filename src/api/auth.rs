@@ -0,0 +1,129 @@
+use super::{problem, Status};
+use crate::domain::authorize;
+use crate::repositories::api_key_store::{ApiKeyStore, Role};
+use std::sync::Arc;
+
+/// The least role each route needs, the health checks staying open so that they can be probed
+/// without a key.
+fn required_role(req: &rouille::Request) -> Option<Role> {
+    match (req.method(), req.url().as_str()) {
+        (_, "/health") | (_, "/health/live") | (_, "/health/ready") => None,
+        ("GET", "/audit") => Some(Role::Admin),
+        ("GET", _) | ("HEAD", _) => Some(Role::Reader),
+        _ => Some(Role::Editor),
+    }
+}
+
+/// Checks the `Authorization: Bearer` key of a request against the role its route needs, returning
+/// the name of the key or the response refusing the request.
+pub fn authorize(
+    store: Arc<dyn ApiKeyStore>,
+    req: &rouille::Request,
+) -> Result<Option<String>, rouille::Response> {
+    let role = match required_role(req) {
+        Some(role) => role,
+        None => return Ok(None),
+    };
+
+    let key = match req
+        .header("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+    {
+        Some(key) if !key.trim().is_empty() => String::from(key.trim()),
+        _ => return Err(unauthorized("The request should carry an API key")),
+    };
+
+    match authorize::execute(store, authorize::Request { key, role }) {
+        Ok(res) => Ok(Some(res.name)),
+        Err(authorize::Error::Unauthenticated) => {
+            Err(unauthorized("The API key is unknown or was revoked"))
+        }
+        Err(authorize::Error::Forbidden) => Err(problem::response(
+            403,
+            "Forbidden",
+            "The role of the API key does not allow this request",
+        )),
+        Err(authorize::Error::Unknown) => Err(rouille::Response::from(Status::InternalServerError)),
+    }
+}
+
+fn unauthorized(detail: &'static str) -> rouille::Response {
+    problem::response(401, "Unauthorized", detail).with_unique_header("WWW-Authenticate", "Bearer")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::issue_api_key;
+    use crate::repositories::sqlite_api_key_store::SqliteApiKeyStore;
+
+    fn issue(store: Arc<dyn ApiKeyStore>, role: &str) -> String {
+        match issue_api_key::execute(
+            store,
+            issue_api_key::Request {
+                name: String::from(role),
+                role: String::from(role),
+            },
+        ) {
+            Ok(res) => res.key,
+            _ => unreachable!(),
+        }
+    }
+
+    fn request(method: &str, url: &str, key: Option<&str>) -> rouille::Request {
+        let headers = match key {
+            Some(key) => vec![(String::from("Authorization"), format!("Bearer {}", key))],
+            None => vec![],
+        };
+        rouille::Request::fake_http(method, url, headers, vec![])
+    }
+
+    #[test]
+    fn it_should_refuse_requests_without_a_valid_key() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+
+        for req in [
+            request("GET", "/", None),
+            request("GET", "/", Some("pdx_0")),
+        ] {
+            match authorize(store.clone(), &req) {
+                Err(res) => {
+                    assert_eq!(res.status_code, 401);
+                    assert!(res
+                        .headers
+                        .iter()
+                        .any(|(name, value)| name == "Content-Type"
+                            && value == "application/problem+json"));
+                }
+                _ => unreachable!(),
+            };
+        }
+        assert!(matches!(
+            authorize(store, &request("GET", "/health", None)),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn it_should_enforce_the_role_of_each_route() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        let reader = issue(store.clone(), "reader");
+        let editor = issue(store.clone(), "editor");
+
+        let status = |method: &str, url: &str, key: &str| match authorize(
+            store.clone(),
+            &request(method, url, Some(key)),
+        ) {
+            Ok(_) => 200,
+            Err(res) => res.status_code,
+        };
+
+        assert_eq!(status("GET", "/25", &reader), 200);
+        assert_eq!(status("DELETE", "/25", &reader), 403);
+        assert_eq!(status("DELETE", "/25", &editor), 200);
+        assert_eq!(status("POST", "/25/restore", &editor), 200);
+        assert_eq!(status("GET", "/audit", &editor), 403);
+    }
+}
@@ -0,0 +1,217 @@
+use super::problem;
+use std::time::Duration;
+
+/// Headers which browsers hide from scripts unless told otherwise.
+const EXPOSED_HEADERS: &[&str] = &[
+    "ETag",
+    "Retry-After",
+    "RateLimit-Limit",
+    "RateLimit-Remaining",
+    "RateLimit-Reset",
+    "WWW-Authenticate",
+];
+
+/// Which other origins browsers let call the API, and how.
+pub struct Cors {
+    /// Origins such as `https://pokedex.example`, `*` allowing any of them.
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    pub headers: Vec<String>,
+    /// How long browsers may cache the answer to a preflight request.
+    pub max_age: Duration,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self {
+            origins: vec![],
+            methods: ["GET", "POST", "DELETE"].map(String::from).to_vec(),
+            headers: ["Authorization", "Content-Type", "If-Match", "If-None-Match"]
+                .map(String::from)
+                .to_vec(),
+            max_age: Duration::from_secs(600),
+        }
+    }
+}
+
+impl Cors {
+    /// Answers a preflight request, which is not one when `None` is returned.
+    pub fn preflight(&self, req: &rouille::Request) -> Option<rouille::Response> {
+        let method = match (req.method(), req.header("Access-Control-Request-Method")) {
+            ("OPTIONS", Some(method)) => method,
+            _ => return None,
+        };
+
+        let headers = req
+            .header("Access-Control-Request-Headers")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .collect::<Vec<&str>>();
+
+        let allowed = self.methods.iter().any(|allowed| allowed == method)
+            && headers.iter().all(|header| {
+                self.headers
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(header))
+            });
+
+        let res = match (self.allowed_origin(req), allowed) {
+            (Some(origin), true) => rouille::Response::empty_204()
+                .with_unique_header("Access-Control-Allow-Origin", origin)
+                .with_unique_header("Access-Control-Allow-Methods", self.methods.join(", "))
+                .with_unique_header("Access-Control-Allow-Headers", self.headers.join(", "))
+                .with_unique_header("Access-Control-Max-Age", self.max_age.as_secs().to_string()),
+            _ => problem::response(
+                403,
+                "Forbidden",
+                "The origin, method or headers of the request are not allowed",
+            ),
+        };
+
+        Some(res.with_unique_header("Vary", "Origin"))
+    }
+
+    /// Lets the origin of a request read its response, whatever its status.
+    pub fn apply(&self, req: &rouille::Request, res: rouille::Response) -> rouille::Response {
+        let res = res.with_unique_header("Vary", "Origin");
+
+        match self.allowed_origin(req) {
+            Some(origin) => res
+                .with_unique_header("Access-Control-Allow-Origin", origin)
+                .with_unique_header("Access-Control-Expose-Headers", EXPOSED_HEADERS.join(", ")),
+            None => res,
+        }
+    }
+
+    fn allowed_origin(&self, req: &rouille::Request) -> Option<String> {
+        let origin = req.header("Origin")?;
+
+        if self
+            .origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+        {
+            Some(String::from(origin))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors() -> Cors {
+        Cors {
+            origins: vec![String::from("https://pokedex.example")],
+            ..Cors::default()
+        }
+    }
+
+    fn request(method: &str, headers: &[(&str, &str)]) -> rouille::Request {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (String::from(*name), String::from(*value)))
+            .collect();
+        rouille::Request::fake_http(method, "/25", headers, vec![])
+    }
+
+    fn header<'a>(res: &'a rouille::Response, name: &str) -> Option<&'a str> {
+        res.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    #[test]
+    fn it_should_answer_the_preflight_requests_it_allows() {
+        let req = request(
+            "OPTIONS",
+            &[
+                ("Origin", "https://pokedex.example"),
+                ("Access-Control-Request-Method", "DELETE"),
+                ("Access-Control-Request-Headers", "authorization, if-match"),
+            ],
+        );
+
+        match cors().preflight(&req) {
+            Some(res) => {
+                assert_eq!(res.status_code, 204);
+                assert_eq!(
+                    header(&res, "Access-Control-Allow-Origin"),
+                    Some("https://pokedex.example")
+                );
+                assert_eq!(
+                    header(&res, "Access-Control-Allow-Methods"),
+                    Some("GET, POST, DELETE")
+                );
+                assert_eq!(header(&res, "Access-Control-Max-Age"), Some("600"));
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_refuse_the_preflight_requests_it_does_not_allow() {
+        for headers in [
+            [
+                ("Origin", "https://evil.example"),
+                ("Access-Control-Request-Method", "GET"),
+                ("Access-Control-Request-Headers", ""),
+            ],
+            [
+                ("Origin", "https://pokedex.example"),
+                ("Access-Control-Request-Method", "PUT"),
+                ("Access-Control-Request-Headers", ""),
+            ],
+            [
+                ("Origin", "https://pokedex.example"),
+                ("Access-Control-Request-Method", "GET"),
+                ("Access-Control-Request-Headers", "X-Actor"),
+            ],
+        ] {
+            match cors().preflight(&request("OPTIONS", &headers)) {
+                Some(res) => {
+                    assert_eq!(res.status_code, 403);
+                    assert_eq!(header(&res, "Access-Control-Allow-Origin"), None);
+                }
+                _ => unreachable!(),
+            };
+        }
+        assert!(cors().preflight(&request("GET", &[])).is_none());
+    }
+
+    #[test]
+    fn it_should_let_allowed_origins_read_any_response() {
+        let req = request("GET", &[("Origin", "https://pokedex.example")]);
+
+        let res = cors().apply(&req, rouille::Response::empty_404());
+
+        assert_eq!(
+            header(&res, "Access-Control-Allow-Origin"),
+            Some("https://pokedex.example")
+        );
+        assert_eq!(header(&res, "Vary"), Some("Origin"));
+        assert!(header(&res, "Access-Control-Expose-Headers").is_some_and(|h| h.contains("ETag")));
+    }
+
+    #[test]
+    fn it_should_only_let_the_listed_origins_read_responses() {
+        let req = request("GET", &[("Origin", "https://evil.example")]);
+        let res = cors().apply(&req, rouille::Response::text(""));
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), None);
+
+        let any = Cors {
+            origins: vec![String::from("*")],
+            ..Cors::default()
+        };
+        let res = any.apply(&req, rouille::Response::text(""));
+        assert_eq!(
+            header(&res, "Access-Control-Allow-Origin"),
+            Some("https://evil.example")
+        );
+    }
+}
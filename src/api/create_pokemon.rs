@@ -1,4 +1,4 @@
-use crate::api::Status;
+use crate::api::{etag, Status};
 use crate::domain::create_pokemon::{self, Error};
 use crate::repositories::Repository;
 use serde::{Deserialize, Serialize};
@@ -18,7 +18,9 @@ struct Response {
     types: Vec<String>,
 }
 
+/// `If-None-Match: *` turns the Pokemon already existing into a failed precondition.
 pub fn serve(repo: Arc<dyn Repository>, req: &rouille::Request) -> rouille::Response {
+    let none_match_any = etag::none_match_any(req);
     let req = match rouille::input::json_input::<Request>(req) {
         Ok(req) => create_pokemon::Request {
             number: req.number,
@@ -33,12 +35,17 @@ pub fn serve(repo: Arc<dyn Repository>, req: &rouille::Request) -> rouille::Resp
             number,
             name,
             types,
+            version,
         }) => rouille::Response::json(&Response {
             number,
             name,
             types,
-        }),
+        })
+        .with_unique_header("ETag", etag::of(version)),
         Err(Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(Error::Conflict) if none_match_any => {
+            rouille::Response::from(Status::PreconditionFailed)
+        }
         Err(Error::Conflict) => rouille::Response::from(Status::Conflict),
         Err(Error::Unknown) => rouille::Response::from(Status::InternalServerError),
     }
@@ -1,13 +1,27 @@
-use super::Status;
+use super::{etag, Status};
 use crate::{domain::delete_pokemon, repositories::Repository};
 use std::sync::Arc;
 
-pub fn serve(repo: Arc<dyn Repository>, number: u16) -> rouille::Response {
-    let req = delete_pokemon::Request { number };
+/// Only deletes the Pokemon while it is still at one of the versions given by `If-Match`, if any,
+/// which then also fails when there is no Pokemon to delete.
+pub fn serve(repo: Arc<dyn Repository>, number: u16, req: &rouille::Request) -> rouille::Response {
+    let versions = match etag::if_match(req) {
+        Ok(versions) => versions,
+        _ => return rouille::Response::from(Status::PreconditionFailed),
+    };
+
+    let has_if_match = etag::has_if_match(req);
+    let req = delete_pokemon::Request { number, versions };
     match delete_pokemon::execute(repo, req) {
         Ok(()) => rouille::Response::from(Status::Ok),
         Err(delete_pokemon::Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(delete_pokemon::Error::NotFound) if has_if_match => {
+            rouille::Response::from(Status::PreconditionFailed)
+        }
         Err(delete_pokemon::Error::NotFound) => rouille::Response::from(Status::NotFound),
+        Err(delete_pokemon::Error::VersionMismatch) => {
+            rouille::Response::from(Status::PreconditionFailed)
+        }
         Err(delete_pokemon::Error::Unknown) => rouille::Response::from(Status::InternalServerError),
     }
 }
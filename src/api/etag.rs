@@ -0,0 +1,87 @@
+//! Entity tags are the versions of the Pokemons, quoted as HTTP requires.
+
+pub fn of(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// The versions an `If-Match` header requires the Pokemon to be at one of, `None` meaning any
+/// version.
+///
+/// Weak tags are skipped rather than rejected, since writes compare tags strongly and they can
+/// then never match.
+pub fn if_match(req: &rouille::Request) -> Result<Option<Vec<u64>>, ()> {
+    let tags = match req.header("If-Match").map(str::trim) {
+        None | Some("*") => return Ok(None),
+        Some(tags) => tags,
+    };
+
+    let mut versions = vec![];
+    for tag in tags.split(',').map(str::trim) {
+        match (tag.starts_with("W/"), parse(tag)) {
+            (true, _) => {}
+            (false, Some(version)) => versions.push(version),
+            (false, None) => return Err(()),
+        }
+    }
+    Ok(Some(versions))
+}
+
+/// Whether the request carries an `If-Match` header, which fails on a missing Pokemon.
+pub fn has_if_match(req: &rouille::Request) -> bool {
+    req.header("If-Match").is_some()
+}
+
+/// Whether an `If-None-Match: *` header requires that the Pokemons created do not exist yet.
+pub fn none_match_any(req: &rouille::Request) -> bool {
+    req.header("If-None-Match").map(str::trim) == Some("*")
+}
+
+/// Whether an `If-None-Match` header lists the version, weak tags included.
+pub fn none_match(req: &rouille::Request, version: u64) -> bool {
+    match req.header("If-None-Match") {
+        Some(tags) => tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || parse(tag.strip_prefix("W/").unwrap_or(tag)) == Some(version)),
+        None => false,
+    }
+}
+
+fn parse(tag: &str) -> Option<u64> {
+    tag.strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .and_then(|version| version.parse::<u64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, value: &str) -> rouille::Request {
+        rouille::Request::fake_http(
+            "GET",
+            "/25",
+            vec![(String::from(name), String::from(value))],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn it_should_only_match_the_strong_tags_listed_in_if_match() {
+        assert_eq!(if_match(&request("If-Match", "\"3\"")), Ok(Some(vec![3])));
+        assert_eq!(if_match(&request("If-Match", "*")), Ok(None));
+        assert_eq!(if_match(&request("If-Match", "W/\"3\"")), Ok(Some(vec![])));
+        assert_eq!(
+            if_match(&request("If-Match", "\"3\", W/\"4\", \"5\"")),
+            Ok(Some(vec![3, 5]))
+        );
+        assert_eq!(if_match(&request("If-Match", "3")), Err(()));
+    }
+
+    #[test]
+    fn it_should_match_any_listed_tag_in_if_none_match() {
+        assert!(none_match(&request("If-None-Match", "\"2\", W/\"3\""), 3));
+        assert!(none_match(&request("If-None-Match", "*"), 3));
+        assert!(!none_match(&request("If-None-Match", "\"2\""), 3));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::api::Status;
+use crate::domain::export_pokemons::{self, Error};
+use crate::formats::{Format, TypesLayout};
+use crate::repositories::Repository;
+use std::sync::Arc;
+
+/// Downloads the Pokedex as `?format=csv|json|ndjson`, JSON by default, with the types
+/// `?types=flattened|array`.
+pub fn serve(repo: Arc<dyn Repository>, req: &rouille::Request) -> rouille::Response {
+    let format = match req.get_param("format") {
+        Some(format) => Format::try_from(format.as_str()),
+        None => Ok(Format::Json),
+    };
+
+    let layout = match req.get_param("types") {
+        Some(layout) => TypesLayout::try_from(layout.as_str()).map(Some),
+        None => Ok(None),
+    };
+
+    let req = match (format, layout) {
+        (Ok(format), Ok(layout)) => export_pokemons::Request { format, layout },
+        _ => return rouille::Response::from(Status::BadRequest),
+    };
+
+    let format = req.format;
+
+    match export_pokemons::execute(repo, req) {
+        Ok(exporter) => rouille::Response {
+            status_code: 200,
+            headers: vec![
+                ("Content-Type".into(), format.content_type().into()),
+                (
+                    "Content-Disposition".into(),
+                    format!("attachment; filename=\"pokedex.{}\"", format.extension()).into(),
+                ),
+            ],
+            data: rouille::ResponseBody::from_reader(exporter),
+            upgrade: None,
+        },
+        Err(Error::Unknown) => rouille::Response::from(Status::InternalServerError),
+    }
+}
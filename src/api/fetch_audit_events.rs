@@ -0,0 +1,62 @@
+use crate::api::Status;
+use crate::domain::fetch_audit_events;
+use crate::repositories::audit_log::AuditLog;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Response {
+    at: DateTime<Utc>,
+    actor: String,
+    transport: String,
+    operation: String,
+    number: u16,
+    before: Option<Pokemon>,
+    after: Option<Pokemon>,
+}
+
+#[derive(Serialize)]
+struct Pokemon {
+    number: u16,
+    name: String,
+    types: Vec<String>,
+}
+
+/// Lists the changes made to the Pokedex, oldest first, or only the ones about `?number=`.
+pub fn serve(log: Arc<dyn AuditLog>, req: &rouille::Request) -> rouille::Response {
+    let number = match req.get_param("number").map(|number| number.parse::<u16>()) {
+        Some(Ok(number)) => Some(number),
+        Some(Err(_)) => return rouille::Response::from(Status::BadRequest),
+        None => None,
+    };
+
+    match fetch_audit_events::execute(log, fetch_audit_events::Request { number }) {
+        Ok(events) => rouille::Response::json(
+            &events
+                .into_iter()
+                .map(|event| Response {
+                    at: event.at,
+                    actor: event.actor,
+                    transport: event.transport,
+                    operation: event.operation,
+                    number: event.number,
+                    before: event.before.map(|p| Pokemon {
+                        number: p.number,
+                        name: p.name,
+                        types: p.types,
+                    }),
+                    after: event.after.map(|p| Pokemon {
+                        number: p.number,
+                        name: p.name,
+                        types: p.types,
+                    }),
+                })
+                .collect::<Vec<Response>>(),
+        ),
+        Err(fetch_audit_events::Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(fetch_audit_events::Error::Unknown) => {
+            rouille::Response::from(Status::InternalServerError)
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::api::Status;
+use super::{etag, Status};
 use crate::{domain::fetch_pokemon, repositories::Repository};
 use serde::Serialize;
 use std::sync::Arc;
@@ -10,18 +10,27 @@ struct Response {
     types: Vec<String>,
 }
 
-pub fn serve(repo: Arc<dyn Repository>, number: u16) -> rouille::Response {
+/// Tags the Pokemon with its version, answering 304 when `If-None-Match` already lists it.
+pub fn serve(repo: Arc<dyn Repository>, number: u16, req: &rouille::Request) -> rouille::Response {
+    let none_match = |version| etag::none_match(req, version);
+
     let req = fetch_pokemon::Request { number };
     match fetch_pokemon::execute(repo, req) {
+        Ok(fetch_pokemon::Response { version, .. }) if none_match(version) => {
+            rouille::Response::from(Status::NotModified)
+                .with_unique_header("ETag", etag::of(version))
+        }
         Ok(fetch_pokemon::Response {
             number,
             name,
             types,
+            version,
         }) => rouille::Response::json(&Response {
             number,
             name,
             types,
-        }),
+        })
+        .with_unique_header("ETag", etag::of(version)),
         Err(fetch_pokemon::Error::BadRequest) => rouille::Response::from(Status::BadRequest),
         Err(fetch_pokemon::Error::NotFound) => rouille::Response::from(Status::NotFound),
         Err(fetch_pokemon::Error::Unknown) => rouille::Response::from(Status::InternalServerError),
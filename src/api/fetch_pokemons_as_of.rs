@@ -0,0 +1,30 @@
+use crate::api::Status;
+use crate::domain::fetch_pokemons_as_of;
+use crate::repositories::event_sourced_repository::History;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Response {
+    number: u16,
+    name: String,
+    types: Vec<String>,
+}
+
+pub fn serve(history: Arc<dyn History>, as_of: String) -> rouille::Response {
+    match fetch_pokemons_as_of::execute(history, fetch_pokemons_as_of::Request { as_of }) {
+        Ok(res) => rouille::Response::json(
+            &res.into_iter()
+                .map(|p| Response {
+                    number: p.number,
+                    name: p.name,
+                    types: p.types,
+                })
+                .collect::<Vec<Response>>(),
+        ),
+        Err(fetch_pokemons_as_of::Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(fetch_pokemons_as_of::Error::Unknown) => {
+            rouille::Response::from(Status::InternalServerError)
+        }
+    }
+}
@@ -1,10 +1,29 @@
+use crate::domain::check_health;
+use crate::repositories::Repository;
 use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the outcome of a readiness check is reused, so that probes, which anyone may send,
+/// cannot load the backend.
+const READINESS_TTL: Duration = Duration::from_secs(5);
 
 #[derive(Serialize)]
 struct Response {
     message: String,
 }
 
+#[derive(Serialize)]
+struct Probe {
+    status: &'static str,
+    version: &'static str,
+    uptime_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backend: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
 pub fn serve() -> rouille::Response {
     //rouille::Response::text("Gotta catch them all!")
 
@@ -12,3 +31,160 @@ pub fn serve() -> rouille::Response {
         message: String::from("Gotta catch them all!"),
     })
 }
+
+/// Answers as long as the server is up, whatever the state of the backend, so that it is only
+/// restarted when it stops answering.
+pub fn live(started_at: Instant) -> rouille::Response {
+    rouille::Response::json(&Probe {
+        status: "live",
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_seconds: started_at.elapsed().as_secs(),
+        backend: None,
+        detail: None,
+    })
+}
+
+/// The last readiness check of a server's backend, which the probes answered shortly after reuse.
+#[derive(Default)]
+pub struct Readiness {
+    last: Mutex<Option<(Instant, Check)>>,
+}
+
+#[derive(Clone)]
+struct Check {
+    status_code: u16,
+    status: &'static str,
+    backend: String,
+    detail: Option<String>,
+}
+
+impl Readiness {
+    /// The lock is held while the backend is checked, so that concurrent probes wait for the same
+    /// check rather than each sending their own.
+    fn check(&self, repo: Arc<dyn Repository>) -> Check {
+        let mut last = match self.last.lock() {
+            Ok(last) => last,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some((checked_at, check)) = last.as_ref() {
+            if checked_at.elapsed() < READINESS_TTL {
+                return check.clone();
+            }
+        }
+
+        let check = match check_health::execute(repo) {
+            Ok(res) => Check {
+                status_code: 200,
+                status: "ready",
+                backend: res.backend,
+                detail: None,
+            },
+            Err(check_health::Error::Unavailable { backend, detail }) => Check {
+                status_code: 503,
+                status: "unavailable",
+                backend,
+                detail: Some(detail),
+            },
+        };
+        *last = Some((Instant::now(), check.clone()));
+        check
+    }
+}
+
+/// Answers with a 503 when the backend cannot serve requests, so that no traffic is sent to the
+/// server meanwhile. Why it cannot is only told when `disclose` is set, since the probe is open to
+/// callers without a key.
+pub fn ready(
+    repo: Arc<dyn Repository>,
+    started_at: Instant,
+    readiness: &Readiness,
+    disclose: bool,
+) -> rouille::Response {
+    let check = readiness.check(repo);
+
+    rouille::Response::json(&Probe {
+        status: check.status,
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_seconds: started_at.elapsed().as_secs(),
+        backend: Some(check.backend),
+        detail: check.detail.filter(|_| disclose),
+    })
+    .with_status_code(check.status_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+    use std::io::Read;
+
+    fn body(res: rouille::Response) -> serde_json::Value {
+        let mut body = String::new();
+        res.data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn it_should_not_be_ready_when_the_backend_cannot_serve_requests() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+
+        let res = ready(repo, Instant::now(), &Readiness::default(), true);
+
+        assert_eq!(res.status_code, 503);
+        let body = body(res);
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["backend"], "memory");
+        assert!(body["detail"].is_string());
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn it_should_be_ready_when_the_backend_can_serve_requests() {
+        let repo = Arc::new(InMemoryRepository::new());
+
+        let res = ready(repo, Instant::now(), &Readiness::default(), true);
+
+        assert_eq!(res.status_code, 200);
+        let body = body(res);
+        assert_eq!(body["status"], "ready");
+        assert!(body.get("detail").is_none());
+        assert!(body["uptime_seconds"].is_u64());
+    }
+
+    #[test]
+    fn it_should_reuse_the_last_check_for_a_few_seconds() {
+        let readiness = Readiness::default();
+        ready(
+            Arc::new(InMemoryRepository::new()),
+            Instant::now(),
+            &readiness,
+            true,
+        );
+
+        let res = ready(
+            Arc::new(InMemoryRepository::new().with_error()),
+            Instant::now(),
+            &readiness,
+            true,
+        );
+
+        assert_eq!(res.status_code, 200);
+    }
+
+    #[test]
+    fn it_should_not_tell_why_the_backend_is_unavailable_unless_disclosed() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+
+        let res = ready(repo, Instant::now(), &Readiness::default(), false);
+
+        assert_eq!(res.status_code, 503);
+        let body = body(res);
+        assert_eq!(body["status"], "unavailable");
+        assert!(body.get("detail").is_none());
+    }
+}
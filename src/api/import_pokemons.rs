@@ -0,0 +1,101 @@
+use crate::api::{etag, Status};
+use crate::domain::import_pokemons::{self, ConflictMode, Error, RowError};
+use crate::formats::Format;
+use crate::repositories::Repository;
+use serde::Serialize;
+use std::io::Read;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Response {
+    imported: Vec<u16>,
+    skipped: Vec<u16>,
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    errors: Vec<RowErrorResponse>,
+}
+
+#[derive(Serialize)]
+struct RowErrorResponse {
+    line: usize,
+    message: String,
+}
+
+/// Imports the file sent as the request body, in the format given by `?format=csv|json` or else
+/// by the `Content-Type` header. `?dry_run=true` only validates it, and `?on_conflict=skip` skips
+/// the Pokemons which already exist instead of aborting. `If-None-Match: *` requires that none of
+/// them exists, which cannot be combined with skipping them.
+pub fn serve(repo: Arc<dyn Repository>, req: &rouille::Request) -> rouille::Response {
+    let none_match_any = etag::none_match_any(req);
+
+    let format = match req.get_param("format") {
+        Some(format) => Format::try_from(format.as_str()),
+        None => match req.header("Content-Type") {
+            Some(content_type) if content_type.starts_with("text/csv") => Ok(Format::Csv),
+            _ => Ok(Format::Json),
+        },
+    };
+
+    let on_conflict = match (req.get_param("on_conflict").as_deref(), none_match_any) {
+        (Some("skip"), false) => Ok(ConflictMode::Skip),
+        (Some("abort") | None, _) => Ok(ConflictMode::Abort),
+        _ => Err(()),
+    };
+
+    let dry_run = match req.get_param("dry_run").as_deref() {
+        Some("true") => Ok(true),
+        Some("false") | None => Ok(false),
+        _ => Err(()),
+    };
+
+    let mut content = String::new();
+    let body_read = match req.data() {
+        Some(mut data) => data.read_to_string(&mut content).is_ok(),
+        None => false,
+    };
+
+    let req = match (format, on_conflict, dry_run, body_read) {
+        (Ok(format), Ok(on_conflict), Ok(dry_run), true) => import_pokemons::Request {
+            format,
+            content,
+            dry_run,
+            on_conflict,
+        },
+        _ => return rouille::Response::from(Status::BadRequest),
+    };
+
+    let dry_run = req.dry_run;
+
+    match import_pokemons::execute(repo, req) {
+        Ok(import_pokemons::Response { imported, skipped }) => rouille::Response::json(&Response {
+            imported,
+            skipped,
+            dry_run,
+        }),
+        Err(Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(Error::InvalidRows(errors)) => {
+            rouille::Response::json(&to_error_response(errors)).with_status_code(400)
+        }
+        Err(Error::Conflict(conflicts)) => rouille::Response::json(&to_error_response(conflicts))
+            .with_status_code(match none_match_any {
+                true => 412,
+                false => 409,
+            }),
+        Err(Error::Unknown) => rouille::Response::from(Status::InternalServerError),
+    }
+}
+
+fn to_error_response(errors: Vec<RowError>) -> ErrorResponse {
+    ErrorResponse {
+        errors: errors
+            .into_iter()
+            .map(|error| RowErrorResponse {
+                line: error.line,
+                message: error.message,
+            })
+            .collect(),
+    }
+}
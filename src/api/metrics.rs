@@ -0,0 +1,18 @@
+use crate::{domain::fetch_all_pokemons, metrics::Metrics, repositories::Repository};
+use std::sync::Arc;
+
+/// The Pokemon count is left out when the repository cannot be read, rather than failing the
+/// scrape along with every other metric. It is only counted again once it gets old, reading the
+/// whole Pokedex being slow, and rate limited on Airtable.
+pub fn serve(metrics: Arc<Metrics>, repo: Arc<dyn Repository>) -> rouille::Response {
+    let pokemons = metrics.pokemons(|| {
+        fetch_all_pokemons::execute(repo)
+            .ok()
+            .map(|pokemons| pokemons.len())
+    });
+
+    rouille::Response::from_data(
+        "text/plain; version=0.0.4; charset=utf-8",
+        metrics.render(pokemons),
+    )
+}
@@ -1,17 +1,47 @@
-use crate::repositories::Repository;
+use crate::logging::{self, Logger};
+use crate::metrics::Metrics;
+use crate::repositories::{
+    api_key_store::ApiKeyStore,
+    audit_log::{Actor, AuditLog, Transport},
+    audited_repository::AuditedRepository,
+    event_sourced_repository::History,
+    Repository,
+};
+use rand::RngCore;
+use serde_json::Value;
+use shutdown::InFlight;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+pub use cors::Cors;
+pub use rate_limit::{Quota, RateLimiter};
+pub use shutdown::Shutdown;
+
+mod auth;
+mod cors;
 mod create_pokemon;
 mod delete_pokemon;
+mod etag;
+mod export_pokemons;
 mod fetch_all_pokemons;
+mod fetch_audit_events;
 mod fetch_pokemon;
+mod fetch_pokemons_as_of;
 mod health;
+mod import_pokemons;
+mod metrics;
+mod problem;
+mod rate_limit;
+mod restore_pokemon;
+mod shutdown;
 
 enum Status {
     Ok,
+    NotModified,
     BadRequest,
     NotFound,
     Conflict,
+    PreconditionFailed,
     InternalServerError,
 }
 
@@ -19,9 +49,11 @@ impl From<Status> for rouille::Response {
     fn from(status: Status) -> Self {
         let status_code = match status {
             Status::Ok => 200,
+            Status::NotModified => 304,
             Status::BadRequest => 400,
             Status::NotFound => 404,
             Status::Conflict => 409,
+            Status::PreconditionFailed => 412,
             Status::InternalServerError => 500,
         };
         Self {
@@ -33,27 +65,487 @@ impl From<Status> for rouille::Response {
     }
 }
 
-pub fn serve(url: &str, repo: Arc<dyn Repository>) {
-    rouille::start_server(url, move |req| {
-        router!(req,
-            (GET) (/health) => {
-                health::serve()
-            },
-            (POST) (/) => {
-                create_pokemon::serve(repo.clone(), req) //Clones only the ARC pointer
-            },
-            (GET) (/) => {
-                fetch_all_pokemons::serve(repo.clone())
-            },
-            (GET) (/{number: u16}) => {
-                fetch_pokemon::serve(repo.clone(), number)
-            },
-            (DELETE) (/{number: u16}) => {
-                delete_pokemon::serve(repo.clone(), number)
+/// What the server does besides serving the Pokedex.
+#[derive(Default)]
+pub struct Options {
+    /// Where the writes are recorded, `GET /audit` being unavailable without it.
+    pub audit: Option<Arc<dyn AuditLog>>,
+    /// Where `GET /?as_of=` reads past versions of the Pokedex, which it refuses without one.
+    pub history: Option<Arc<dyn History>>,
+    /// The keys requests are authenticated with, every request being let through without them.
+    pub keys: Option<Arc<dyn ApiKeyStore>>,
+    /// How many requests each client may send, checked right after the API key.
+    pub rate_limiter: Option<RateLimiter>,
+    /// Which other origins browsers let call the API, none without it.
+    pub cors: Option<Cors>,
+    /// Where a line is logged per request and per use case, nothing being logged without it.
+    pub logger: Option<Arc<Logger>>,
+    /// What `GET /metrics` exposes, which is not served without it.
+    pub metrics: Option<Arc<Metrics>>,
+    /// When to stop serving, the server running until killed without it.
+    pub shutdown: Option<Shutdown>,
+}
+
+/// Serves the Pokedex until shut down, failing when requests were still being served at the
+/// deadline.
+pub fn serve(url: &str, repo: Arc<dyn Repository>, mut options: Options) -> Result<(), ()> {
+    let up_since = Instant::now();
+    let readiness = health::Readiness::default();
+    let shutdown = options.shutdown.take();
+    let logger = options.logger.clone();
+    let in_flight = InFlight::default();
+    let serving = in_flight.clone();
+
+    let server = match rouille::Server::new(url, move |req| {
+        serving
+            .enter()
+            .until_sent(respond(req, &repo, &options, up_since, &readiness))
+    }) {
+        Ok(server) => server,
+        Err(error) => panic!("Could not listen on {}: {}", url, error),
+    };
+
+    let shutdown = match shutdown {
+        Some(shutdown) => shutdown,
+        None => {
+            server.run();
+            return Ok(());
+        }
+    };
+
+    while !shutdown.is_requested() {
+        server.poll_timeout(Duration::from_millis(100));
+    }
+
+    // The connections accepted meanwhile are served, and dropping the server closes the listening
+    // socket so that no other one is.
+    server.poll();
+    drop(server);
+    if let Some(logger) = &logger {
+        logger.log(
+            "info",
+            "shutting down",
+            vec![("in_flight", Value::from(in_flight.count()))],
+        );
+    }
+
+    // The repository is closed along with the server once the last request is served.
+    let abandoned = in_flight.drain(shutdown.deadline);
+    if let Some(logger) = &logger {
+        logger.log(
+            if abandoned > 0 { "error" } else { "info" },
+            "shut down",
+            vec![("abandoned", Value::from(abandoned))],
+        );
+        logger.flush();
+    }
+
+    match abandoned {
+        0 => Ok(()),
+        _ => Err(()),
+    }
+}
+
+/// Serves a request on behalf of its request id, which the response carries, logging and
+/// measuring it.
+fn respond(
+    req: &rouille::Request,
+    repo: &Arc<dyn Repository>,
+    options: &Options,
+    up_since: Instant,
+    readiness: &health::Readiness,
+) -> rouille::Response {
+    let request_id = request_id(req);
+    let started_at = Instant::now();
+
+    let res = logging::with_request_id(&request_id, || {
+        let res = match &options.cors {
+            // Browsers send preflight requests without credentials, so they are answered first.
+            Some(cors) => match cors.preflight(req) {
+                Some(res) => res,
+                None => cors.apply(req, handle(req, repo, options, up_since, readiness)),
             },
-            _ => {
-                rouille::Response::from(Status::NotFound)
-            }
-        )
+            None => handle(req, repo, options, up_since, readiness),
+        };
+        let duration = started_at.elapsed();
+
+        if let Some(metrics) = &options.metrics {
+            metrics.observe_request(route(req), method(req), res.status_code, duration);
+        }
+
+        if let Some(logger) = &options.logger {
+            logger.log(
+                if res.status_code >= 500 {
+                    "error"
+                } else {
+                    "info"
+                },
+                "request",
+                vec![
+                    ("method", Value::from(req.method())),
+                    ("path", Value::from(req.url())),
+                    ("status", Value::from(res.status_code)),
+                    ("duration_ms", logging::millis(duration)),
+                ],
+            );
+        }
+        res
     });
+
+    res.with_unique_header("X-Request-Id", request_id)
+}
+
+/// The `X-Request-Id` the request carries, so that it can be followed across services, or else a
+/// new one.
+fn request_id(req: &rouille::Request) -> String {
+    match req.header("X-Request-Id") {
+        Some(id) if (1..=128).contains(&id.len()) && id.chars().all(|c| c.is_ascii_graphic()) => {
+            String::from(id)
+        }
+        _ => {
+            let mut bytes = [0u8; 8];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+    }
+}
+
+/// The route a request matches, which labels its metrics rather than its path, so that there is
+/// a bounded number of them.
+fn route(req: &rouille::Request) -> &'static str {
+    let url = req.url();
+    let segments = url
+        .trim_start_matches('/')
+        .split('/')
+        .collect::<Vec<&str>>();
+
+    match segments[..] {
+        [""] => "/",
+        ["health"] => "/health",
+        ["health", "live"] => "/health/live",
+        ["health", "ready"] => "/health/ready",
+        ["metrics"] => "/metrics",
+        ["export"] => "/export",
+        ["import"] => "/import",
+        ["audit"] => "/audit",
+        [number] if number.parse::<u16>().is_ok() => "/{number}",
+        [number, "restore"] if number.parse::<u16>().is_ok() => "/{number}/restore",
+        _ => "unmatched",
+    }
+}
+
+/// The method of a request, which labels its metrics along with its route, any method the API does
+/// not serve being labelled `other`.
+fn method(req: &rouille::Request) -> &'static str {
+    match req.method() {
+        "GET" => "GET",
+        "HEAD" => "HEAD",
+        "POST" => "POST",
+        "DELETE" => "DELETE",
+        "OPTIONS" => "OPTIONS",
+        _ => "other",
+    }
+}
+
+/// Times a use case, the repository calls it makes being logged on their own when instrumented.
+fn use_case(
+    options: &Options,
+    name: &str,
+    serve: impl FnOnce() -> rouille::Response,
+) -> rouille::Response {
+    let started_at = Instant::now();
+    let res = serve();
+
+    if let Some(logger) = &options.logger {
+        logger.log(
+            "info",
+            "span",
+            vec![
+                ("kind", Value::from("use_case")),
+                ("name", Value::from(name)),
+                ("status", Value::from(res.status_code)),
+                ("duration_ms", logging::millis(started_at.elapsed())),
+            ],
+        );
+    }
+    res
+}
+
+fn handle(
+    req: &rouille::Request,
+    repo: &Arc<dyn Repository>,
+    options: &Options,
+    up_since: Instant,
+    readiness: &health::Readiness,
+) -> rouille::Response {
+    let key = match &options.keys {
+        Some(keys) => auth::authorize(keys.clone(), req),
+        None => Ok(None),
+    };
+
+    // Refused requests are counted against their address, so that guessing keys is limited too.
+    let limits = match (&options.rate_limiter, &key) {
+        (Some(limiter), Ok(key)) => limiter.check(req, key.as_deref()),
+        (Some(limiter), Err(_)) => limiter.check(req, None),
+        (None, _) => Ok(vec![]),
+    };
+    let (key, limits) = match (key, limits) {
+        (_, Err(res)) => return res,
+        (Err(res), Ok(_)) => return res,
+        (Ok(key), Ok(limits)) => (key, limits),
+    };
+    let repo = scoped(repo, options, req, key);
+
+    let mut res = router!(req,
+        (GET) (/health) => {
+            health::serve()
+        },
+        (GET) (/health/live) => {
+            health::live(up_since)
+        },
+        (GET) (/health/ready) => {
+            use_case(options, "check_health", || {
+                health::ready(repo.clone(), up_since, readiness, options.keys.is_none())
+            })
+        },
+        (GET) (/metrics) => {
+            match &options.metrics {
+                Some(metrics) => metrics::serve(metrics.clone(), repo.clone()),
+                None => rouille::Response::from(Status::NotFound),
+            }
+        },
+        (POST) (/) => {
+            use_case(options, "create_pokemon", || create_pokemon::serve(repo.clone(), req)) //Clones only the ARC pointer
+        },
+        (GET) (/) => {
+            match (req.get_param("as_of"), &options.history) {
+                (Some(as_of), Some(history)) => use_case(options, "fetch_pokemons_as_of", || fetch_pokemons_as_of::serve(history.clone(), as_of)),
+                (Some(_), None) => rouille::Response::from(Status::BadRequest),
+                (None, _) => use_case(options, "fetch_all_pokemons", || fetch_all_pokemons::serve(repo.clone())),
+            }
+        },
+        (GET) (/export) => {
+            use_case(options, "export_pokemons", || export_pokemons::serve(repo.clone(), req))
+        },
+        (POST) (/import) => {
+            use_case(options, "import_pokemons", || import_pokemons::serve(repo.clone(), req))
+        },
+        (GET) (/audit) => {
+            match &options.audit {
+                Some(log) => use_case(options, "fetch_audit_events", || fetch_audit_events::serve(log.clone(), req)),
+                None => rouille::Response::from(Status::NotFound),
+            }
+        },
+        (GET) (/{number: u16}) => {
+            use_case(options, "fetch_pokemon", || fetch_pokemon::serve(repo.clone(), number, req))
+        },
+        (DELETE) (/{number: u16}) => {
+            use_case(options, "delete_pokemon", || delete_pokemon::serve(repo.clone(), number, req))
+        },
+        (POST) (/{number: u16}/restore) => {
+            use_case(options, "restore_pokemon", || restore_pokemon::serve(repo.clone(), number, req))
+        },
+        _ => {
+            rouille::Response::from(Status::NotFound)
+        }
+    );
+
+    for (name, value) in limits {
+        res = res.with_unique_header(name, value);
+    }
+    res
+}
+
+/// Attributes the writes made by a request to whoever sent it. That is the name of its API key
+/// when requests are authenticated, and otherwise its address, since anything else it carries
+/// could be made up.
+fn scoped(
+    repo: &Arc<dyn Repository>,
+    options: &Options,
+    req: &rouille::Request,
+    key: Option<String>,
+) -> Arc<dyn Repository> {
+    match &options.audit {
+        Some(log) => {
+            let name = match key {
+                Some(key) => key,
+                None => req.remote_addr().ip().to_string(),
+            };
+            Arc::new(
+                AuditedRepository::new(
+                    repo.clone(),
+                    log.clone(),
+                    Actor {
+                        name,
+                        transport: Transport::Api,
+                    },
+                )
+                .with_logger(options.logger.clone()),
+            )
+        }
+        None => repo.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::logging::{tests::Lines, Format};
+    use crate::repositories::audit_log::InMemoryAuditLog;
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    #[test]
+    fn it_should_attribute_unauthenticated_writes_to_the_address_rather_than_a_claimed_actor() {
+        let log = Arc::new(InMemoryAuditLog::new());
+        let options = Options {
+            audit: Some(log.clone()),
+            ..Options::default()
+        };
+        let req = rouille::Request::fake_http_from(
+            "10.0.0.7:4000".parse().unwrap(),
+            "POST",
+            "/",
+            vec![(String::from("X-Actor"), String::from("misty"))],
+            vec![],
+        );
+        let repo: Arc<dyn Repository> = Arc::new(InMemoryRepository::new());
+
+        for (key, actor) in [(None, "10.0.0.7"), (Some(String::from("brock")), "brock")] {
+            scoped(&repo, &options, &req, key)
+                .insert(
+                    PokemonNumber::pikachu(),
+                    PokemonName::pikachu(),
+                    PokemonTypes::pikachu(),
+                )
+                .ok();
+            repo.delete(PokemonNumber::pikachu()).ok();
+            repo.purge(chrono::Utc::now() + chrono::TimeDelta::seconds(1))
+                .ok();
+
+            let events = log.events(None).unwrap();
+            assert_eq!(events.last().map(|e| e.actor.as_str()), Some(actor));
+        }
+    }
+
+    #[test]
+    fn it_should_label_the_metrics_with_a_bounded_set_of_routes_and_methods() {
+        let req =
+            |method: &str, url: &str| rouille::Request::fake_http(method, url, vec![], vec![]);
+
+        assert_eq!(route(&req("GET", "/25")), "/{number}");
+        assert_eq!(route(&req("GET", "/25/anything")), "unmatched");
+        assert_eq!(method(&req("DELETE", "/25")), "DELETE");
+        assert_eq!(method(&req("BREW", "/25")), "other");
+    }
+
+    #[test]
+    fn it_should_log_every_request_under_the_request_id_its_response_carries() {
+        let lines = Lines::default();
+        let options = Options {
+            logger: Some(Arc::new(Logger::with_sink(
+                Format::Json,
+                Box::new(lines.clone()),
+            ))),
+            ..Options::default()
+        };
+        let repo: Arc<dyn Repository> = Arc::new(InMemoryRepository::new());
+        let header = |res: &rouille::Response| match res
+            .headers
+            .iter()
+            .find(|(name, _)| name == "X-Request-Id")
+        {
+            Some((_, value)) => value.to_string(),
+            None => unreachable!(),
+        };
+
+        let propagated = respond(
+            &rouille::Request::fake_http(
+                "GET",
+                "/25",
+                vec![(String::from("X-Request-Id"), String::from("abc-123"))],
+                vec![],
+            ),
+            &repo,
+            &options,
+            Instant::now(),
+            &health::Readiness::default(),
+        );
+        let generated = respond(
+            &rouille::Request::fake_http("GET", "/", vec![], vec![]),
+            &repo,
+            &options,
+            Instant::now(),
+            &health::Readiness::default(),
+        );
+
+        assert_eq!(header(&propagated), "abc-123");
+        let generated_id = header(&generated);
+        assert_eq!(generated_id.len(), 16);
+        assert!(generated_id.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let requests = lines
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .filter(|line| line["msg"] == "request")
+            .collect::<Vec<Value>>();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["request_id"], "abc-123");
+        assert_eq!(requests[0]["method"], "GET");
+        assert_eq!(requests[0]["path"], "/25");
+        assert_eq!(requests[0]["status"], 404);
+        assert_eq!(requests[1]["request_id"], generated_id.as_str());
+        assert_eq!(requests[1]["status"], 200);
+    }
+
+    #[test]
+    fn it_should_check_the_preconditions_of_writes_against_every_listed_tag() {
+        let repo: Arc<dyn Repository> = Arc::new(InMemoryRepository::new());
+        let options = Options::default();
+        let send = |method: &str, url: &str, header: (&str, &str), body: &str| {
+            respond(
+                &rouille::Request::fake_http(
+                    method,
+                    url,
+                    vec![
+                        (
+                            String::from("Content-Type"),
+                            String::from("application/json"),
+                        ),
+                        (String::from(header.0), String::from(header.1)),
+                    ],
+                    body.as_bytes().to_vec(),
+                ),
+                &repo,
+                &options,
+                Instant::now(),
+                &health::Readiness::default(),
+            )
+            .status_code
+        };
+        let pikachu = r#"{"number":25,"name":"Pikachu","types":["Electric"]}"#;
+
+        assert_eq!(send("DELETE", "/25", ("If-Match", "*"), ""), 412);
+        assert_eq!(send("POST", "/", ("If-None-Match", "*"), pikachu), 200);
+        assert_eq!(send("POST", "/", ("If-None-Match", "*"), pikachu), 412);
+        assert_eq!(send("POST", "/", ("Accept", "*/*"), pikachu), 409);
+        assert_eq!(
+            send(
+                "POST",
+                "/import",
+                ("If-None-Match", "*"),
+                &format!("[{}]", pikachu)
+            ),
+            412
+        );
+        assert_eq!(send("DELETE", "/25", ("If-Match", "\"2\", \"3\""), ""), 412);
+        assert_eq!(send("DELETE", "/25", ("If-Match", "\"2\", \"1\""), ""), 200);
+        assert_eq!(
+            send("POST", "/25/restore", ("If-Match", "\"2\", \"1\""), ""),
+            200
+        );
+        assert_eq!(send("POST", "/4/restore", ("If-Match", "\"1\""), ""), 412);
+        assert_eq!(send("POST", "/4/restore", ("Accept", "*/*"), ""), 404);
+    }
 }
@@ -0,0 +1,22 @@
+use serde::Serialize;
+
+/// Explains why a request was refused, as an RFC 7807 problem.
+#[derive(Serialize)]
+struct Problem {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'static str,
+}
+
+pub fn response(status: u16, title: &'static str, detail: &'static str) -> rouille::Response {
+    rouille::Response::json(&Problem {
+        kind: "about:blank",
+        title,
+        status,
+        detail,
+    })
+    .with_status_code(status)
+    .with_unique_header("Content-Type", "application/problem+json")
+}
@@ -0,0 +1,372 @@
+use super::problem;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Above this many buckets, the least recently used ones are forgotten.
+const MAX_CLIENTS: usize = 10_000;
+
+/// Where the limiter reads the time from, so that tests can move it forward.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Lets a client send `requests` per `period`, all at once or spread over it.
+#[derive(Clone, Copy)]
+pub struct Quota {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    /// Time for a spent request to be given back.
+    fn refill(&self) -> Duration {
+        self.period / self.requests.max(1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Class {
+    Read,
+    Write,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// Where the bucket stands in `Buckets::recency`.
+    used: u64,
+}
+
+/// The buckets along with the order they were last used in, so that the least recently used one
+/// can be forgotten without scanning them all.
+#[derive(Default)]
+struct Buckets {
+    by_client: HashMap<(Class, String), Bucket>,
+    recency: BTreeMap<u64, (Class, String)>,
+    uses: u64,
+}
+
+/// Where a client stands against its quota, as told by the `RateLimit-*` headers.
+struct State {
+    limit: u32,
+    remaining: u32,
+    /// Until the bucket is full again.
+    reset: Duration,
+    /// Until the next request is let through, when it is refused.
+    retry_after: Option<Duration>,
+}
+
+/// Token buckets per client and route class, a client being its authenticated API key or else its
+/// address.
+pub struct RateLimiter {
+    reads: Option<Quota>,
+    writes: Option<Quota>,
+    clock: Arc<dyn Clock>,
+    max_clients: usize,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    /// Either class is unlimited without a quota.
+    pub fn new(reads: Option<Quota>, writes: Option<Quota>) -> Self {
+        Self {
+            reads,
+            writes,
+            clock: Arc::new(SystemClock),
+            max_clients: MAX_CLIENTS,
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// Returns the headers to add to the response, or the response refusing the request. `key` is
+    /// the name of the API key the request was authenticated with, its address counting otherwise,
+    /// so that sending made up keys does not get a client new buckets.
+    pub fn check(
+        &self,
+        req: &rouille::Request,
+        key: Option<&str>,
+    ) -> Result<Vec<(String, String)>, rouille::Response> {
+        let class = match (req.method(), req.url().as_str()) {
+            (_, "/health") | (_, "/health/live") | (_, "/health/ready") => return Ok(vec![]),
+            ("GET", _) | ("HEAD", _) => Class::Read,
+            _ => Class::Write,
+        };
+
+        let client = match key {
+            Some(key) => format!("key:{}", key),
+            None => format!("ip:{}", req.remote_addr().ip()),
+        };
+
+        match self.take(class, client) {
+            Some(state) => match state.retry_after {
+                None => Ok(headers(&state)),
+                Some(retry_after) => {
+                    let mut res = problem::response(
+                        429,
+                        "Too Many Requests",
+                        "The client sent too many requests, see Retry-After",
+                    );
+                    for (name, value) in headers(&state) {
+                        res = res.with_unique_header(name, value);
+                    }
+                    Err(res.with_unique_header("Retry-After", seconds(retry_after).to_string()))
+                }
+            },
+            None => Ok(vec![]),
+        }
+    }
+
+    fn quota(&self, class: Class) -> Option<Quota> {
+        match class {
+            Class::Read => self.reads,
+            Class::Write => self.writes,
+        }
+    }
+
+    /// Spends a token of the client, if it has one left.
+    fn take(&self, class: Class, client: String) -> Option<State> {
+        let quota = self.quota(class)?;
+        let capacity = f64::from(quota.requests);
+        let refill = quota.refill().as_secs_f64();
+        let now = self.clock.now();
+
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let buckets = &mut *buckets;
+        buckets.uses += 1;
+        let used = buckets.uses;
+        let client = (class, client);
+
+        match buckets.by_client.get_mut(&client) {
+            Some(bucket) => {
+                buckets.recency.remove(&bucket.used);
+                bucket.used = used;
+            }
+            None => {
+                if buckets.by_client.len() >= self.max_clients {
+                    if let Some((_, forgotten)) = buckets.recency.pop_first() {
+                        buckets.by_client.remove(&forgotten);
+                    }
+                }
+                buckets.by_client.insert(
+                    client.clone(),
+                    Bucket {
+                        tokens: capacity,
+                        updated_at: now,
+                        used,
+                    },
+                );
+            }
+        }
+        buckets.recency.insert(used, client.clone());
+        let bucket = match buckets.by_client.get_mut(&client) {
+            Some(bucket) => bucket,
+            None => unreachable!(),
+        };
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed / refill).min(capacity);
+        bucket.updated_at = now;
+
+        let retry_after = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) * refill))
+        };
+
+        Some(State {
+            limit: quota.requests,
+            remaining: bucket.tokens.floor() as u32,
+            reset: Duration::from_secs_f64((capacity - bucket.tokens) * refill),
+            retry_after,
+        })
+    }
+}
+
+#[cfg(test)]
+impl RateLimiter {
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    pub fn with_max_clients(self, max_clients: usize) -> Self {
+        Self {
+            max_clients,
+            ..self
+        }
+    }
+}
+
+fn headers(state: &State) -> Vec<(String, String)> {
+    vec![
+        (String::from("RateLimit-Limit"), state.limit.to_string()),
+        (
+            String::from("RateLimit-Remaining"),
+            state.remaining.to_string(),
+        ),
+        (
+            String::from("RateLimit-Reset"),
+            seconds(state.reset).to_string(),
+        ),
+    ]
+}
+
+/// Whole seconds, rounded up so that clients never retry too early.
+fn seconds(duration: Duration) -> u64 {
+    duration.as_secs_f64().ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeClock {
+        now: Mutex<Instant>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self {
+                now: Mutex::new(Instant::now()),
+            }
+        }
+
+        fn advance(&self, duration: Duration) {
+            *self.now.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn limiter(clock: Arc<FakeClock>) -> RateLimiter {
+        RateLimiter::new(
+            Some(Quota {
+                requests: 3,
+                period: Duration::from_secs(60),
+            }),
+            Some(Quota {
+                requests: 1,
+                period: Duration::from_secs(60),
+            }),
+        )
+        .with_clock(clock)
+    }
+
+    fn request(method: &str) -> rouille::Request {
+        rouille::Request::fake_http(method, "/25", vec![], vec![])
+    }
+
+    fn request_from(address: &str) -> rouille::Request {
+        rouille::Request::fake_http_from(address.parse().unwrap(), "GET", "/25", vec![], vec![])
+    }
+
+    fn header<'a>(headers: &'a [(impl AsRef<str>, impl AsRef<str>)], name: &str) -> &'a str {
+        match headers.iter().find(|(key, _)| key.as_ref() == name) {
+            Some((_, value)) => value.as_ref(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_should_let_a_burst_through_then_refuse_until_a_token_is_given_back() {
+        let clock = Arc::new(FakeClock::new());
+        let limiter = limiter(clock.clone());
+
+        for remaining in ["2", "1", "0"] {
+            match limiter.check(&request("GET"), None) {
+                Ok(headers) => {
+                    assert_eq!(header(&headers, "RateLimit-Limit"), "3");
+                    assert_eq!(header(&headers, "RateLimit-Remaining"), remaining);
+                }
+                _ => unreachable!(),
+            };
+        }
+
+        match limiter.check(&request("GET"), None) {
+            Err(res) => {
+                assert_eq!(res.status_code, 429);
+                assert_eq!(header(&res.headers, "Retry-After"), "20");
+                assert_eq!(header(&res.headers, "RateLimit-Remaining"), "0");
+                assert_eq!(header(&res.headers, "RateLimit-Reset"), "60");
+            }
+            _ => unreachable!(),
+        };
+
+        clock.advance(Duration::from_secs(10));
+        assert!(limiter.check(&request("GET"), None).is_err());
+        clock.advance(Duration::from_secs(10));
+        assert!(limiter.check(&request("GET"), None).is_ok());
+    }
+
+    #[test]
+    fn it_should_refill_the_bucket_up_to_its_capacity() {
+        let clock = Arc::new(FakeClock::new());
+        let limiter = limiter(clock.clone());
+        for _ in 0..3 {
+            limiter.check(&request("GET"), None).ok();
+        }
+
+        clock.advance(Duration::from_secs(3600));
+
+        match limiter.check(&request("GET"), None) {
+            Ok(headers) => assert_eq!(header(&headers, "RateLimit-Remaining"), "2"),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_keep_a_bucket_per_client_and_route_class() {
+        let clock = Arc::new(FakeClock::new());
+        let limiter = limiter(clock);
+
+        assert!(limiter.check(&request("DELETE"), Some("misty")).is_ok());
+        assert!(limiter.check(&request("DELETE"), Some("misty")).is_err());
+        assert!(limiter.check(&request("GET"), Some("misty")).is_ok());
+        assert!(limiter.check(&request("DELETE"), Some("brock")).is_ok());
+        assert!(limiter.check(&request("DELETE"), None).is_ok());
+    }
+
+    #[test]
+    fn it_should_not_limit_the_classes_without_a_quota() {
+        let limiter = RateLimiter::new(None, None);
+
+        for _ in 0..100 {
+            match limiter.check(&request("DELETE"), None) {
+                Ok(headers) => assert!(headers.is_empty()),
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    #[test]
+    fn it_should_forget_the_least_recently_used_buckets_above_the_maximum() {
+        let clock = Arc::new(FakeClock::new());
+        let limiter = limiter(clock).with_max_clients(2);
+        for address in ["10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1"] {
+            for _ in 0..3 {
+                limiter.check(&request_from(address), None).ok();
+            }
+        }
+
+        assert_eq!(limiter.buckets.lock().unwrap().by_client.len(), 2);
+        assert!(limiter.check(&request_from("10.0.0.3:1"), None).is_err());
+        assert!(limiter.check(&request_from("10.0.0.1:1"), None).is_ok());
+    }
+}
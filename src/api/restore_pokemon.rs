@@ -0,0 +1,47 @@
+use super::{etag, Status};
+use crate::{domain::restore_pokemon, repositories::Repository};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Response {
+    number: u16,
+    name: String,
+    types: Vec<String>,
+}
+
+/// `If-Match` holds the version the Pokemon was deleted at, which is the last one it was served
+/// with, and then also fails when there is no Pokemon to restore.
+pub fn serve(repo: Arc<dyn Repository>, number: u16, req: &rouille::Request) -> rouille::Response {
+    let versions = match etag::if_match(req) {
+        Ok(versions) => versions,
+        _ => return rouille::Response::from(Status::PreconditionFailed),
+    };
+
+    let has_if_match = etag::has_if_match(req);
+    let req = restore_pokemon::Request { number, versions };
+    match restore_pokemon::execute(repo, req) {
+        Ok(restore_pokemon::Response {
+            number,
+            name,
+            types,
+            version,
+        }) => rouille::Response::json(&Response {
+            number,
+            name,
+            types,
+        })
+        .with_unique_header("ETag", etag::of(version)),
+        Err(restore_pokemon::Error::BadRequest) => rouille::Response::from(Status::BadRequest),
+        Err(restore_pokemon::Error::NotFound) if has_if_match => {
+            rouille::Response::from(Status::PreconditionFailed)
+        }
+        Err(restore_pokemon::Error::NotFound) => rouille::Response::from(Status::NotFound),
+        Err(restore_pokemon::Error::VersionMismatch) => {
+            rouille::Response::from(Status::PreconditionFailed)
+        }
+        Err(restore_pokemon::Error::Unknown) => {
+            rouille::Response::from(Status::InternalServerError)
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// When the server stops accepting connections, and how long it then waits for the requests it is
+/// serving.
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    pub deadline: Duration,
+}
+
+impl Shutdown {
+    /// Shuts down on SIGINT and SIGTERM, exiting right away on a second one rather than waiting
+    /// for the requests in flight.
+    pub fn on_signals(deadline: Duration) -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+            // Registered first, so that it only sees the flag set by an earlier signal.
+            signal_hook::flag::register_conditional_shutdown(signal, 1, requested.clone())?;
+            signal_hook::flag::register(signal, requested.clone())?;
+        }
+
+        Ok(Self {
+            requested,
+            deadline,
+        })
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+/// Counts the requests being served.
+#[derive(Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+/// Stops counting a request once dropped, even when serving it panicked.
+pub struct Serving(Arc<AtomicUsize>);
+
+impl Drop for Serving {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Serving {
+    /// Keeps the request counted until the body of `res`, which is only sent once the handler
+    /// returned, was sent whole or given up on.
+    pub fn until_sent(self, mut res: rouille::Response) -> rouille::Response {
+        let (reader, size) = res.data.into_reader_and_size();
+        let reader = Sending {
+            reader,
+            _serving: self,
+        };
+        res.data = match size {
+            Some(size) => rouille::ResponseBody::from_reader_and_size(reader, size),
+            None => rouille::ResponseBody::from_reader(reader),
+        };
+        res
+    }
+}
+
+/// A response body along with the request it answers, which stops being counted once it is
+/// dropped.
+struct Sending<R> {
+    reader: R,
+    _serving: Serving,
+}
+
+impl<R: Read> Read for Sending<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl InFlight {
+    pub fn enter(&self) -> Serving {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Serving(self.0.clone())
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no request is being served or `deadline` elapsed, returning how many requests
+    /// were still being served then.
+    pub fn drain(&self, deadline: Duration) -> usize {
+        let started_at = Instant::now();
+        while self.count() > 0 && started_at.elapsed() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_wait_for_the_requests_being_served() {
+        let in_flight = InFlight::default();
+        let serving = in_flight.enter();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(serving);
+        });
+
+        assert_eq!(in_flight.drain(Duration::from_secs(5)), 0);
+    }
+
+    #[test]
+    fn it_should_count_a_request_until_its_body_was_sent() {
+        let in_flight = InFlight::default();
+        let res = in_flight
+            .enter()
+            .until_sent(rouille::Response::text("Gotta catch them all!"));
+
+        assert_eq!(in_flight.count(), 1);
+
+        let (mut reader, _) = res.data.into_reader_and_size();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        drop(reader);
+
+        assert_eq!(body, "Gotta catch them all!");
+        assert_eq!(in_flight.count(), 0);
+    }
+
+    #[test]
+    fn it_should_give_up_on_the_requests_still_served_at_the_deadline() {
+        let in_flight = InFlight::default();
+        let _serving = in_flight.enter();
+
+        let started_at = Instant::now();
+
+        assert_eq!(in_flight.drain(Duration::from_millis(50)), 1);
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+}
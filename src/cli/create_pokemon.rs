@@ -1,19 +1,29 @@
-use crate::cli::{prompt_name, prompt_number, prompt_types};
+use crate::cli::output::{self, Output, Pokemon};
+use crate::cli::{is_interactive, prompt_name, prompt_number, prompt_types, Exit};
 use crate::domain::create_pokemon;
 use crate::repositories::Repository;
 use std::sync::Arc;
 
-#[derive(Debug)]
-struct Response {
-    number: u16,
-    name: String,
-    types: Vec<String>,
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    create(repo, None, None, None, output)
 }
 
-pub fn run(repo: Arc<dyn Repository>) {
-    let number = prompt_number();
-    let name = prompt_name();
-    let types = prompt_types();
+/// Creates a Pokemon, prompting for whatever was not given when running in a terminal.
+pub fn create(
+    repo: Arc<dyn Repository>,
+    number: Option<u16>,
+    name: Option<String>,
+    types: Option<Vec<String>>,
+    output: Output,
+) -> Exit {
+    if !is_interactive() && (number.is_none() || name.is_none() || types.is_none()) {
+        eprintln!("The number, name and types are required");
+        return Exit::BadRequest;
+    }
+
+    let number = number.map_or_else(prompt_number, Ok);
+    let name = name.map_or_else(prompt_name, Ok);
+    let types = types.map_or_else(prompt_types, Ok);
 
     let req = match (number, name, types) {
         (Ok(number), Ok(name), Ok(types)) => create_pokemon::Request {
@@ -22,22 +32,34 @@ pub fn run(repo: Arc<dyn Repository>) {
             types,
         },
         _ => {
-            println!("An error occurred during the prompt");
-            return;
+            eprintln!("An error occurred during the prompt");
+            return Exit::Unknown;
         }
     };
 
     match create_pokemon::execute(repo, req) {
-        Ok(res) => println!(
-            "{:?}",
-            Response {
-                number: res.number,
-                name: res.name,
-                types: res.types,
-            }
-        ),
-        Err(create_pokemon::Error::BadRequest) => println!("The request is invalid"),
-        Err(create_pokemon::Error::Conflict) => println!("The Pokemon already exists"),
-        Err(create_pokemon::Error::Unknown) => println!("An unknown error occurred"),
-    };
+        Ok(res) => {
+            output::print(
+                output,
+                &Pokemon {
+                    number: res.number,
+                    name: res.name,
+                    types: res.types,
+                },
+            );
+            Exit::Success
+        }
+        Err(create_pokemon::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(create_pokemon::Error::Conflict) => {
+            eprintln!("The Pokemon already exists");
+            Exit::Conflict
+        }
+        Err(create_pokemon::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
 }
@@ -1,22 +1,68 @@
-use crate::cli::prompt_number;
+use crate::cli::output::Output;
+use crate::cli::{is_interactive, prompt_number, Exit};
 use crate::domain::delete_pokemon;
 use crate::repositories::Repository;
+use dialoguer::Confirm;
 use std::sync::Arc;
 
-pub fn run(repo: Arc<dyn Repository>) {
-    let number = prompt_number();
-
-    let req = match number {
-        Ok(number) => delete_pokemon::Request { number },
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    match prompt_number() {
+        Ok(number) => delete(repo, number, false, output),
         _ => {
-            println!("An error occurred during the prompt");
-            return;
+            eprintln!("An error occurred during the prompt");
+            Exit::Unknown
+        }
+    }
+}
+
+/// Deletes a Pokemon, asking for a confirmation first in a terminal unless `confirmed`.
+pub fn delete(repo: Arc<dyn Repository>, number: u16, confirmed: bool, output: Output) -> Exit {
+    if !confirmed && is_interactive() {
+        match Confirm::new()
+            .with_prompt(format!("Delete the Pokemon {}?", number))
+            .default(false)
+            .interact()
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("The deletion has been cancelled");
+                return Exit::Success;
+            }
+            _ => {
+                eprintln!("An error occurred during the prompt");
+                return Exit::Unknown;
+            }
+        }
+    }
+
+    match delete_pokemon::execute(
+        repo,
+        delete_pokemon::Request {
+            number,
+            versions: None,
+        },
+    ) {
+        Ok(()) => {
+            // Like the API, structured outputs have nothing to print on success.
+            if let Output::Table = output {
+                println!(
+                    "The Pokemon has been deleted, `restore {}` brings it back until it is purged",
+                    number
+                );
+            }
+            Exit::Success
+        }
+        Err(delete_pokemon::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(delete_pokemon::Error::NotFound) => {
+            eprintln!("The Pokemon does not exist");
+            Exit::NotFound
+        }
+        Err(delete_pokemon::Error::VersionMismatch) | Err(delete_pokemon::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
         }
-    };
-    match delete_pokemon::execute(repo, req) {
-        Ok(()) => println!("The Pokemon has been deleted"),
-        Err(delete_pokemon::Error::BadRequest) => println!("The request is invalid"),
-        Err(delete_pokemon::Error::NotFound) => println!("The Pokemon does not exist"),
-        Err(delete_pokemon::Error::Unknown) => println!("An unknown error occurred"),
     }
 }
@@ -0,0 +1,24 @@
+use crate::cli::Exit;
+use crate::domain::export_pokemons::{self, Error};
+use crate::formats::{Format, TypesLayout};
+use crate::repositories::Repository;
+use std::io;
+use std::sync::Arc;
+
+pub fn run(repo: Arc<dyn Repository>, format: Format, layout: Option<TypesLayout>) -> Exit {
+    match export_pokemons::execute(repo, export_pokemons::Request { format, layout }) {
+        Ok(mut exporter) => match io::copy(&mut exporter, &mut io::stdout().lock()) {
+            // The output being piped into a command which stopped reading is not a failure.
+            Ok(_) => Exit::Success,
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Exit::Success,
+            Err(_) => {
+                eprintln!("The export could not be written");
+                Exit::Unknown
+            }
+        },
+        Err(Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
@@ -1,26 +1,27 @@
+use crate::cli::output::{self, Output, Pokemon};
+use crate::cli::Exit;
 use crate::domain::fetch_all_pokemons;
 use crate::repositories::Repository;
 use std::sync::Arc;
 
-#[derive(Debug)]
-struct Response {
-    number: u16,
-    name: String,
-    types: Vec<String>,
-}
-
-pub fn run(repo: Arc<dyn Repository>) {
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
     match fetch_all_pokemons::execute(repo) {
-        Ok(res) => res.into_iter().for_each(|p| {
-            println!(
-                "{:?}",
-                Response {
-                    number: p.number,
-                    name: p.name,
-                    types: p.types,
-                }
+        Ok(res) => {
+            output::print(
+                output,
+                &res.into_iter()
+                    .map(|p| Pokemon {
+                        number: p.number,
+                        name: p.name,
+                        types: p.types,
+                    })
+                    .collect::<Vec<Pokemon>>(),
             );
-        }),
-        Err(fetch_all_pokemons::Error::Unknown) => println!("An unknown error occurred"),
-    };
+            Exit::Success
+        }
+        Err(fetch_all_pokemons::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use crate::cli::output::{self, Output, Pokemon, Tabular};
+use crate::cli::Exit;
+use crate::domain::fetch_audit_events;
+use crate::repositories::audit_log::AuditLog;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Same schema as the events returned by `GET /audit`.
+#[derive(Serialize)]
+struct Event {
+    at: DateTime<Utc>,
+    actor: String,
+    transport: String,
+    operation: String,
+    number: u16,
+    before: Option<Pokemon>,
+    after: Option<Pokemon>,
+}
+
+impl Tabular for Event {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "at",
+            "actor",
+            "transport",
+            "operation",
+            "number",
+            "before",
+            "after",
+        ]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        let name = |pokemon: &Option<Pokemon>| match pokemon {
+            Some(pokemon) => pokemon.name.clone(),
+            None => String::new(),
+        };
+
+        vec![vec![
+            self.at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.actor.clone(),
+            self.transport.clone(),
+            self.operation.clone(),
+            self.number.to_string(),
+            name(&self.before),
+            name(&self.after),
+        ]]
+    }
+}
+
+pub fn run(log: Arc<dyn AuditLog>, number: Option<u16>, output: Output) -> Exit {
+    match fetch_audit_events::execute(log, fetch_audit_events::Request { number }) {
+        Ok(events) => {
+            output::print(
+                output,
+                &events
+                    .into_iter()
+                    .map(|event| Event {
+                        at: event.at,
+                        actor: event.actor,
+                        transport: event.transport,
+                        operation: event.operation,
+                        number: event.number,
+                        before: event.before.map(|p| Pokemon {
+                            number: p.number,
+                            name: p.name,
+                            types: p.types,
+                        }),
+                        after: event.after.map(|p| Pokemon {
+                            number: p.number,
+                            name: p.name,
+                            types: p.types,
+                        }),
+                    })
+                    .collect::<Vec<Event>>(),
+            );
+            Exit::Success
+        }
+        Err(fetch_audit_events::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(fetch_audit_events::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
@@ -1,36 +1,43 @@
-use crate::cli::prompt_number;
+use crate::cli::output::{self, Output, Pokemon};
+use crate::cli::{prompt_number, Exit};
 use crate::domain::fetch_pokemon;
 use crate::repositories::Repository;
 use std::sync::Arc;
 
-#[derive(Debug)]
-struct Response {
-    number: u16,
-    name: String,
-    types: Vec<String>,
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    match prompt_number() {
+        Ok(number) => fetch(repo, number, output),
+        _ => {
+            eprintln!("An error occurred during the prompt");
+            Exit::Unknown
+        }
+    }
 }
 
-pub fn run(repo: Arc<dyn Repository>) {
-    let number = prompt_number();
-
-    let req = match number {
-        Ok(number) => fetch_pokemon::Request { number },
-        _ => {
-            println!("An error occurred during the prompt");
-            return;
+pub fn fetch(repo: Arc<dyn Repository>, number: u16, output: Output) -> Exit {
+    match fetch_pokemon::execute(repo, fetch_pokemon::Request { number }) {
+        Ok(res) => {
+            output::print(
+                output,
+                &Pokemon {
+                    number: res.number,
+                    name: res.name,
+                    types: res.types,
+                },
+            );
+            Exit::Success
+        }
+        Err(fetch_pokemon::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(fetch_pokemon::Error::NotFound) => {
+            eprintln!("The Pokemon does not exist");
+            Exit::NotFound
+        }
+        Err(fetch_pokemon::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
         }
-    };
-    match fetch_pokemon::execute(repo, req) {
-        Ok(res) => println!(
-            "{:?}",
-            Response {
-                number: res.number,
-                name: res.name,
-                types: res.types,
-            }
-        ),
-        Err(fetch_pokemon::Error::BadRequest) => println!("The request is invalid"),
-        Err(fetch_pokemon::Error::NotFound) => println!("The Pokemon does not exist"),
-        Err(fetch_pokemon::Error::Unknown) => println!("An unknown error occurred"),
     }
 }
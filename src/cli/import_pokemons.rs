@@ -0,0 +1,125 @@
+use crate::cli::output::{self, Output, Tabular};
+use crate::cli::Exit;
+use crate::domain::import_pokemons::{self, ConflictMode, Error, RowError};
+use crate::formats::Format;
+use crate::repositories::Repository;
+use serde::Serialize;
+use std::fs;
+use std::sync::Arc;
+
+/// Same schema as the response of `POST /import`.
+#[derive(Serialize)]
+struct Response {
+    imported: Vec<u16>,
+    skipped: Vec<u16>,
+    dry_run: bool,
+}
+
+impl Tabular for Response {
+    fn columns() -> &'static [&'static str] {
+        &["number", "status"]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        let imported = if self.dry_run { "valid" } else { "imported" };
+        self.imported
+            .iter()
+            .map(|number| vec![number.to_string(), String::from(imported)])
+            .chain(
+                self.skipped
+                    .iter()
+                    .map(|number| vec![number.to_string(), String::from("skipped")]),
+            )
+            .collect()
+    }
+}
+
+pub fn run(
+    repo: Arc<dyn Repository>,
+    path: &str,
+    dry_run: bool,
+    skip_conflicts: bool,
+    output: Output,
+) -> Exit {
+    let format = if path.ends_with(".csv") {
+        Format::Csv
+    } else if path.ends_with(".ndjson") || path.ends_with(".jsonl") {
+        Format::Ndjson
+    } else {
+        Format::Json
+    };
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        _ => {
+            eprintln!("The file {} could not be read", path);
+            return Exit::BadRequest;
+        }
+    };
+
+    let req = import_pokemons::Request {
+        format,
+        content,
+        dry_run,
+        on_conflict: if skip_conflicts {
+            ConflictMode::Skip
+        } else {
+            ConflictMode::Abort
+        },
+    };
+
+    match import_pokemons::execute(repo, req) {
+        Ok(res) => {
+            match output {
+                Output::Table => {
+                    let verb = if dry_run { "Would import" } else { "Imported" };
+                    println!("{} {} Pokemons", verb, res.imported.len());
+                    if !res.skipped.is_empty() {
+                        println!("Skipped {} existing Pokemons", res.skipped.len());
+                    }
+                }
+                _ => output::print(
+                    output,
+                    &Response {
+                        imported: res.imported,
+                        skipped: res.skipped,
+                        dry_run,
+                    },
+                ),
+            };
+            Exit::Success
+        }
+        Err(Error::BadRequest) => {
+            eprintln!("The file is not valid {}", describe(format));
+            Exit::BadRequest
+        }
+        Err(Error::InvalidRows(errors)) => {
+            eprintln!("Nothing was imported, some rows are invalid:");
+            print_errors(errors);
+            Exit::BadRequest
+        }
+        Err(Error::Conflict(conflicts)) => {
+            eprintln!("Nothing was imported, some Pokemons already exist:");
+            print_errors(conflicts);
+            Exit::Conflict
+        }
+        Err(Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
+
+fn describe(format: Format) -> &'static str {
+    match format {
+        Format::Csv => "CSV",
+        Format::Json => "JSON",
+        Format::Ndjson => "NDJSON",
+    }
+}
+
+fn print_errors(errors: Vec<RowError>) {
+    for error in errors {
+        eprintln!("  line {}: {}", error.line, error.message);
+    }
+}
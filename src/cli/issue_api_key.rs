@@ -0,0 +1,52 @@
+use crate::cli::output::{self, Output, Tabular};
+use crate::cli::Exit;
+use crate::domain::issue_api_key;
+use crate::repositories::api_key_store::ApiKeyStore;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Key {
+    name: String,
+    role: String,
+    key: String,
+}
+
+impl Tabular for Key {
+    fn columns() -> &'static [&'static str] {
+        &["name", "role", "key"]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        vec![vec![self.name.clone(), self.role.clone(), self.key.clone()]]
+    }
+}
+
+pub fn run(store: Arc<dyn ApiKeyStore>, name: String, role: String, output: Output) -> Exit {
+    match issue_api_key::execute(store, issue_api_key::Request { name, role }) {
+        Ok(res) => {
+            output::print(
+                output,
+                &Key {
+                    name: res.name,
+                    role: res.role,
+                    key: res.key,
+                },
+            );
+            eprintln!("Store the key now, it cannot be shown again");
+            Exit::Success
+        }
+        Err(issue_api_key::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(issue_api_key::Error::Conflict) => {
+            eprintln!("A key with this name already exists, revoke it first to issue a new one");
+            Exit::Conflict
+        }
+        Err(issue_api_key::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
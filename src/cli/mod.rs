@@ -1,19 +1,89 @@
-use crate::repositories::Repository;
+use crate::formats::{Format, TypesLayout};
+use crate::repositories::{
+    airtable_repository::AirtableRepository,
+    api_key_store::ApiKeyStore,
+    audit_log::{Actor, AuditLog, Transport},
+    sqlite_repository::SqliteRepository,
+    Repository,
+};
 use dialoguer::{theme::ColorfulTheme, Input, MultiSelect, Select};
+use output::Output;
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::Duration;
+
+pub use purge_pokemons::parse_age;
 
 mod create_pokemon;
 mod delete_pokemon;
+mod export_pokemons;
 mod fetch_all_pokemons;
+mod fetch_audit_events;
 mod fetch_pokemon;
+mod import_pokemons;
+mod issue_api_key;
+pub mod output;
+mod purge_pokemons;
+mod reconcile_pokemons;
+mod restore_pokemon;
+mod revoke_api_key;
+mod sync_pokemons;
+mod tui;
+
+/// How a command ended, each failure having its own exit code so that scripts can tell them
+/// apart.
+pub enum Exit {
+    Success,
+    Unknown,
+    BadRequest,
+    NotFound,
+    Conflict,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        let code = match exit {
+            Exit::Success => 0,
+            Exit::Unknown => 1,
+            Exit::BadRequest => 2,
+            Exit::NotFound => 3,
+            Exit::Conflict => 4,
+        };
+        ExitCode::from(code)
+    }
+}
+
+/// Changes made from the command line are attributed to the user running it.
+pub fn actor() -> Actor {
+    let name = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| String::from("unknown"));
+
+    Actor {
+        name,
+        transport: Transport::Cli,
+    }
+}
+
+/// Prompts are only shown when a user can answer them, never when stdin is piped.
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    if !is_interactive() {
+        eprintln!("The interactive mode needs a terminal, use the subcommands in scripts");
+        return Exit::BadRequest;
+    }
 
-pub fn run(repo: Arc<dyn Repository>) {
     loop {
         let choices = [
             "Fetch all Pokemons",
             "Fetch a Pokemon",
             "Create a Pokemon",
             "Delete a Pokemon",
+            "Restore a Pokemon",
             "Exit",
         ];
         let index = match Select::with_theme(&ColorfulTheme::default())
@@ -27,14 +97,89 @@ pub fn run(repo: Arc<dyn Repository>) {
         };
 
         match index {
-            0 => fetch_all_pokemons::run(repo.clone()),
-            1 => fetch_pokemon::run(repo.clone()),
-            2 => create_pokemon::run(repo.clone()),
-            3 => delete_pokemon::run(repo.clone()),
-            4 => break,
+            0 => fetch_all_pokemons::run(repo.clone(), output),
+            1 => fetch_pokemon::run(repo.clone(), output),
+            2 => create_pokemon::run(repo.clone(), output),
+            3 => delete_pokemon::run(repo.clone(), output),
+            4 => restore_pokemon::run(repo.clone(), output),
+            5 => break,
             _ => continue,
         };
     }
+
+    Exit::Success
+}
+
+pub fn browse(repo: Arc<dyn Repository>) -> Exit {
+    tui::run(repo)
+}
+
+pub fn list(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    fetch_all_pokemons::run(repo, output)
+}
+
+pub fn get(repo: Arc<dyn Repository>, number: u16, output: Output) -> Exit {
+    fetch_pokemon::fetch(repo, number, output)
+}
+
+pub fn create(
+    repo: Arc<dyn Repository>,
+    number: Option<u16>,
+    name: Option<String>,
+    types: Option<Vec<String>>,
+    output: Output,
+) -> Exit {
+    create_pokemon::create(repo, number, name, types, output)
+}
+
+pub fn delete(repo: Arc<dyn Repository>, number: u16, confirmed: bool, output: Output) -> Exit {
+    delete_pokemon::delete(repo, number, confirmed, output)
+}
+
+pub fn restore(repo: Arc<dyn Repository>, number: u16, output: Output) -> Exit {
+    restore_pokemon::restore(repo, number, output)
+}
+
+pub fn purge(repo: Arc<dyn Repository>, older_than: Duration, output: Output) -> Exit {
+    purge_pokemons::run(repo, older_than, output)
+}
+
+pub fn export(repo: Arc<dyn Repository>, format: Format, layout: Option<TypesLayout>) -> Exit {
+    export_pokemons::run(repo, format, layout)
+}
+
+pub fn import(
+    repo: Arc<dyn Repository>,
+    path: &str,
+    dry_run: bool,
+    skip_conflicts: bool,
+    output: Output,
+) -> Exit {
+    import_pokemons::run(repo, path, dry_run, skip_conflicts, output)
+}
+
+pub fn reconcile(
+    primary: Arc<dyn Repository>,
+    secondary: Arc<dyn Repository>,
+    output: Output,
+) -> Exit {
+    reconcile_pokemons::run(primary, secondary, output)
+}
+
+pub fn audit(log: Arc<dyn AuditLog>, number: Option<u16>, output: Output) -> Exit {
+    fetch_audit_events::run(log, number, output)
+}
+
+pub fn issue_key(store: Arc<dyn ApiKeyStore>, name: String, role: String, output: Output) -> Exit {
+    issue_api_key::run(store, name, role, output)
+}
+
+pub fn revoke_key(store: Arc<dyn ApiKeyStore>, name: String, output: Output) -> Exit {
+    revoke_api_key::run(store, name, output)
+}
+
+pub fn sync(local: &SqliteRepository, remote: &AirtableRepository, output: Output) -> Exit {
+    sync_pokemons::run(local, remote, output)
 }
 
 pub fn prompt_number() -> Result<u16, ()> {
@@ -0,0 +1,204 @@
+use serde::Serialize;
+
+/// How command results are printed. Structured outputs use the same schema as the HTTP API.
+#[derive(Clone, Copy)]
+pub enum Output {
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl TryFrom<&str> for Output {
+    type Error = ();
+
+    fn try_from(output: &str) -> Result<Self, Self::Error> {
+        match output {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "csv" => Ok(Self::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A result which can be laid out as rows, for the table and CSV outputs.
+pub trait Tabular {
+    fn columns() -> &'static [&'static str];
+    /// Cells of every row, lists being joined with `separator`.
+    fn rows(&self, separator: &str) -> Vec<Vec<String>>;
+}
+
+#[derive(Serialize)]
+pub struct Pokemon {
+    pub number: u16,
+    pub name: String,
+    pub types: Vec<String>,
+}
+
+impl Tabular for Pokemon {
+    fn columns() -> &'static [&'static str] {
+        &["number", "name", "types"]
+    }
+
+    fn rows(&self, separator: &str) -> Vec<Vec<String>> {
+        vec![vec![
+            self.number.to_string(),
+            self.name.clone(),
+            self.types.join(separator),
+        ]]
+    }
+}
+
+impl<T: Tabular> Tabular for Vec<T> {
+    fn columns() -> &'static [&'static str] {
+        T::columns()
+    }
+
+    fn rows(&self, separator: &str) -> Vec<Vec<String>> {
+        self.iter().flat_map(|item| item.rows(separator)).collect()
+    }
+}
+
+pub fn print<T: Serialize + Tabular>(output: Output, value: &T) {
+    match render(output, value) {
+        Ok(rendered) => print!("{}", rendered),
+        _ => eprintln!("The result could not be printed"),
+    }
+}
+
+fn render<T: Serialize + Tabular>(output: Output, value: &T) -> Result<String, ()> {
+    match output {
+        Output::Table => Ok(table(T::columns(), &value.rows(", "))),
+        Output::Json => match serde_json::to_string_pretty(value) {
+            Ok(json) => Ok(json + "\n"),
+            _ => Err(()),
+        },
+        Output::Yaml => match serde_yaml::to_string(value) {
+            Ok(yaml) => Ok(yaml),
+            _ => Err(()),
+        },
+        Output::Csv => csv(T::columns(), &value.rows(";")),
+    }
+}
+
+/// Left-aligns every column on its widest cell, under an upper-cased header.
+fn table(columns: &[&str], rows: &[Vec<String>]) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let header = columns
+        .iter()
+        .map(|column| column.to_uppercase())
+        .collect::<Vec<String>>();
+
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(index, title)| {
+            rows.iter()
+                .map(|row| row[index].chars().count())
+                .chain([title.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<usize>>();
+
+    let mut rendered = String::new();
+
+    for row in [header].iter().chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ");
+        rendered.push_str(line.trim_end());
+        rendered.push('\n');
+    }
+
+    rendered
+}
+
+fn csv(columns: &[&str], rows: &[Vec<String>]) -> Result<String, ()> {
+    let mut writer = ::csv::Writer::from_writer(vec![]);
+
+    if writer.write_record(columns).is_err() {
+        return Err(());
+    }
+
+    for row in rows {
+        if writer.write_record(row).is_err() {
+            return Err(());
+        }
+    }
+
+    match writer.into_inner() {
+        Ok(bytes) => String::from_utf8(bytes).map_err(|_| ()),
+        _ => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pokemons() -> Vec<Pokemon> {
+        vec![
+            Pokemon {
+                number: 4,
+                name: String::from("Charmander"),
+                types: vec![String::from("Fire")],
+            },
+            Pokemon {
+                number: 25,
+                name: String::from("Pikachu"),
+                types: vec![String::from("Electric"), String::from("Fire")],
+            },
+        ]
+    }
+
+    #[test]
+    fn it_should_align_the_table_columns() {
+        let rendered = render(Output::Table, &pokemons()).ok().unwrap();
+
+        assert_eq!(
+            rendered,
+            "NUMBER  NAME        TYPES\n\
+             4       Charmander  Fire\n\
+             25      Pikachu     Electric, Fire\n"
+        );
+    }
+
+    #[test]
+    fn it_should_print_the_same_json_as_the_api() {
+        let rendered = render(Output::Json, &pokemons()).ok().unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&rendered).unwrap(),
+            serde_json::json!([
+                { "number": 4, "name": "Charmander", "types": ["Fire"] },
+                { "number": 25, "name": "Pikachu", "types": ["Electric", "Fire"] },
+            ])
+        );
+    }
+
+    #[test]
+    fn it_should_print_yaml() {
+        let rendered = render(Output::Yaml, &pokemons()[0]).ok().unwrap();
+
+        assert_eq!(rendered, "number: 4\nname: Charmander\ntypes:\n- Fire\n");
+    }
+
+    #[test]
+    fn it_should_print_csv_with_flattened_types() {
+        let rendered = render(Output::Csv, &pokemons()).ok().unwrap();
+
+        assert_eq!(
+            rendered,
+            "number,name,types\n4,Charmander,Fire\n25,Pikachu,Electric;Fire\n"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::cli::output::{self, Output, Tabular};
+use crate::cli::Exit;
+use crate::domain::purge_pokemons;
+use crate::repositories::Repository;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Serialize)]
+struct Response {
+    purged: usize,
+}
+
+impl Tabular for Response {
+    fn columns() -> &'static [&'static str] {
+        &["purged"]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        vec![vec![self.purged.to_string()]]
+    }
+}
+
+pub fn run(repo: Arc<dyn Repository>, older_than: Duration, output: Output) -> Exit {
+    match purge_pokemons::execute(repo, purge_pokemons::Request { older_than }) {
+        Ok(res) if !matches!(output, Output::Table) => {
+            output::print(output, &Response { purged: res.purged });
+            Exit::Success
+        }
+        Ok(res) => {
+            println!("{} deleted Pokemons have been purged", res.purged);
+            Exit::Success
+        }
+        Err(purge_pokemons::Error::BadRequest) => {
+            eprintln!("The age is too large");
+            Exit::BadRequest
+        }
+        Err(purge_pokemons::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
+
+/// Parses an age such as `90s`, `15m`, `12h`, `30d` or `2w`.
+pub fn parse_age(age: &str) -> Result<Duration, ()> {
+    let (count, unit) = age.split_at(age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len()));
+
+    let count = match count.parse::<u64>() {
+        Ok(count) => count,
+        _ => return Err(()),
+    };
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(()),
+    };
+
+    match count.checked_mul(seconds) {
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_an_age_with_its_unit() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("30d"), Ok(Duration::from_secs(30 * 86400)));
+    }
+
+    #[test]
+    fn it_should_reject_an_age_without_a_known_unit() {
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("30y").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("-1d").is_err());
+    }
+}
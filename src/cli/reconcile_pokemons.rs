@@ -0,0 +1,116 @@
+use crate::cli::output::{self, Output, Tabular};
+use crate::cli::Exit;
+use crate::domain::reconcile_pokemons;
+use crate::repositories::Repository;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Response {
+    missing_from_airtable: Vec<u16>,
+    missing_locally: Vec<u16>,
+    different: Vec<u16>,
+}
+
+impl Tabular for Response {
+    fn columns() -> &'static [&'static str] {
+        &["number", "difference"]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        [
+            (&self.missing_from_airtable, "missing_from_airtable"),
+            (&self.missing_locally, "missing_locally"),
+            (&self.different, "different"),
+        ]
+        .into_iter()
+        .flat_map(|(numbers, difference)| {
+            numbers
+                .iter()
+                .map(move |number| vec![number.to_string(), String::from(difference)])
+        })
+        .collect()
+    }
+}
+
+/// Fails with `Exit::Conflict` when the repositories differ, so that scripts can tell.
+pub fn run(primary: Arc<dyn Repository>, secondary: Arc<dyn Repository>, output: Output) -> Exit {
+    let res = match reconcile_pokemons::execute(primary, secondary) {
+        Ok(res) => res,
+        Err(reconcile_pokemons::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            return Exit::Unknown;
+        }
+    };
+
+    let exit = match res.missing_from_secondary.is_empty()
+        && res.missing_from_primary.is_empty()
+        && res.different.is_empty()
+    {
+        true => Exit::Success,
+        false => Exit::Conflict,
+    };
+
+    match output {
+        Output::Table => match exit {
+            Exit::Success => println!("The repositories hold the same Pokemons"),
+            _ => {
+                print_numbers("Missing from Airtable", &res.missing_from_secondary);
+                print_numbers(
+                    "Missing from the local repository",
+                    &res.missing_from_primary,
+                );
+                print_numbers("Different in both repositories", &res.different);
+            }
+        },
+        _ => output::print(
+            output,
+            &Response {
+                missing_from_airtable: res.missing_from_secondary,
+                missing_locally: res.missing_from_primary,
+                different: res.different,
+            },
+        ),
+    }
+    exit
+}
+
+fn print_numbers(label: &str, numbers: &[u16]) {
+    if !numbers.is_empty() {
+        println!(
+            "{}: {}",
+            label,
+            numbers
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    #[test]
+    fn it_should_only_succeed_when_the_repositories_hold_the_same_pokemons() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+
+        let exit = run(primary.clone(), secondary.clone(), Output::Json);
+        assert!(matches!(exit, Exit::Success));
+
+        primary
+            .insert(
+                PokemonNumber::pikachu(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        let exit = run(primary, secondary, Output::Json);
+        assert!(matches!(exit, Exit::Conflict));
+    }
+}
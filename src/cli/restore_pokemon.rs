@@ -0,0 +1,49 @@
+use crate::cli::output::{self, Output, Pokemon};
+use crate::cli::{prompt_number, Exit};
+use crate::domain::restore_pokemon;
+use crate::repositories::Repository;
+use std::sync::Arc;
+
+pub fn run(repo: Arc<dyn Repository>, output: Output) -> Exit {
+    match prompt_number() {
+        Ok(number) => restore(repo, number, output),
+        _ => {
+            eprintln!("An error occurred during the prompt");
+            Exit::Unknown
+        }
+    }
+}
+
+pub fn restore(repo: Arc<dyn Repository>, number: u16, output: Output) -> Exit {
+    match restore_pokemon::execute(
+        repo,
+        restore_pokemon::Request {
+            number,
+            versions: None,
+        },
+    ) {
+        Ok(res) => {
+            output::print(
+                output,
+                &Pokemon {
+                    number: res.number,
+                    name: res.name,
+                    types: res.types,
+                },
+            );
+            Exit::Success
+        }
+        Err(restore_pokemon::Error::BadRequest) => {
+            eprintln!("The request is invalid");
+            Exit::BadRequest
+        }
+        Err(restore_pokemon::Error::NotFound) => {
+            eprintln!("The Pokemon was not deleted, or it was purged since");
+            Exit::NotFound
+        }
+        Err(restore_pokemon::Error::VersionMismatch) | Err(restore_pokemon::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::cli::output::Output;
+use crate::cli::Exit;
+use crate::domain::revoke_api_key;
+use crate::repositories::api_key_store::ApiKeyStore;
+use std::sync::Arc;
+
+pub fn run(store: Arc<dyn ApiKeyStore>, name: String, output: Output) -> Exit {
+    match revoke_api_key::execute(store, revoke_api_key::Request { name }) {
+        Ok(()) => {
+            // Like the other commands, structured outputs have nothing to print on success.
+            if let Output::Table = output {
+                println!("The key has been revoked");
+            }
+            Exit::Success
+        }
+        Err(revoke_api_key::Error::NotFound) => {
+            eprintln!("There is no key with this name");
+            Exit::NotFound
+        }
+        Err(revoke_api_key::Error::Unknown) => {
+            eprintln!("An unknown error occurred");
+            Exit::Unknown
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::cli::output::{self, Output, Tabular};
+use crate::cli::Exit;
+use crate::repositories::airtable_repository::AirtableRepository;
+use crate::repositories::sqlite_repository::SqliteRepository;
+use crate::repositories::sync::{self, Side, SyncError, SyncReport};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Response {
+    pushed: Vec<u16>,
+    deleted_remotely: Vec<u16>,
+    pulled: Vec<u16>,
+    deleted_locally: Vec<u16>,
+    conflicts: Vec<ConflictResponse>,
+}
+
+#[derive(Serialize)]
+struct ConflictResponse {
+    number: u16,
+    winner: &'static str,
+}
+
+impl From<SyncReport> for Response {
+    fn from(report: SyncReport) -> Self {
+        Self {
+            pushed: report.pushed,
+            deleted_remotely: report.deleted_remotely,
+            pulled: report.pulled,
+            deleted_locally: report.deleted_locally,
+            conflicts: report
+                .conflicts
+                .into_iter()
+                .map(|conflict| ConflictResponse {
+                    number: conflict.number,
+                    winner: match conflict.winner {
+                        Side::Local => "local",
+                        Side::Remote => "remote",
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Tabular for Response {
+    fn columns() -> &'static [&'static str] {
+        &["number", "change"]
+    }
+
+    fn rows(&self, _: &str) -> Vec<Vec<String>> {
+        let row = |number: &u16, change: &str| vec![number.to_string(), String::from(change)];
+
+        self.pushed
+            .iter()
+            .map(|number| row(number, "pushed"))
+            .chain(
+                self.deleted_remotely
+                    .iter()
+                    .map(|number| row(number, "deleted_remotely")),
+            )
+            .chain(self.pulled.iter().map(|number| row(number, "pulled")))
+            .chain(
+                self.deleted_locally
+                    .iter()
+                    .map(|number| row(number, "deleted_locally")),
+            )
+            .chain(self.conflicts.iter().map(|conflict| {
+                row(
+                    &conflict.number,
+                    &format!("conflict_{}_won", conflict.winner),
+                )
+            }))
+            .collect()
+    }
+}
+
+pub fn run(local: &SqliteRepository, remote: &AirtableRepository, output: Output) -> Exit {
+    match sync::sync(local, remote) {
+        Ok(report) if !matches!(output, Output::Table) => {
+            output::print(output, &Response::from(report));
+            Exit::Success
+        }
+        Ok(report) => {
+            print_numbers("Pushed to Airtable", &report.pushed);
+            print_numbers("Deleted from Airtable", &report.deleted_remotely);
+            print_numbers("Pulled from Airtable", &report.pulled);
+            print_numbers("Deleted locally", &report.deleted_locally);
+
+            for conflict in report.conflicts {
+                let winner = match conflict.winner {
+                    Side::Local => "the local change",
+                    Side::Remote => "the Airtable change",
+                };
+                println!("Conflict on Pokemon {}: kept {}", conflict.number, winner);
+            }
+
+            println!("Sync complete");
+            Exit::Success
+        }
+        Err(SyncError::Unknown) => {
+            eprintln!("The sync failed, pending changes will be pushed by the next one");
+            Exit::Unknown
+        }
+    }
+}
+
+fn print_numbers(label: &str, numbers: &[u16]) {
+    if !numbers.is_empty() {
+        println!(
+            "{}: {}",
+            label,
+            numbers
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+}
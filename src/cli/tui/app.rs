@@ -0,0 +1,414 @@
+use crate::domain::fetch_all_pokemons::Response as Pokemon;
+use crate::domain::{create_pokemon, delete_pokemon, fetch_all_pokemons, restore_pokemon};
+use crate::repositories::Repository;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::sync::Arc;
+
+pub enum Mode {
+    Browse,
+    Search,
+    Create(Form),
+    ConfirmDelete(u16),
+}
+
+/// The fields of a Pokemon being created, the types being separated by commas.
+#[derive(Default)]
+pub struct Form {
+    pub number: String,
+    pub name: String,
+    pub types: String,
+    pub focus: Field,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Field {
+    #[default]
+    Number,
+    Name,
+    Types,
+}
+
+impl Field {
+    fn next(self) -> Self {
+        match self {
+            Field::Number => Field::Name,
+            Field::Name => Field::Types,
+            Field::Types => Field::Number,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            Field::Number => Field::Types,
+            Field::Name => Field::Number,
+            Field::Types => Field::Name,
+        }
+    }
+}
+
+impl Form {
+    fn focused(&mut self) -> &mut String {
+        match self.focus {
+            Field::Number => &mut self.number,
+            Field::Name => &mut self.name,
+            Field::Types => &mut self.types,
+        }
+    }
+}
+
+/// The state of the terminal UI, updated by key presses and kept apart from the drawing so that
+/// it can be tested without a terminal.
+pub struct App {
+    repo: Arc<dyn Repository>,
+    pokemons: Vec<Pokemon>,
+    pub query: String,
+    pub selected: usize,
+    pub mode: Mode,
+    pub message: Option<String>,
+    pub quit: bool,
+    /// Restored by the undo key.
+    last_deleted: Option<u16>,
+}
+
+impl App {
+    pub fn new(repo: Arc<dyn Repository>) -> Self {
+        let mut app = Self {
+            repo,
+            pokemons: vec![],
+            query: String::new(),
+            selected: 0,
+            mode: Mode::Browse,
+            message: None,
+            quit: false,
+            last_deleted: None,
+        };
+        app.reload();
+        app
+    }
+
+    /// The Pokemons whose name contains the query, ignoring case, or whose number starts with it.
+    pub fn filtered(&self) -> Vec<&Pokemon> {
+        let query = self.query.to_lowercase();
+        self.pokemons
+            .iter()
+            .filter(|p| {
+                p.name.to_lowercase().contains(&query) || p.number.to_string().starts_with(&query)
+            })
+            .collect()
+    }
+
+    pub fn current(&self) -> Option<&Pokemon> {
+        self.filtered().get(self.selected).copied()
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        match &mut self.mode {
+            Mode::Browse => self.on_browse_key(key.code),
+            Mode::Search => self.on_search_key(key.code),
+            Mode::Create(form) => match key.code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Enter => self.create(),
+                KeyCode::Tab | KeyCode::Down => form.focus = form.focus.next(),
+                KeyCode::BackTab | KeyCode::Up => form.focus = form.focus.previous(),
+                KeyCode::Backspace => {
+                    form.focused().pop();
+                }
+                KeyCode::Char(c) => form.focused().push(c),
+                _ => {}
+            },
+            Mode::ConfirmDelete(number) => {
+                let number = *number;
+                self.mode = Mode::Browse;
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => self.delete(number),
+                    _ => self.message = Some(String::from("Deletion cancelled")),
+                }
+            }
+        }
+    }
+
+    fn on_browse_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected.saturating_add(1)),
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('n') => self.mode = Mode::Create(Form::default()),
+            KeyCode::Char('d') => {
+                if let Some(pokemon) = self.current() {
+                    self.mode = Mode::ConfirmDelete(pokemon.number);
+                }
+            }
+            KeyCode::Char('u') => match self.last_deleted.take() {
+                Some(number) => self.restore(number),
+                None => self.message = Some(String::from("Nothing to undo")),
+            },
+            KeyCode::Char('r') => self.reload(),
+            _ => {}
+        }
+    }
+
+    fn on_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => self.mode = Mode::Browse,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.mode = Mode::Browse;
+                self.select(0);
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.select(0);
+            }
+            KeyCode::Down => self.select(self.selected.saturating_add(1)),
+            KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.select(0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Selects the Pokemon at `index` in the filtered list, or the last one past its end.
+    fn select(&mut self, index: usize) {
+        let len = self.filtered().len();
+        self.selected = index.min(len.saturating_sub(1));
+    }
+
+    fn reload(&mut self) {
+        match fetch_all_pokemons::execute(self.repo.clone()) {
+            Ok(pokemons) => self.pokemons = pokemons,
+            Err(fetch_all_pokemons::Error::Unknown) => {
+                self.message = Some(String::from("An unknown error occurred"))
+            }
+        };
+        self.select(self.selected);
+    }
+
+    fn create(&mut self) {
+        let req = match &self.mode {
+            Mode::Create(form) => match form.number.trim().parse::<u16>() {
+                Ok(number) => create_pokemon::Request {
+                    number,
+                    name: String::from(form.name.trim()),
+                    types: form
+                        .types
+                        .split(',')
+                        .map(|t| String::from(t.trim()))
+                        .filter(|t| !t.is_empty())
+                        .collect(),
+                },
+                _ => {
+                    self.message = Some(String::from("The number is invalid"));
+                    return;
+                }
+            },
+            _ => return,
+        };
+
+        // The form stays open on errors so that it can be fixed.
+        match create_pokemon::execute(self.repo.clone(), req) {
+            Ok(res) => {
+                self.mode = Mode::Browse;
+                self.query.clear();
+                self.reload();
+                if let Some(index) = self.filtered().iter().position(|p| p.number == res.number) {
+                    self.selected = index;
+                }
+                self.message = Some(format!("{} has been created", res.name));
+            }
+            Err(create_pokemon::Error::BadRequest) => {
+                self.message = Some(String::from("The request is invalid"))
+            }
+            Err(create_pokemon::Error::Conflict) => {
+                self.message = Some(String::from("The Pokemon already exists"))
+            }
+            Err(create_pokemon::Error::Unknown) => {
+                self.message = Some(String::from("An unknown error occurred"))
+            }
+        }
+    }
+
+    fn delete(&mut self, number: u16) {
+        self.message = Some(
+            match delete_pokemon::execute(
+                self.repo.clone(),
+                delete_pokemon::Request {
+                    number,
+                    versions: None,
+                },
+            ) {
+                Ok(()) => {
+                    self.last_deleted = Some(number);
+                    String::from("The Pokemon has been deleted, press u to undo")
+                }
+                Err(delete_pokemon::Error::BadRequest) => String::from("The request is invalid"),
+                Err(delete_pokemon::Error::NotFound) => String::from("The Pokemon does not exist"),
+                Err(delete_pokemon::Error::VersionMismatch)
+                | Err(delete_pokemon::Error::Unknown) => String::from("An unknown error occurred"),
+            },
+        );
+        self.reload();
+    }
+
+    fn restore(&mut self, number: u16) {
+        self.message = Some(
+            match restore_pokemon::execute(
+                self.repo.clone(),
+                restore_pokemon::Request {
+                    number,
+                    versions: None,
+                },
+            ) {
+                Ok(res) => format!("{} has been restored", res.name),
+                Err(restore_pokemon::Error::BadRequest) => String::from("The request is invalid"),
+                Err(restore_pokemon::Error::NotFound) => {
+                    String::from("The Pokemon is not deleted anymore")
+                }
+                Err(restore_pokemon::Error::VersionMismatch)
+                | Err(restore_pokemon::Error::Unknown) => String::from("An unknown error occurred"),
+            },
+        );
+        self.reload();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    fn repo() -> Arc<InMemoryRepository> {
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.insert(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        )
+        .ok();
+        repo
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.on_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    fn names(app: &App) -> Vec<String> {
+        app.filtered().iter().map(|p| p.name.clone()).collect()
+    }
+
+    #[test]
+    fn it_should_filter_the_list_while_searching() {
+        let mut app = App::new(repo());
+
+        press(&mut app, "/CHAR");
+
+        assert_eq!(names(&app), vec!["Charmander"]);
+
+        app.on_key(KeyEvent::from(KeyCode::Backspace));
+        app.on_key(KeyEvent::from(KeyCode::Backspace));
+        app.on_key(KeyEvent::from(KeyCode::Backspace));
+        app.on_key(KeyEvent::from(KeyCode::Backspace));
+        press(&mut app, "25");
+
+        assert_eq!(names(&app), vec!["Pikachu"]);
+
+        app.on_key(KeyEvent::from(KeyCode::Esc));
+
+        assert_eq!(names(&app).len(), 2);
+    }
+
+    #[test]
+    fn it_should_keep_the_selection_within_the_list() {
+        let mut app = App::new(repo());
+
+        press(&mut app, "jjj");
+        assert_eq!(app.selected, 1);
+
+        press(&mut app, "kkk");
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn it_should_create_a_pokemon_from_the_form() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let mut app = App::new(repo.clone());
+
+        press(&mut app, "n25");
+        app.on_key(KeyEvent::from(KeyCode::Tab));
+        press(&mut app, "Pikachu");
+        app.on_key(KeyEvent::from(KeyCode::Tab));
+        press(&mut app, "Electric");
+        app.on_key(KeyEvent::from(KeyCode::Enter));
+
+        assert!(matches!(app.mode, Mode::Browse));
+        assert_eq!(names(&app), vec!["Pikachu"]);
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+
+    #[test]
+    fn it_should_keep_the_form_open_when_the_pokemon_cannot_be_created() {
+        let mut app = App::new(repo());
+
+        press(&mut app, "n25");
+        app.on_key(KeyEvent::from(KeyCode::Tab));
+        press(&mut app, "Pikachu");
+        app.on_key(KeyEvent::from(KeyCode::Tab));
+        press(&mut app, "Electric");
+        app.on_key(KeyEvent::from(KeyCode::Enter));
+
+        assert!(matches!(app.mode, Mode::Create(_)));
+        assert_eq!(app.message.as_deref(), Some("The Pokemon already exists"));
+    }
+
+    #[test]
+    fn it_should_only_delete_a_pokemon_once_confirmed() {
+        let repo = repo();
+        let mut app = App::new(repo.clone());
+        let number = app.current().map(|p| p.number);
+
+        press(&mut app, "dn");
+        assert_eq!(app.filtered().len(), 2);
+
+        press(&mut app, "dy");
+        assert_eq!(app.filtered().len(), 1);
+        assert!(app.filtered().iter().all(|p| Some(p.number) != number));
+    }
+
+    #[test]
+    fn it_should_undo_the_last_deletion() {
+        let mut app = App::new(repo());
+
+        press(&mut app, "dyu");
+
+        assert_eq!(names(&app).len(), 2);
+
+        press(&mut app, "u");
+
+        assert_eq!(app.message.as_deref(), Some("Nothing to undo"));
+    }
+
+    #[test]
+    fn it_should_show_an_error_when_the_pokemons_cannot_be_fetched() {
+        let app = App::new(Arc::new(InMemoryRepository::new().with_error()));
+
+        assert_eq!(app.message.as_deref(), Some("An unknown error occurred"));
+    }
+}
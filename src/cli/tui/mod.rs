@@ -0,0 +1,46 @@
+use crate::cli::{is_interactive, Exit};
+use crate::repositories::Repository;
+use app::App;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+use std::io;
+use std::sync::Arc;
+
+mod app;
+mod ui;
+
+/// Browses the Pokemons in a full-screen terminal UI until the user quits.
+pub fn run(repo: Arc<dyn Repository>) -> Exit {
+    if !is_interactive() {
+        eprintln!("The terminal UI needs a terminal, use the subcommands in scripts");
+        return Exit::BadRequest;
+    }
+
+    let mut terminal = ratatui::init();
+    let res = event_loop(&mut terminal, App::new(repo));
+    ratatui::restore();
+
+    match res {
+        Ok(()) => Exit::Success,
+        Err(err) => {
+            eprintln!("The terminal UI failed: {}", err);
+            Exit::Unknown
+        }
+    }
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, mut app: App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        // Windows also reports key releases, which would type every character twice.
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.message = None;
+                app.on_key(key);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use super::app::{App, Field, Form, Mode};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+const HELP: &str = "↑↓ move  / search  n new  d delete  u undo  r reload  q quit";
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [search, body, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [list, detail] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body);
+
+    let search_style = match app.mode {
+        Mode::Search => Style::default().add_modifier(Modifier::BOLD),
+        _ => Style::default(),
+    };
+    frame.render_widget(
+        Paragraph::new(app.query.as_str())
+            .style(search_style)
+            .block(Block::default().borders(Borders::ALL).title("Search")),
+        search,
+    );
+
+    let pokemons = app.filtered();
+    let items = pokemons
+        .iter()
+        .map(|p| ListItem::new(format!("{:>3}  {}", p.number, p.name)))
+        .collect::<Vec<ListItem>>();
+    let mut state = ListState::default();
+    if !pokemons.is_empty() {
+        state.select(Some(app.selected));
+    }
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Pokemons ({})", pokemons.len())),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        list,
+        &mut state,
+    );
+
+    let lines = match app.current() {
+        Some(p) => vec![
+            Line::from(format!("Number  {}", p.number)),
+            Line::from(format!("Name    {}", p.name)),
+            Line::from(format!("Types   {}", p.types.join(", "))),
+        ],
+        None => vec![Line::from("No Pokemon")],
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Details")),
+        detail,
+    );
+
+    frame.render_widget(
+        Paragraph::new(app.message.as_deref().unwrap_or(HELP)),
+        status,
+    );
+
+    match &app.mode {
+        Mode::Create(form) => draw_form(frame, form),
+        Mode::ConfirmDelete(number) => {
+            let area = centered(frame.area(), 40, 3);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(format!("Delete Pokemon {}? (y/n)", number))
+                    .block(Block::default().borders(Borders::ALL).title("Confirm")),
+                area,
+            );
+        }
+        _ => {}
+    }
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let area = centered(frame.area(), 50, 5);
+    let lines = [
+        (Field::Number, "Number", &form.number),
+        (Field::Name, "Name", &form.name),
+        (Field::Types, "Types", &form.types),
+    ]
+    .into_iter()
+    .map(|(field, label, value)| {
+        let style = match form.focus == field {
+            true => Style::default().add_modifier(Modifier::REVERSED),
+            false => Style::default(),
+        };
+        Line::styled(format!("{:<7} {}", label, value), style)
+    })
+    .collect::<Vec<Line>>();
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("New Pokemon (Tab next, Enter create, Esc cancel)"),
+        ),
+        area,
+    );
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,99 @@
+use crate::repositories::api_key_store::{self, ApiKeyStore, Role};
+use std::sync::Arc;
+
+pub struct Request {
+    pub key: String,
+    /// The least role allowed to do what the key is used for.
+    pub role: Role,
+}
+
+pub struct Response {
+    pub name: String,
+}
+
+pub enum Error {
+    Unauthenticated,
+    Forbidden,
+    Unknown,
+}
+
+pub fn execute(store: Arc<dyn ApiKeyStore>, req: Request) -> Result<Response, Error> {
+    match store.fetch_by_hash(&api_key_store::hash(&req.key)) {
+        Ok(Some(key)) if key.role >= req.role => Ok(Response { name: key.name }),
+        Ok(Some(_)) => Err(Error::Forbidden),
+        Ok(None) => Err(Error::Unauthenticated),
+        Err(()) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::issue_api_key;
+    use crate::repositories::sqlite_api_key_store::SqliteApiKeyStore;
+
+    fn issue(store: Arc<dyn ApiKeyStore>, role: &str) -> String {
+        match issue_api_key::execute(
+            store,
+            issue_api_key::Request {
+                name: String::from("misty"),
+                role: String::from(role),
+            },
+        ) {
+            Ok(res) => res.key,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_should_return_an_unauthenticated_error_when_the_key_is_unknown() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let req = Request {
+            key: String::from("pdx_unknown"),
+            role: Role::Reader,
+        };
+
+        let res = execute(Arc::new(store), req);
+
+        match res {
+            Err(Error::Unauthenticated) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_forbidden_error_when_the_role_is_not_enough() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        let key = issue(store.clone(), "reader");
+        let req = Request {
+            key,
+            role: Role::Editor,
+        };
+
+        let res = execute(store, req);
+
+        match res {
+            Err(Error::Forbidden) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_name_of_the_key_otherwise() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        let key = issue(store.clone(), "admin");
+        let req = Request {
+            key,
+            role: Role::Editor,
+        };
+
+        let res = execute(store, req);
+
+        match res {
+            Ok(res) => assert_eq!(res.name, "misty"),
+            _ => unreachable!(),
+        };
+    }
+}
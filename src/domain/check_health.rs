@@ -0,0 +1,52 @@
+use crate::repositories::{HealthError, Repository};
+use std::sync::Arc;
+
+pub enum Error {
+    Unavailable { backend: String, detail: String },
+}
+
+pub struct Response {
+    pub backend: String,
+}
+
+pub fn execute(repo: Arc<dyn Repository>) -> Result<Response, Error> {
+    let backend = repo.backend();
+
+    match repo.check_health() {
+        Ok(()) => Ok(Response { backend }),
+        Err(HealthError::Unavailable(detail)) => Err(Error::Unavailable { backend, detail }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    #[test]
+    fn it_should_return_an_unavailable_error_when_the_backend_cannot_serve_requests() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+
+        let res = execute(repo);
+
+        match res {
+            Err(Error::Unavailable { backend, detail }) => {
+                assert_eq!(backend, "memory");
+                assert!(!detail.is_empty());
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_backend_otherwise() {
+        let repo = Arc::new(InMemoryRepository::new());
+
+        let res = execute(repo);
+
+        match res {
+            Ok(res) => assert_eq!(res.backend, "memory"),
+            _ => unreachable!(),
+        };
+    }
+}
@@ -13,6 +13,7 @@ pub struct Response {
     pub number: u16,
     pub name: String,
     pub types: Vec<String>,
+    pub version: u64,
 }
 
 pub enum Error {
@@ -32,6 +33,7 @@ pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Response, Erro
                 number: u16::from(p.number),
                 name: String::from(p.name),
                 types: Vec::<String>::from(p.types),
+                version: p.version,
             }),
             Err(InsertError::Conflict) => Err(Error::Conflict),
             Err(InsertError::Unknown) => Err(Error::Unknown),
@@ -72,10 +74,12 @@ mod tests {
                 number,
                 name,
                 types,
+                version,
             }) => {
                 assert_eq!(number, 25);
                 assert_eq!(name, String::from("Pikachu"));
                 assert_eq!(types, vec![String::from("Electric")]);
+                assert_eq!(version, 1);
             }
             _ => unreachable!(),
         };
@@ -98,6 +102,23 @@ mod tests {
         };
     }
 
+    #[test]
+    fn it_should_return_a_bad_request_error_when_types_are_duplicated() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request {
+            number: u16::from(PokemonNumber::pikachu()),
+            name: String::from(PokemonName::pikachu()),
+            types: vec![String::from("Electric"), String::from("Electric")],
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
     #[test]
     fn it_should_return_a_conflict_error_when_pokemon_number_already_exists() {
         let repo = Arc::new(InMemoryRepository::new());
@@ -4,19 +4,23 @@ use std::sync::Arc;
 
 pub struct Request {
     pub number: u16,
+    /// The versions the Pokemon must still be at one of, for the deletion to go through.
+    pub versions: Option<Vec<u64>>,
 }
 
 pub enum Error {
     BadRequest,
     NotFound,
+    VersionMismatch,
     Unknown,
 }
 
 pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<(), Error> {
     match PokemonNumber::try_from(req.number) {
-        Ok(number) => match repo.delete(number) {
+        Ok(number) => match repo.conditional_delete(number, req.versions.as_deref()) {
             Ok(()) => Ok(()),
             Err(DeleteError::NotFound) => Err(Error::NotFound),
+            Err(DeleteError::VersionMismatch) => Err(Error::VersionMismatch),
             Err(DeleteError::Unknown) => Err(Error::Unknown),
         },
         _ => Err(Error::BadRequest),
@@ -72,6 +76,29 @@ mod tests {
         };
     }
 
+    #[test]
+    fn it_should_return_a_version_mismatch_error_when_the_pokemon_is_at_another_version() {
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        let req = Request {
+            versions: Some(vec![2]),
+            ..Request::new(PokemonNumber::pikachu())
+        };
+
+        let res = execute(repo.clone(), req);
+
+        match res {
+            Err(Error::VersionMismatch) => {}
+            _ => unreachable!(),
+        };
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+
     #[test]
     fn it_should_return_ok_otherwise() {
         let repo = Arc::new(InMemoryRepository::new());
@@ -95,6 +122,7 @@ mod tests {
         fn new(number: PokemonNumber) -> Self {
             Self {
                 number: u16::from(number),
+                versions: None,
             }
         }
     }
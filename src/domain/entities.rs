@@ -18,7 +18,7 @@ impl From<PokemonNumber> for u16 {
         n.0
     }
 }
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct PokemonName(String);
 
 impl TryFrom<String> for PokemonName {
@@ -39,7 +39,7 @@ impl From<PokemonName> for String {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct PokemonTypes(Vec<PokemonType>);
 
 impl TryFrom<Vec<String>> for PokemonTypes {
@@ -52,7 +52,7 @@ impl TryFrom<Vec<String>> for PokemonTypes {
             let mut pts = vec![];
             for t in ts.iter() {
                 match PokemonType::try_from(String::from(t)) {
-                    Ok(pt) => pts.push(pt),
+                    Ok(pt) if !pts.contains(&pt) => pts.push(pt),
                     _ => return Err(()),
                 }
             }
@@ -71,7 +71,7 @@ impl From<PokemonTypes> for Vec<String> {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 enum PokemonType {
     Electric,
     Fire,
@@ -103,6 +103,8 @@ pub struct Pokemon {
     pub number: PokemonNumber,
     pub name: PokemonName,
     pub types: PokemonTypes,
+    /// Bumped by the repository every time the Pokemon is written, starting at 1.
+    pub version: u64,
 }
 
 impl Pokemon {
@@ -111,8 +113,20 @@ impl Pokemon {
             number,
             name,
             types,
+            version: 1,
         }
     }
+
+    pub fn with_version(self, version: u64) -> Self {
+        Self { version, ..self }
+    }
+}
+
+/// Pokemons are equal when their content is, since every repository keeps its own versions.
+impl PartialEq for Pokemon {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number && self.name == other.name && self.types == other.types
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,82 @@
+use crate::formats::{Exporter, Format, PokemonRecord, TypesLayout};
+use crate::repositories::{FetchAllError, Repository};
+use std::sync::Arc;
+
+pub struct Request {
+    pub format: Format,
+    pub layout: Option<TypesLayout>,
+}
+
+pub enum Error {
+    Unknown,
+}
+
+/// Returns the whole Pokedex as a readable stream in the requested format, which is only
+/// serialized as it is read.
+pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Exporter, Error> {
+    match repo.fetch_all() {
+        Ok(pokemons) => Ok(Exporter::new(
+            req.format,
+            req.layout,
+            pokemons.into_iter().map(|p| PokemonRecord {
+                number: u16::from(p.number),
+                name: String::from(p.name),
+                types: Vec::<String>::from(p.types),
+            }),
+        )),
+        Err(FetchAllError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+    use std::io::Read;
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+        let req = Request {
+            format: Format::Csv,
+            layout: None,
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_export_every_pokemon_otherwise() {
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        let req = Request {
+            format: Format::Ndjson,
+            layout: Some(TypesLayout::Flattened),
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Ok(mut exporter) => {
+                let mut output = String::new();
+                exporter.read_to_string(&mut output).unwrap();
+                assert_eq!(
+                    output,
+                    "{\"number\":25,\"name\":\"Pikachu\",\"types\":\"Electric\"}\n"
+                );
+            }
+            _ => unreachable!(),
+        };
+    }
+}
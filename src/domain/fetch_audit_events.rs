@@ -0,0 +1,114 @@
+use crate::domain::entities::PokemonNumber;
+use crate::repositories::audit_log::{AuditLog, Snapshot};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+pub struct Request {
+    pub number: Option<u16>,
+}
+
+pub struct Response {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub transport: String,
+    pub operation: String,
+    pub number: u16,
+    pub before: Option<Snapshot>,
+    pub after: Option<Snapshot>,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown,
+}
+
+pub fn execute(log: Arc<dyn AuditLog>, req: Request) -> Result<Vec<Response>, Error> {
+    let number = match req.number.map(PokemonNumber::try_from) {
+        Some(Ok(number)) => Some(u16::from(number)),
+        Some(Err(())) => return Err(Error::BadRequest),
+        None => None,
+    };
+
+    match log.events(number) {
+        Ok(events) => Ok(events
+            .into_iter()
+            .map(|event| Response {
+                at: event.at,
+                actor: event.actor,
+                transport: String::from(<&str>::from(event.transport)),
+                operation: String::from(<&str>::from(event.operation)),
+                number: event.number,
+                before: event.before,
+                after: event.after,
+            })
+            .collect()),
+        Err(()) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::audit_log::{AuditEvent, InMemoryAuditLog, Operation, Transport};
+
+    fn record(log: &dyn AuditLog, number: u16, operation: Operation) {
+        log.record(&AuditEvent {
+            at: Utc::now(),
+            actor: String::from("brock"),
+            transport: Transport::Api,
+            operation,
+            number,
+            before: None,
+            after: None,
+        })
+        .ok();
+    }
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let log = Arc::new(InMemoryAuditLog::new().with_error());
+
+        let res = execute(log, Request { number: None });
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_the_number_is_invalid() {
+        let log = Arc::new(InMemoryAuditLog::new());
+
+        let res = execute(log, Request { number: Some(0) });
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_events_of_the_pokemon_otherwise() {
+        let log = Arc::new(InMemoryAuditLog::new());
+        record(&*log, 25, Operation::Insert);
+        record(&*log, 4, Operation::Insert);
+        record(&*log, 25, Operation::Delete);
+
+        let res = execute(log, Request { number: Some(25) });
+
+        match res {
+            Ok(res) => {
+                assert_eq!(
+                    res.iter()
+                        .map(|e| e.operation.as_str())
+                        .collect::<Vec<&str>>(),
+                    vec!["insert", "delete"]
+                );
+                assert_eq!(res[0].actor, "brock");
+                assert_eq!(res[0].transport, "api");
+            }
+            _ => unreachable!(),
+        };
+    }
+}
@@ -12,6 +12,7 @@ pub struct Response {
     pub number: u16,
     pub name: String,
     pub types: Vec<String>,
+    pub version: u64,
 }
 
 pub enum Error {
@@ -27,6 +28,7 @@ pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Response, Erro
                 number: u16::from(p.number),
                 name: String::from(p.name),
                 types: Vec::<String>::from(p.types),
+                version: p.version,
             }),
             Err(FetchOneError::NotFound) => Err(Error::NotFound),
             Err(FetchOneError::Unknown) => Err(Error::Unknown),
@@ -0,0 +1,112 @@
+use crate::repositories::event_sourced_repository::{AsOf, History};
+use crate::repositories::FetchAllError;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// `as_of` is either the id of an event or an RFC 3339 timestamp.
+pub struct Request {
+    pub as_of: String,
+}
+
+pub struct Response {
+    pub number: u16,
+    pub name: String,
+    pub types: Vec<String>,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown,
+}
+
+pub fn execute(history: Arc<dyn History>, req: Request) -> Result<Vec<Response>, Error> {
+    let as_of = match (
+        req.as_of.parse::<u64>(),
+        DateTime::parse_from_rfc3339(&req.as_of),
+    ) {
+        (Ok(id), _) => AsOf::Event(id),
+        (_, Ok(at)) => AsOf::Time(at.with_timezone(&Utc)),
+        _ => return Err(Error::BadRequest),
+    };
+
+    match history.fetch_all_as_of(as_of) {
+        Ok(pokemons) => Ok(pokemons
+            .into_iter()
+            .map(|p| Response {
+                number: u16::from(p.number),
+                name: String::from(p.name),
+                types: Vec::<String>::from(p.types),
+            })
+            .collect::<Vec<Response>>()),
+        Err(FetchAllError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::repositories::event_sourced_repository::EventSourcedRepository;
+    use crate::repositories::Repository;
+
+    fn history() -> (Arc<EventSourcedRepository>, tempfile::TempDir) {
+        let (repo, dir) = EventSourcedRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        (Arc::new(repo), dir)
+    }
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_as_of_is_neither_an_event_nor_a_time() {
+        let (history, _dir) = history();
+        let req = Request {
+            as_of: String::from("yesterday"),
+        };
+
+        let res = execute(history, req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_pokemons_as_of_an_event() {
+        let (history, _dir) = history();
+        let req = Request {
+            as_of: String::from("1"),
+        };
+
+        let res = execute(history, req);
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.len(), 1);
+                assert_eq!(res[0].number, u16::from(PokemonNumber::pikachu()));
+                assert_eq!(res[0].name, String::from(PokemonName::pikachu()));
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_pokemons_as_of_a_time() {
+        let (history, _dir) = history();
+        let req = Request {
+            as_of: Utc::now().to_rfc3339(),
+        };
+
+        let res = execute(history, req);
+
+        match res {
+            Ok(res) => assert!(res.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,279 @@
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use crate::formats::{self, Format};
+use crate::repositories::{InsertError, Repository};
+use std::sync::Arc;
+
+pub enum ConflictMode {
+    Skip,
+    Abort,
+}
+
+pub struct Request {
+    pub format: Format,
+    pub content: String,
+    pub dry_run: bool,
+    pub on_conflict: ConflictMode,
+}
+
+pub struct Response {
+    pub imported: Vec<u16>,
+    pub skipped: Vec<u16>,
+}
+
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+pub enum Error {
+    BadRequest,
+    InvalidRows(Vec<RowError>),
+    Conflict(Vec<RowError>),
+    Unknown,
+}
+
+pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Response, Error> {
+    let lines = match formats::read(req.format, &req.content) {
+        Ok(lines) => lines,
+        _ => return Err(Error::BadRequest),
+    };
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+
+    for line in lines {
+        match line.record {
+            Ok(record) => match (
+                PokemonNumber::try_from(record.number),
+                PokemonName::try_from(record.name),
+                PokemonTypes::try_from(record.types),
+            ) {
+                (Ok(number), Ok(name), Ok(types)) => {
+                    rows.push((line.line, Pokemon::new(number, name, types)))
+                }
+                (Err(_), _, _) => errors.push(RowError {
+                    line: line.line,
+                    message: format!("invalid number {}", record.number),
+                }),
+                (_, Err(_), _) => errors.push(RowError {
+                    line: line.line,
+                    message: String::from("the name is empty"),
+                }),
+                (_, _, Err(_)) => errors.push(RowError {
+                    line: line.line,
+                    message: String::from("the types are empty, unknown or duplicated"),
+                }),
+            },
+            Err(message) => errors.push(RowError {
+                line: line.line,
+                message,
+            }),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::InvalidRows(errors));
+    }
+
+    let mut existing = match repo.fetch_all() {
+        Ok(pokemons) => pokemons
+            .into_iter()
+            .map(|pokemon| pokemon.number)
+            .collect::<Vec<PokemonNumber>>(),
+        _ => return Err(Error::Unknown),
+    };
+
+    let mut pokemons = vec![];
+    let mut skipped = vec![];
+    let mut conflicts = vec![];
+
+    // A number appearing twice in the file conflicts with its first occurrence.
+    for (line, pokemon) in rows {
+        if existing.contains(&pokemon.number) {
+            skipped.push(u16::from(pokemon.number.clone()));
+            conflicts.push(RowError {
+                line,
+                message: format!("Pokemon {} already exists", u16::from(pokemon.number)),
+            });
+        } else {
+            existing.push(pokemon.number.clone());
+            pokemons.push(pokemon);
+        }
+    }
+
+    if let (ConflictMode::Abort, false) = (&req.on_conflict, conflicts.is_empty()) {
+        return Err(Error::Conflict(conflicts));
+    }
+
+    let imported = pokemons
+        .iter()
+        .map(|pokemon| u16::from(pokemon.number.clone()))
+        .collect::<Vec<u16>>();
+
+    if req.dry_run || pokemons.is_empty() {
+        return Ok(Response { imported, skipped });
+    }
+
+    match repo.insert_batch(pokemons) {
+        Ok(_) => Ok(Response { imported, skipped }),
+        // Another client inserted one of the numbers since they were checked.
+        Err(InsertError::Conflict) => Err(Error::Conflict(vec![])),
+        Err(InsertError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    const CSV: &str = "number,name,types\n25,Pikachu,Electric\n4,Charmander,Fire\n";
+
+    impl Request {
+        fn csv(content: &str, on_conflict: ConflictMode) -> Self {
+            Self {
+                format: Format::Csv,
+                content: String::from(content),
+                dry_run: false,
+                on_conflict,
+            }
+        }
+    }
+
+    fn insert_pikachu(repo: &dyn Repository) {
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+    }
+
+    #[test]
+    fn it_should_import_every_row() {
+        let repo = Arc::new(InMemoryRepository::new());
+
+        let res = execute(repo.clone(), Request::csv(CSV, ConflictMode::Abort));
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.imported, vec![25, 4]);
+                assert!(res.skipped.is_empty());
+            }
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 2),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_report_every_invalid_row_with_its_line_and_import_nothing() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let content =
+            "number,name,types\n25,Pikachu,Electric\n0,Missingno,Fire\n4,,Fire\n5,X,Water\n";
+
+        let res = execute(repo.clone(), Request::csv(content, ConflictMode::Abort));
+
+        match res {
+            Err(Error::InvalidRows(errors)) => assert_eq!(
+                errors.iter().map(|e| e.line).collect::<Vec<usize>>(),
+                vec![3, 4, 5]
+            ),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_the_file_cannot_be_read() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request {
+            format: Format::Json,
+            content: String::from("not json"),
+            dry_run: false,
+            on_conflict: ConflictMode::Abort,
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_skip_conflicting_rows_when_asked_to() {
+        let repo = Arc::new(InMemoryRepository::new());
+        insert_pikachu(&*repo);
+
+        let res = execute(repo.clone(), Request::csv(CSV, ConflictMode::Skip));
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.imported, vec![4]);
+                assert_eq!(res.skipped, vec![25]);
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_abort_on_conflicting_rows_otherwise() {
+        let repo = Arc::new(InMemoryRepository::new());
+        insert_pikachu(&*repo);
+
+        let res = execute(repo.clone(), Request::csv(CSV, ConflictMode::Abort));
+
+        match res {
+            Err(Error::Conflict(conflicts)) => {
+                assert_eq!(
+                    conflicts.iter().map(|c| c.line).collect::<Vec<usize>>(),
+                    vec![2]
+                )
+            }
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 1),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_not_write_anything_during_a_dry_run() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request {
+            dry_run: true,
+            ..Request::csv(CSV, ConflictMode::Abort)
+        };
+
+        let res = execute(repo.clone(), req);
+
+        match res {
+            Ok(res) => assert_eq!(res.imported, vec![25, 4]),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+
+        let res = execute(repo, Request::csv(CSV, ConflictMode::Abort));
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+}
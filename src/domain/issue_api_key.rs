@@ -0,0 +1,127 @@
+use crate::repositories::api_key_store::{self, ApiKey, ApiKeyStore, InsertKeyError, Role};
+use chrono::Utc;
+use rand::RngCore;
+use std::sync::Arc;
+
+const PREFIX: &str = "pdx_";
+
+pub struct Request {
+    pub name: String,
+    pub role: String,
+}
+
+pub struct Response {
+    pub name: String,
+    pub role: String,
+    /// The key itself, which only its hash is kept of.
+    pub key: String,
+}
+
+pub enum Error {
+    BadRequest,
+    Conflict,
+    Unknown,
+}
+
+pub fn execute(store: Arc<dyn ApiKeyStore>, req: Request) -> Result<Response, Error> {
+    let name = String::from(req.name.trim());
+
+    let role = match Role::try_from(req.role.as_str()) {
+        Ok(role) if !name.is_empty() => role,
+        _ => return Err(Error::BadRequest),
+    };
+
+    let key = generate();
+
+    match store.insert(
+        ApiKey {
+            name: name.clone(),
+            role,
+            created_at: Utc::now(),
+        },
+        api_key_store::hash(&key),
+    ) {
+        Ok(()) => Ok(Response {
+            name,
+            role: String::from(<&str>::from(role)),
+            key,
+        }),
+        Err(InsertKeyError::Conflict) => Err(Error::Conflict),
+        Err(InsertKeyError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+/// 256 random bits, hex encoded behind a prefix which makes leaked keys easy to spot.
+fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let secret: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", PREFIX, secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::sqlite_api_key_store::SqliteApiKeyStore;
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_the_role_is_unknown() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let req = Request {
+            name: String::from("misty"),
+            role: String::from("owner"),
+        };
+
+        let res = execute(Arc::new(store), req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_conflict_error_when_the_name_is_taken() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        let req = || Request {
+            name: String::from("misty"),
+            role: String::from("reader"),
+        };
+        execute(store.clone(), req()).ok();
+
+        let res = execute(store, req());
+
+        match res {
+            Err(Error::Conflict) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_store_only_the_hash_of_the_key_otherwise() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        let req = Request {
+            name: String::from("misty"),
+            role: String::from("editor"),
+        };
+
+        let res = execute(store.clone(), req);
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.name, "misty");
+                assert_eq!(res.role, "editor");
+                assert!(res.key.starts_with(PREFIX));
+                assert!(matches!(store.fetch_by_hash(&res.key), Ok(None)));
+                match store.fetch_by_hash(&api_key_store::hash(&res.key)) {
+                    Ok(Some(key)) => assert_eq!(key.role, Role::Editor),
+                    _ => unreachable!(),
+                };
+            }
+            _ => unreachable!(),
+        };
+    }
+}
@@ -1,5 +1,16 @@
+pub mod authorize;
+pub mod check_health;
 pub mod create_pokemon;
 pub mod delete_pokemon;
 pub mod entities;
+pub mod export_pokemons;
 pub mod fetch_all_pokemons;
+pub mod fetch_audit_events;
 pub mod fetch_pokemon;
+pub mod fetch_pokemons_as_of;
+pub mod import_pokemons;
+pub mod issue_api_key;
+pub mod purge_pokemons;
+pub mod reconcile_pokemons;
+pub mod restore_pokemon;
+pub mod revoke_api_key;
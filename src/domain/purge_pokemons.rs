@@ -0,0 +1,122 @@
+use crate::repositories::{PurgeError, Repository};
+use chrono::{TimeDelta, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct Request {
+    /// How long a deleted Pokemon stays restorable.
+    pub older_than: Duration,
+}
+
+pub struct Response {
+    pub purged: usize,
+}
+
+pub enum Error {
+    BadRequest,
+    Unknown,
+}
+
+/// Permanently removes the Pokemons deleted more than `older_than` ago.
+pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Response, Error> {
+    let deleted_before = match TimeDelta::from_std(req.older_than)
+        .ok()
+        .and_then(|age| Utc::now().checked_sub_signed(age))
+    {
+        Some(deleted_before) => deleted_before,
+        None => return Err(Error::BadRequest),
+    };
+
+    match repo.purge(deleted_before) {
+        Ok(purged) => Ok(Response {
+            purged: purged.len(),
+        }),
+        Err(PurgeError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonNumber, PokemonTypes};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+    use crate::repositories::RestoreError;
+
+    fn delete_pikachu(repo: &dyn Repository) {
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+    }
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+        let req = Request {
+            older_than: Duration::ZERO,
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_the_age_is_out_of_range() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request {
+            older_than: Duration::MAX,
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_keep_the_pokemons_deleted_more_recently() {
+        let repo = Arc::new(InMemoryRepository::new());
+        delete_pikachu(&*repo);
+        let req = Request {
+            older_than: Duration::from_secs(3600),
+        };
+
+        let res = execute(repo.clone(), req);
+
+        match res {
+            Ok(res) => assert_eq!(res.purged, 0),
+            _ => unreachable!(),
+        };
+        assert!(repo.restore(PokemonNumber::pikachu()).is_ok());
+    }
+
+    #[test]
+    fn it_should_purge_the_older_ones() {
+        let repo = Arc::new(InMemoryRepository::new());
+        delete_pikachu(&*repo);
+        std::thread::sleep(Duration::from_millis(10));
+        let req = Request {
+            older_than: Duration::from_millis(5),
+        };
+
+        let res = execute(repo.clone(), req);
+
+        match res {
+            Ok(res) => assert_eq!(res.purged, 1),
+            _ => unreachable!(),
+        };
+        match repo.restore(PokemonNumber::pikachu()) {
+            Err(RestoreError::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+}
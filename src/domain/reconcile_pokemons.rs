@@ -0,0 +1,146 @@
+use crate::repositories::{FetchAllError, Repository};
+use std::sync::Arc;
+
+/// Numbers of the Pokemons on which two repositories meant to hold the same Pokedex disagree.
+pub struct Response {
+    pub missing_from_secondary: Vec<u16>,
+    pub missing_from_primary: Vec<u16>,
+    pub different: Vec<u16>,
+}
+
+pub enum Error {
+    Unknown,
+}
+
+pub fn execute(
+    primary: Arc<dyn Repository>,
+    secondary: Arc<dyn Repository>,
+) -> Result<Response, Error> {
+    let (primary_pokemons, secondary_pokemons) = match (primary.fetch_all(), secondary.fetch_all())
+    {
+        (Ok(primary_pokemons), Ok(secondary_pokemons)) => (primary_pokemons, secondary_pokemons),
+        (Err(FetchAllError::Unknown), _) | (_, Err(FetchAllError::Unknown)) => {
+            return Err(Error::Unknown)
+        }
+    };
+
+    let mut res = Response {
+        missing_from_secondary: vec![],
+        missing_from_primary: vec![],
+        different: vec![],
+    };
+
+    for pokemon in primary_pokemons.iter() {
+        match secondary_pokemons
+            .iter()
+            .find(|p| p.number == pokemon.number)
+        {
+            Some(mirrored) if mirrored == pokemon => {}
+            Some(_) => res.different.push(u16::from(pokemon.number.clone())),
+            None => res
+                .missing_from_secondary
+                .push(u16::from(pokemon.number.clone())),
+        }
+    }
+
+    for pokemon in secondary_pokemons.iter() {
+        if !primary_pokemons.iter().any(|p| p.number == pokemon.number) {
+            res.missing_from_primary
+                .push(u16::from(pokemon.number.clone()));
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::entities::{PokemonName, PokemonNumber, PokemonTypes},
+        repositories::in_memory_repository::InMemoryRepository,
+    };
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new().with_error());
+
+        let res = execute(primary, secondary);
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_no_differences_when_both_repositories_agree() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+        for repo in [&primary, &secondary] {
+            repo.insert(
+                PokemonNumber::pikachu(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        }
+
+        let res = execute(primary, secondary);
+
+        match res {
+            Ok(res) => {
+                assert!(res.missing_from_secondary.is_empty());
+                assert!(res.missing_from_primary.is_empty());
+                assert!(res.different.is_empty());
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_differences_otherwise() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+        primary
+            .insert(
+                PokemonNumber::pikachu(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        primary
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            )
+            .ok();
+        secondary
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        secondary
+            .insert(
+                PokemonNumber::try_from(150).unwrap(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+
+        let res = execute(primary, secondary);
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.missing_from_secondary, vec![25]);
+                assert_eq!(res.missing_from_primary, vec![150]);
+                assert_eq!(res.different, vec![4]);
+            }
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,145 @@
+use crate::domain::entities::PokemonNumber;
+use crate::repositories::{Repository, RestoreError};
+use std::sync::Arc;
+
+pub struct Request {
+    pub number: u16,
+    /// The versions the Pokemon may have been deleted at, one of which it must still be at to be
+    /// restored.
+    pub versions: Option<Vec<u64>>,
+}
+
+pub struct Response {
+    pub number: u16,
+    pub name: String,
+    pub types: Vec<String>,
+    pub version: u64,
+}
+
+pub enum Error {
+    BadRequest,
+    NotFound,
+    VersionMismatch,
+    Unknown,
+}
+
+pub fn execute(repo: Arc<dyn Repository>, req: Request) -> Result<Response, Error> {
+    match PokemonNumber::try_from(req.number) {
+        Ok(number) => match repo.conditional_restore(number, req.versions.as_deref()) {
+            Ok(p) => Ok(Response {
+                number: u16::from(p.number),
+                name: String::from(p.name),
+                types: Vec::<String>::from(p.types),
+                version: p.version,
+            }),
+            Err(RestoreError::NotFound) => Err(Error::NotFound),
+            Err(RestoreError::VersionMismatch) => Err(Error::VersionMismatch),
+            Err(RestoreError::Unknown) => Err(Error::Unknown),
+        },
+        _ => Err(Error::BadRequest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonTypes};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    impl Request {
+        fn new(number: PokemonNumber) -> Self {
+            Self {
+                number: u16::from(number),
+                versions: None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_return_an_unknown_error_when_an_unexpected_error_happens() {
+        let repo = Arc::new(InMemoryRepository::new().with_error());
+        let req = Request::new(PokemonNumber::pikachu());
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_bad_request_error_when_request_is_invalid() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request::new(PokemonNumber::bad());
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::BadRequest) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_not_found_error_when_the_pokemon_was_not_deleted() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let req = Request::new(PokemonNumber::pikachu());
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_a_version_mismatch_error_when_the_pokemon_was_deleted_at_another_version() {
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        let req = Request {
+            versions: Some(vec![2]),
+            ..Request::new(PokemonNumber::pikachu())
+        };
+
+        let res = execute(repo, req);
+
+        match res {
+            Err(Error::VersionMismatch) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_the_restored_pokemon_otherwise() {
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        let req = Request::new(PokemonNumber::pikachu());
+
+        let res = execute(repo.clone(), req);
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.number, u16::from(PokemonNumber::pikachu()));
+                assert_eq!(res.name, String::from(PokemonName::pikachu()));
+                assert_eq!(res.types, Vec::<String>::from(PokemonTypes::pikachu()));
+                assert_eq!(res.version, 2);
+            }
+            _ => unreachable!(),
+        };
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+}
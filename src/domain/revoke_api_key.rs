@@ -0,0 +1,65 @@
+use crate::repositories::api_key_store::{ApiKeyStore, RevokeKeyError};
+use std::sync::Arc;
+
+pub struct Request {
+    pub name: String,
+}
+
+pub enum Error {
+    NotFound,
+    Unknown,
+}
+
+pub fn execute(store: Arc<dyn ApiKeyStore>, req: Request) -> Result<(), Error> {
+    match store.revoke(req.name.trim()) {
+        Ok(()) => Ok(()),
+        Err(RevokeKeyError::NotFound) => Err(Error::NotFound),
+        Err(RevokeKeyError::Unknown) => Err(Error::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::issue_api_key;
+    use crate::repositories::sqlite_api_key_store::SqliteApiKeyStore;
+
+    #[test]
+    fn it_should_return_a_not_found_error_when_there_is_no_such_key() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let req = Request {
+            name: String::from("misty"),
+        };
+
+        let res = execute(Arc::new(store), req);
+
+        match res {
+            Err(Error::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_return_ok_otherwise() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let store = Arc::new(store);
+        issue_api_key::execute(
+            store.clone(),
+            issue_api_key::Request {
+                name: String::from("misty"),
+                role: String::from("admin"),
+            },
+        )
+        .ok();
+        let req = Request {
+            name: String::from("misty"),
+        };
+
+        let res = execute(store, req);
+
+        match res {
+            Ok(()) => {}
+            _ => unreachable!(),
+        };
+    }
+}
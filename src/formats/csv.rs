@@ -0,0 +1,141 @@
+use super::{Line, PokemonRecord, Row, Types};
+use std::io;
+
+const HEADER: [&str; 3] = ["number", "name", "types"];
+
+/// Reads a file with a `number,name,types` header, such as `25,Pikachu,Fire;Electric`. Types
+/// may also be written as a JSON array, such as `["Fire","Electric"]`.
+pub fn read(content: &str) -> Result<Vec<Line>, ()> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .trim(::csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        _ => return Err(()),
+    };
+
+    if HEADER
+        .iter()
+        .any(|column| !headers.iter().any(|header| header == *column))
+    {
+        return Err(());
+    }
+
+    let mut lines = vec![];
+
+    for res in reader.records() {
+        let line = match res {
+            Ok(record) => Line {
+                line: line_at(content, record.position()),
+                record: match record.deserialize::<CsvRow>(Some(&headers)) {
+                    Ok(row) => Ok(PokemonRecord::from(Row {
+                        number: row.number,
+                        name: row.name,
+                        types: match serde_json::from_str::<Vec<String>>(&row.types) {
+                            Ok(types) => Types::Array(types),
+                            _ => Types::Flattened(row.types),
+                        },
+                    })),
+                    Err(err) => Err(match err.kind() {
+                        ::csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+                        _ => err.to_string(),
+                    }),
+                },
+            },
+            Err(err) => Line {
+                line: line_at(content, err.position()),
+                record: Err(err.to_string()),
+            },
+        };
+
+        lines.push(line);
+    }
+
+    Ok(lines)
+}
+
+/// Types have a single column, holding either layout.
+#[derive(serde::Deserialize)]
+struct CsvRow {
+    number: u16,
+    name: String,
+    types: String,
+}
+
+pub fn write_header(buffer: &mut Vec<u8>) -> io::Result<()> {
+    let mut writer = ::csv::Writer::from_writer(buffer);
+    writer.write_record(HEADER)?;
+    writer.flush()
+}
+
+pub fn write(buffer: &mut Vec<u8>, row: &Row, first: bool) -> io::Result<()> {
+    if first {
+        write_header(buffer)?;
+    }
+
+    let types = match &row.types {
+        Types::Flattened(types) => types.clone(),
+        Types::Array(types) => serde_json::to_string(types)?,
+    };
+
+    let mut writer = ::csv::Writer::from_writer(buffer);
+    writer.write_record([row.number.to_string(), row.name.clone(), types])?;
+    writer.flush()
+}
+
+/// The line numbers counted by the reader skip blank lines, so they are derived from the byte
+/// offset instead, which points before the blank lines preceding the record.
+fn line_at(content: &str, position: Option<&::csv::Position>) -> usize {
+    let offset = match position {
+        Some(position) => (position.byte() as usize).min(content.len()),
+        None => return 0,
+    };
+
+    let start = match content[offset..].find(|c| c != '\r' && c != '\n') {
+        Some(skipped) => offset + skipped,
+        None => offset,
+    };
+
+    content[..start].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_the_records_with_their_line_numbers() {
+        let content = "number,name,types\n25,Pikachu,Electric\n\n4,Charmander,Fire;Electric\n";
+
+        let lines = read(content).unwrap();
+
+        assert_eq!(
+            lines.iter().map(|line| line.line).collect::<Vec<usize>>(),
+            vec![2, 4]
+        );
+        match &lines[1].record {
+            Ok(record) => {
+                assert_eq!(record.number, 4);
+                assert_eq!(record.name, "Charmander");
+                assert_eq!(record.types, vec!["Fire", "Electric"]);
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_report_rows_which_cannot_be_read() {
+        let content = "number,name,types\nabc,Pikachu,Electric\n";
+
+        let lines = read(content).unwrap();
+
+        assert_eq!(lines[0].line, 2);
+        assert!(lines[0].record.is_err());
+    }
+
+    #[test]
+    fn it_should_fail_when_a_column_is_missing() {
+        assert!(read("number,name\n25,Pikachu\n").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Line, PokemonRecord, Row};
+use serde_json::value::RawValue;
+use std::io::{self, Write};
+
+/// Reads an array of `{ "number": 25, "name": "Pikachu", "types": ["Electric"] }` objects.
+pub fn read(content: &str) -> Result<Vec<Line>, ()> {
+    // Keeping every element raw tells where it starts in the file, and lets a malformed element
+    // be reported without rejecting the others.
+    let elements = match serde_json::from_str::<Vec<&RawValue>>(content) {
+        Ok(elements) => elements,
+        _ => return Err(()),
+    };
+
+    Ok(elements
+        .into_iter()
+        .map(|element| {
+            let offset = element.get().as_ptr() as usize - content.as_ptr() as usize;
+            Line {
+                line: content[..offset].matches('\n').count() + 1,
+                record: match serde_json::from_str::<Row>(element.get()) {
+                    Ok(row) => Ok(PokemonRecord::from(row)),
+                    Err(err) => Err(err.to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+/// Writes one element of a pretty printed array, opening the array before the first one.
+pub fn write(buffer: &mut Vec<u8>, row: &Row, first: bool) -> io::Result<()> {
+    buffer.write_all(if first { b"[\n" } else { b",\n" })?;
+
+    let element = serde_json::to_string_pretty(row)?;
+    for (index, line) in element.lines().enumerate() {
+        if index > 0 {
+            buffer.write_all(b"\n")?;
+        }
+        write!(buffer, "  {}", line)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_end(buffer: &mut Vec<u8>, empty: bool) {
+    buffer.extend_from_slice(if empty { b"[]\n" } else { b"\n]\n" });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_the_records_with_their_line_numbers() {
+        let content = r#"[
+            { "number": 25, "name": "Pikachu", "types": ["Electric"] },
+
+            {
+                "number": 4,
+                "name": "Charmander",
+                "types": "Fire"
+            },
+            { "number": "x" }
+        ]"#;
+
+        let lines = read(content).unwrap();
+
+        assert_eq!(
+            lines.iter().map(|line| line.line).collect::<Vec<usize>>(),
+            vec![2, 4, 9]
+        );
+        match &lines[1].record {
+            Ok(record) => {
+                assert_eq!(record.name, "Charmander");
+                assert_eq!(record.types, vec!["Fire"]);
+            }
+            _ => unreachable!(),
+        };
+        assert!(lines[2].record.is_err());
+    }
+
+    #[test]
+    fn it_should_fail_when_the_file_is_not_an_array() {
+        assert!(read(r#"{ "number": 25 }"#).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! File formats the Pokedex can be imported from and exported to.
+
+mod csv;
+mod json;
+mod ndjson;
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+
+/// Flattened types are joined by this character, as in `Fire;Electric`.
+const TYPES_SEPARATOR: char = ';';
+
+/// A Pokemon as written in a file, before any validation.
+pub struct PokemonRecord {
+    pub number: u16,
+    pub name: String,
+    pub types: Vec<String>,
+}
+
+/// A record along with the line it starts on, or why it could not be read.
+pub struct Line {
+    pub line: usize,
+    pub record: Result<PokemonRecord, String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl TryFrom<&str> for Format {
+    type Error = ();
+
+    fn try_from(format: &str) -> Result<Self, Self::Error> {
+        match format {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+        }
+    }
+
+    /// CSV has no arrays, so its types are flattened unless asked otherwise.
+    fn default_layout(&self) -> TypesLayout {
+        match self {
+            Self::Csv => TypesLayout::Flattened,
+            Self::Json | Self::Ndjson => TypesLayout::Array,
+        }
+    }
+}
+
+/// How the types of a Pokemon are written.
+#[derive(Clone, Copy)]
+pub enum TypesLayout {
+    /// A single string, such as `"Fire;Electric"`.
+    Flattened,
+    /// An array, such as `["Fire", "Electric"]`.
+    Array,
+}
+
+impl TryFrom<&str> for TypesLayout {
+    type Error = ();
+
+    fn try_from(layout: &str) -> Result<Self, Self::Error> {
+        match layout {
+            "flattened" => Ok(Self::Flattened),
+            "array" => Ok(Self::Array),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Types as found in a file, where either layout is accepted.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Types {
+    Array(Vec<String>),
+    Flattened(String),
+}
+
+impl Types {
+    fn new(types: Vec<String>, layout: TypesLayout) -> Self {
+        match layout {
+            TypesLayout::Flattened => Self::Flattened(types.join(&TYPES_SEPARATOR.to_string())),
+            TypesLayout::Array => Self::Array(types),
+        }
+    }
+
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::Array(types) => types,
+            Self::Flattened(types) => types
+                .split(TYPES_SEPARATOR)
+                .map(|t| String::from(t.trim()))
+                .filter(|t| !t.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Row {
+    number: u16,
+    name: String,
+    types: Types,
+}
+
+impl From<Row> for PokemonRecord {
+    fn from(row: Row) -> Self {
+        Self {
+            number: row.number,
+            name: row.name,
+            types: row.types.into_vec(),
+        }
+    }
+}
+
+impl Row {
+    fn new(record: PokemonRecord, layout: TypesLayout) -> Self {
+        Self {
+            number: record.number,
+            name: record.name,
+            types: Types::new(record.types, layout),
+        }
+    }
+}
+
+/// Reads every record of the file, failing only when the file as a whole cannot be read.
+pub fn read(format: Format, content: &str) -> Result<Vec<Line>, ()> {
+    match format {
+        Format::Csv => csv::read(content),
+        Format::Json => json::read(content),
+        Format::Ndjson => ndjson::read(content),
+    }
+}
+
+/// Writes records in a format as they are read, so that a large export is never held in memory
+/// all at once.
+pub struct Exporter {
+    format: Format,
+    layout: TypesLayout,
+    records: Box<dyn Iterator<Item = PokemonRecord> + Send>,
+    buffer: Vec<u8>,
+    position: usize,
+    written: usize,
+    finished: bool,
+}
+
+impl Exporter {
+    pub fn new(
+        format: Format,
+        layout: Option<TypesLayout>,
+        records: impl Iterator<Item = PokemonRecord> + Send + 'static,
+    ) -> Self {
+        Self {
+            format,
+            layout: layout.unwrap_or_else(|| format.default_layout()),
+            records: Box::new(records),
+            buffer: vec![],
+            position: 0,
+            written: 0,
+            finished: false,
+        }
+    }
+
+    /// Replaces the buffer with the next chunk of output, returning false once there is none.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.finished {
+            return Ok(false);
+        }
+
+        self.buffer.clear();
+        self.position = 0;
+
+        match self.records.next() {
+            Some(record) => {
+                let row = Row::new(record, self.layout);
+                match self.format {
+                    Format::Csv => csv::write(&mut self.buffer, &row, self.written == 0)?,
+                    Format::Json => json::write(&mut self.buffer, &row, self.written == 0)?,
+                    Format::Ndjson => ndjson::write(&mut self.buffer, &row)?,
+                }
+                self.written += 1;
+            }
+            None => {
+                match self.format {
+                    Format::Csv if self.written == 0 => csv::write_header(&mut self.buffer)?,
+                    Format::Json => json::write_end(&mut self.buffer, self.written == 0),
+                    _ => {}
+                }
+                self.finished = true;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Read for Exporter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<PokemonRecord> {
+        vec![
+            PokemonRecord {
+                number: 4,
+                name: String::from("Charmander"),
+                types: vec![String::from("Fire")],
+            },
+            PokemonRecord {
+                number: 25,
+                name: String::from("Pikachu"),
+                types: vec![String::from("Electric"), String::from("Fire")],
+            },
+        ]
+    }
+
+    fn export(format: Format, layout: Option<TypesLayout>, records: Vec<PokemonRecord>) -> String {
+        let mut output = String::new();
+        Exporter::new(format, layout, records.into_iter())
+            .read_to_string(&mut output)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn it_should_export_csv_with_flattened_types() {
+        assert_eq!(
+            export(Format::Csv, None, records()),
+            "number,name,types\n4,Charmander,Fire\n25,Pikachu,Electric;Fire\n"
+        );
+    }
+
+    #[test]
+    fn it_should_export_csv_with_types_as_arrays() {
+        assert_eq!(
+            export(Format::Csv, Some(TypesLayout::Array), records()),
+            "number,name,types\n4,Charmander,\"[\"\"Fire\"\"]\"\n25,Pikachu,\"[\"\"Electric\"\",\"\"Fire\"\"]\"\n"
+        );
+    }
+
+    #[test]
+    fn it_should_export_pretty_json() {
+        assert_eq!(
+            export(Format::Json, Some(TypesLayout::Flattened), records()),
+            "[\n  {\n    \"number\": 4,\n    \"name\": \"Charmander\",\n    \"types\": \"Fire\"\n  },\n  {\n    \"number\": 25,\n    \"name\": \"Pikachu\",\n    \"types\": \"Electric;Fire\"\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn it_should_export_newline_delimited_json() {
+        assert_eq!(
+            export(Format::Ndjson, None, records()),
+            "{\"number\":4,\"name\":\"Charmander\",\"types\":[\"Fire\"]}\n{\"number\":25,\"name\":\"Pikachu\",\"types\":[\"Electric\",\"Fire\"]}\n"
+        );
+    }
+
+    #[test]
+    fn it_should_export_an_empty_pokedex() {
+        assert_eq!(export(Format::Csv, None, vec![]), "number,name,types\n");
+        assert_eq!(export(Format::Json, None, vec![]), "[]\n");
+        assert_eq!(export(Format::Ndjson, None, vec![]), "");
+    }
+
+    #[test]
+    fn it_should_read_back_what_it_exported() {
+        for format in [Format::Csv, Format::Json, Format::Ndjson] {
+            for layout in [TypesLayout::Flattened, TypesLayout::Array] {
+                let lines = read(format, &export(format, Some(layout), records())).unwrap();
+
+                let types = lines
+                    .into_iter()
+                    .map(|line| line.record.ok().unwrap().types)
+                    .collect::<Vec<Vec<String>>>();
+                assert_eq!(types, vec![vec!["Fire"], vec!["Electric", "Fire"]]);
+            }
+        }
+    }
+}
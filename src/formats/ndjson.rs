@@ -0,0 +1,43 @@
+use super::{Line, PokemonRecord, Row};
+use std::io::{self, Write};
+
+/// Reads one `{ "number": 25, "name": "Pikachu", "types": ["Electric"] }` object per line,
+/// ignoring blank lines.
+pub fn read(content: &str) -> Result<Vec<Line>, ()> {
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| Line {
+            line: index + 1,
+            record: match serde_json::from_str::<Row>(text) {
+                Ok(row) => Ok(PokemonRecord::from(row)),
+                Err(err) => Err(err.to_string()),
+            },
+        })
+        .collect())
+}
+
+pub fn write(buffer: &mut Vec<u8>, row: &Row) -> io::Result<()> {
+    serde_json::to_writer(&mut *buffer, row)?;
+    buffer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_one_record_per_line() {
+        let content = "{\"number\":25,\"name\":\"Pikachu\",\"types\":[\"Electric\"]}\n\nnot json\n";
+
+        let lines = read(content).unwrap();
+
+        assert_eq!(
+            lines.iter().map(|line| line.line).collect::<Vec<usize>>(),
+            vec![1, 3]
+        );
+        assert!(lines[0].record.is_ok());
+        assert!(lines[1].record.is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How log lines are laid out.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Logfmt,
+}
+
+impl TryFrom<&str> for Format {
+    type Error = ();
+
+    fn try_from(format: &str) -> Result<Self, Self::Error> {
+        match format {
+            "json" => Ok(Self::Json),
+            "logfmt" => Ok(Self::Logfmt),
+            _ => Err(()),
+        }
+    }
+}
+
+thread_local! {
+    /// The request being served by the current thread, added to every line logged meanwhile.
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` on behalf of a request, so that the lines it logs can be told apart from the lines
+/// logged for other requests.
+pub fn with_request_id<T>(request_id: &str, f: impl FnOnce() -> T) -> T {
+    let previous = REQUEST_ID.with(|id| id.replace(Some(String::from(request_id))));
+    let result = f();
+    REQUEST_ID.with(|id| id.replace(previous));
+    result
+}
+
+/// Writes one structured line per event, to stderr unless told otherwise.
+pub struct Logger {
+    format: Format,
+    sink: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Logger {
+    pub fn new(format: Format) -> Self {
+        Self::with_sink(format, Box::new(io::stderr()))
+    }
+
+    pub fn with_sink(format: Format, sink: Box<dyn Write + Send>) -> Self {
+        Self {
+            format,
+            sink: Mutex::new(sink),
+        }
+    }
+
+    /// Logs `fields` after the time, level, message and request id.
+    pub fn log(&self, level: &str, msg: &str, fields: Vec<(&str, Value)>) {
+        let mut line = vec![
+            (
+                "ts",
+                Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            ),
+            ("level", Value::from(level)),
+            ("msg", Value::from(msg)),
+        ];
+        if let Some(request_id) = REQUEST_ID.with(|id| id.borrow().clone()) {
+            line.push(("request_id", Value::from(request_id)));
+        }
+        line.extend(fields);
+
+        let mut rendered = match self.format {
+            Format::Json => Value::Object(
+                line.into_iter()
+                    .map(|(key, value)| (String::from(key), value))
+                    .collect::<Map<String, Value>>(),
+            )
+            .to_string(),
+            Format::Logfmt => line
+                .into_iter()
+                .map(|(key, value)| format!("{}={}", key, logfmt(value)))
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+        rendered.push('\n');
+
+        let mut sink = match self.sink.lock() {
+            Ok(sink) => sink,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // Logging must never fail what is being logged.
+        sink.write_all(rendered.as_bytes()).ok();
+    }
+
+    /// Writes out whatever the sink buffered, before exiting.
+    pub fn flush(&self) {
+        let mut sink = match self.sink.lock() {
+            Ok(sink) => sink,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        sink.flush().ok();
+    }
+}
+
+/// Reports a failure which does not fail what is being done, through `logger` when there is one
+/// and on stderr otherwise, so that it is never lost.
+pub fn report(logger: Option<&Logger>, level: &str, msg: &str, fields: Vec<(&str, Value)>) {
+    match logger {
+        Some(logger) => logger.log(level, msg, fields),
+        None => eprintln!("{}", msg),
+    }
+}
+
+/// Milliseconds with a microsecond precision, which is what latencies are logged in.
+pub fn millis(duration: Duration) -> Value {
+    Value::from((duration.as_secs_f64() * 1_000_000.0).round() / 1000.0)
+}
+
+/// Quotes the values which would otherwise be split or mistaken for another key.
+fn logfmt(value: Value) -> String {
+    match value {
+        Value::String(string)
+            if string.is_empty()
+                || string
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '=' || c == '"') =>
+        {
+            Value::String(string).to_string()
+        }
+        Value::String(string) => string,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Keeps the logged lines around so that tests can read them back.
+    #[derive(Clone, Default)]
+    pub struct Lines(Arc<Mutex<Vec<u8>>>);
+
+    impl Lines {
+        pub fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_log_json_lines_with_the_request_id() {
+        let lines = Lines::default();
+        let logger = Logger::with_sink(Format::Json, Box::new(lines.clone()));
+
+        with_request_id("abc", || {
+            logger.log("info", "request", vec![("status", Value::from(200))])
+        });
+        logger.log("info", "request", vec![]);
+
+        let lines = lines.lines();
+        let first: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(first["request_id"], "abc");
+        assert_eq!(first["status"], 200);
+        let second: Value = serde_json::from_str(&lines[1]).unwrap();
+        assert!(second.get("request_id").is_none());
+    }
+
+    #[test]
+    fn it_should_quote_the_logfmt_values_which_need_it() {
+        let lines = Lines::default();
+        let logger = Logger::with_sink(Format::Logfmt, Box::new(lines.clone()));
+
+        logger.log(
+            "error",
+            "span",
+            vec![
+                ("name", Value::from("fetch_one")),
+                ("detail", Value::from("not found")),
+                ("duration_ms", millis(Duration::from_micros(1500))),
+            ],
+        );
+
+        assert!(lines.lines()[0].ends_with(
+            r#"level=error msg=span name=fetch_one detail="not found" duration_ms=1.5"#
+        ));
+    }
+}
@@ -1,13 +1,35 @@
-use clap::{Arg, Command, Values};
+use clap::{Arg, ArgMatches, Command};
+use cli::output::Output;
+use formats::{Format, TypesLayout};
+use logging::Logger;
+use metrics::Metrics;
 use repositories::{
-    airtable_repository::AirtableRepository, in_memory_repository::InMemoryRepository,
-    sqlite_repository::SqliteRepository, Repository,
+    airtable_repository::{AirtableConfig, AirtableFieldMapping, AirtableRepository},
+    api_key_store::ApiKeyStore,
+    audit_log::AuditLog,
+    audited_repository::AuditedRepository,
+    cached_repository::CachedRepository,
+    event_sourced_repository::{EventSourcedRepository, History},
+    in_memory_repository::InMemoryRepository,
+    instrumented_repository::InstrumentedRepository,
+    json_file_repository::JsonFileRepository,
+    json_lines_audit_log::JsonLinesAuditLog,
+    mirrored_repository::{MirroredRepository, SecondaryFailurePolicy},
+    sqlite_api_key_store::SqliteApiKeyStore,
+    sqlite_audit_log::SqliteAuditLog,
+    sqlite_repository::SqliteRepository,
+    Repository,
 };
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::Duration;
 
 mod api;
 mod cli;
 mod domain;
+mod formats;
+mod logging;
+mod metrics;
 mod repositories;
 
 #[macro_use]
@@ -18,43 +40,739 @@ extern crate serde;
 #[macro_use]
 extern crate clap;
 
-fn main() {
+fn main() -> ExitCode {
     let matches = Command::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
         .arg(Arg::new("cli").long("cli").help("Runs in CLI mode"))
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .possible_values(["table", "json", "yaml", "csv"])
+                .default_value("table")
+                .global(true)
+                .help("How the CLI prints results"),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .possible_values(["json", "logfmt"])
+                .global(true)
+                .help("Logs every API request and repository call to stderr in this format"),
+        )
+        .arg(
+            Arg::new("listen")
+                .long("listen")
+                .value_name("ADDRESS")
+                .default_value("localhost:8000")
+                .help("Where the API listens"),
+        )
+        .arg(
+            Arg::new("shutdown-timeout")
+                .long("shutdown-timeout")
+                .value_name("SECONDS")
+                .validator(|v| v.parse::<u64>())
+                .default_value("30")
+                .help("How long the API keeps serving the requests in flight once sent SIGINT or SIGTERM"),
+        )
         .arg(Arg::new("sqlite").long("sqlite").value_name("PATH"))
+        .arg(Arg::new("json").long("json").value_name("PATH"))
+        .arg(
+            Arg::new("events")
+                .long("events")
+                .value_name("PATH")
+                .conflicts_with_all(&["sqlite", "json"])
+                .help("Stores the Pokedex as a log of events, which the API can read as of any point with ?as_of="),
+        )
+        .arg(
+            Arg::new("audit")
+                .long("audit")
+                .value_name("PATH")
+                .help("Records every change in this audit log, a JSON lines file when it ends with .jsonl and a SQLite database otherwise"),
+        )
+        .arg(
+            Arg::new("keys")
+                .long("keys")
+                .value_name("PATH")
+                .help("Requires API requests to carry a key from this SQLite database, as issued by the keys subcommand"),
+        )
+        .arg(
+            Arg::new("read-limit")
+                .long("read-limit")
+                .value_name("REQUESTS")
+                .validator(|v| v.parse::<u32>())
+                .help("Lets each API client, by key or else by address, send this many reads a minute"),
+        )
+        .arg(
+            Arg::new("write-limit")
+                .long("write-limit")
+                .value_name("REQUESTS")
+                .validator(|v| v.parse::<u32>())
+                .help("Lets each API client, by key or else by address, send this many writes a minute"),
+        )
+        .arg(
+            Arg::new("cors-origin")
+                .long("cors-origin")
+                .value_name("ORIGIN")
+                .multiple_occurrences(true)
+                .help("Lets browsers call the API from this origin, repeated for each of them or * for any"),
+        )
+        .arg(
+            Arg::new("cors-method")
+                .long("cors-method")
+                .value_name("METHOD")
+                .multiple_occurrences(true)
+                .requires("cors-origin")
+                .help("A method browsers may use from other origins, repeated for each of them [default: GET POST DELETE]"),
+        )
+        .arg(
+            Arg::new("cors-header")
+                .long("cors-header")
+                .value_name("HEADER")
+                .multiple_occurrences(true)
+                .requires("cors-origin")
+                .help("A header browsers may send from other origins, repeated for each of them [default: Authorization Content-Type If-Match If-None-Match]"),
+        )
+        .arg(
+            Arg::new("cors-max-age")
+                .long("cors-max-age")
+                .value_name("SECONDS")
+                .validator(|v| v.parse::<u64>())
+                .requires("cors-origin")
+                .help("How long browsers may cache the answers to preflight requests [default: 600]"),
+        )
         .arg(
             Arg::new("airtable")
                 .long("airtable")
                 .value_names(&["API_KEY", "WORKSPACE_ID"]),
         )
+        .arg(
+            Arg::new("airtable-url")
+                .long("airtable-url")
+                .value_name("URL")
+                .requires("airtable")
+                .help("Base URL of the Airtable API [default: https://api.airtable.com/v0]"),
+        )
+        .arg(
+            Arg::new("airtable-table")
+                .long("airtable-table")
+                .value_name("TABLE")
+                .requires("airtable")
+                .help("Airtable table holding the Pokemons [default: pokemons]"),
+        )
+        .arg(
+            Arg::new("airtable-fields")
+                .long("airtable-fields")
+                .value_names(&["NUMBER", "NAME", "TYPES", "VERSION", "DELETED_AT"])
+                .min_values(3)
+                .requires("airtable")
+                .help("Airtable columns holding the number, name, types, version and deletion time [default: number name types version deleted_at]. The version column is only written once a Pokemon is restored or inserted again after being deleted, and the deletion time once one is deleted"),
+        )
+        .arg(
+            Arg::new("cache-ttl")
+                .long("cache-ttl")
+                .value_name("SECONDS")
+                .validator(|v| v.parse::<u64>())
+                .help("Caches reads from the repository for this long"),
+        )
+        .arg(
+            Arg::new("cache-size")
+                .long("cache-size")
+                .value_name("ENTRIES")
+                .validator(|v| v.parse::<usize>())
+                .default_value("1000")
+                .help("Maximum number of Pokemons cached individually"),
+        )
+        .arg(
+            Arg::new("mirror")
+                .long("mirror")
+                .value_name("POLICY")
+                .possible_values(["fail", "log", "queue"])
+                .requires("airtable")
+                .help("Mirrors writes to Airtable while reading from the local repository, handling Airtable failures with the given policy. The queue only lives in memory: writes still queued when the process exits are lost"),
+        )
+        .subcommand(Command::new("browse").about("Browses the Pokemons in a full-screen terminal UI"))
+        .subcommand(Command::new("list").about("Lists every Pokemon"))
+        .subcommand(
+            Command::new("get")
+                .about("Shows a Pokemon")
+                .arg(number_arg()),
+        )
+        .subcommand(
+            Command::new("create")
+                .about("Creates a Pokemon, prompting for the missing values in a terminal")
+                .arg(
+                    Arg::new("number")
+                        .long("number")
+                        .value_name("NUMBER")
+                        .validator(|v| v.parse::<u16>()),
+                )
+                .arg(Arg::new("name").long("name").value_name("NAME"))
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .multiple_occurrences(true)
+                        .help("A type of the Pokemon, repeated for each of them"),
+                ),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Deletes a Pokemon, which can be restored until it is purged")
+                .arg(number_arg())
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .help("Deletes without asking for a confirmation in a terminal"),
+                ),
+        )
+        .subcommand(
+            Command::new("restore")
+                .about("Restores a deleted Pokemon")
+                .arg(number_arg()),
+        )
+        .subcommand(
+            Command::new("purge")
+                .about("Permanently removes the Pokemons deleted long enough ago")
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .value_name("AGE")
+                        .required(true)
+                        .validator(|v| cli::parse_age(v).map_err(|_| "invalid age"))
+                        .help("Age of the deletions to purge, such as 90s, 15m, 12h, 30d or 2w"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Writes every Pokemon to the standard output")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(["csv", "json", "ndjson"])
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::new("types")
+                        .long("types")
+                        .value_name("LAYOUT")
+                        .possible_values(["flattened", "array"])
+                        .help("Writes the types as a single string or as an array [default: flattened in CSV, array otherwise]"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Imports Pokemons from a CSV or JSON file")
+                .arg(Arg::new("file").value_name("FILE").required(true))
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Validates the file without importing anything"),
+                )
+                .arg(
+                    Arg::new("skip-conflicts")
+                        .long("skip-conflicts")
+                        .help("Skips the Pokemons which already exist instead of aborting"),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Lists the changes recorded in the audit log, oldest first")
+                .arg(
+                    Arg::new("number")
+                        .long("number")
+                        .value_name("NUMBER")
+                        .validator(|v| v.parse::<u16>())
+                        .help("Only lists the changes to this Pokemon"),
+                ),
+        )
+        .subcommand(
+            Command::new("keys")
+                .about("Manages the keys API requests are authenticated with")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("issue")
+                        .about("Issues a key, which is only ever printed once")
+                        .arg(Arg::new("name").value_name("NAME").required(true))
+                        .arg(
+                            Arg::new("role")
+                                .long("role")
+                                .value_name("ROLE")
+                                .possible_values(["reader", "editor", "admin"])
+                                .default_value("reader")
+                                .help("Readers can only read, editors can also write and admins can also read the audit log"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("revoke")
+                        .about("Revokes a key, which is refused from then on")
+                        .arg(Arg::new("name").value_name("NAME").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("reconcile")
+                .about("Lists the differences between the local repository and Airtable, exiting with 4 when there are some"),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("Pushes the changes made to the SQLite repository to Airtable and pulls the ones made there"),
+        )
         .get_matches();
 
-    let repo = build_repo(matches.value_of("sqlite"), matches.values_of("airtable"));
+    let output = match Output::try_from(matches.value_of("output").unwrap_or_default()) {
+        Ok(output) => output,
+        _ => panic!("Invalid output format"),
+    };
 
-    match matches.occurrences_of("cli") {
-        0 => api::serve("localhost:8000", repo),
-        _ => cli::run(repo),
+    let telemetry = Telemetry {
+        logger: build_logger(&matches),
+        metrics: None,
+    };
+
+    let exit = match matches.subcommand() {
+        Some(("browse", _)) => cli::browse(build_repo(&matches, &telemetry)),
+        Some(("list", _)) => cli::list(build_repo(&matches, &telemetry), output),
+        Some(("get", get_matches)) => cli::get(
+            build_repo(&matches, &telemetry),
+            number_of(get_matches),
+            output,
+        ),
+        Some(("create", create_matches)) => cli::create(
+            build_repo(&matches, &telemetry),
+            create_matches
+                .value_of("number")
+                .and_then(|number| number.parse::<u16>().ok()),
+            create_matches.value_of("name").map(String::from),
+            create_matches
+                .values_of("type")
+                .map(|types| types.map(String::from).collect()),
+            output,
+        ),
+        Some(("delete", delete_matches)) => cli::delete(
+            build_repo(&matches, &telemetry),
+            number_of(delete_matches),
+            delete_matches.is_present("yes"),
+            output,
+        ),
+        Some(("restore", restore_matches)) => cli::restore(
+            build_repo(&matches, &telemetry),
+            number_of(restore_matches),
+            output,
+        ),
+        Some(("purge", purge_matches)) => {
+            match cli::parse_age(purge_matches.value_of("older-than").unwrap_or_default()) {
+                Ok(older_than) => cli::purge(build_repo(&matches, &telemetry), older_than, output),
+                _ => panic!("Invalid age"),
+            }
+        }
+        Some(("export", export_matches)) => match (
+            Format::try_from(export_matches.value_of("format").unwrap_or_default()),
+            export_matches.value_of("types").map(TypesLayout::try_from),
+        ) {
+            (Ok(format), None) => cli::export(build_repo(&matches, &telemetry), format, None),
+            (Ok(format), Some(Ok(layout))) => {
+                cli::export(build_repo(&matches, &telemetry), format, Some(layout))
+            }
+            _ => panic!("Invalid export format"),
+        },
+        Some(("import", import_matches)) => cli::import(
+            build_repo(&matches, &telemetry),
+            import_matches.value_of("file").unwrap_or_default(),
+            import_matches.is_present("dry-run"),
+            import_matches.is_present("skip-conflicts"),
+            output,
+        ),
+        Some(("audit", audit_matches)) => match build_audit(&matches) {
+            Some(log) => cli::audit(
+                log,
+                audit_matches
+                    .value_of("number")
+                    .and_then(|number| number.parse::<u16>().ok()),
+                output,
+            ),
+            None => panic!("Listing the changes requires an audit log"),
+        },
+        Some(("keys", keys_matches)) => match (build_keys(&matches), keys_matches.subcommand()) {
+            (Some(store), Some(("issue", issue_matches))) => cli::issue_key(
+                store,
+                String::from(issue_matches.value_of("name").unwrap_or_default()),
+                String::from(issue_matches.value_of("role").unwrap_or_default()),
+                output,
+            ),
+            (Some(store), Some(("revoke", revoke_matches))) => cli::revoke_key(
+                store,
+                String::from(revoke_matches.value_of("name").unwrap_or_default()),
+                output,
+            ),
+            (None, _) => panic!("Managing the keys requires a key store"),
+            _ => panic!("Unknown keys subcommand"),
+        },
+        Some(("reconcile", _)) => match build_airtable(&matches) {
+            Some(airtable) => cli::reconcile(
+                build_local(&matches, &telemetry, build_events(&matches, &telemetry)),
+                Arc::new(airtable),
+                output,
+            ),
+            None => panic!("Reconciling requires an airtable repo to compare with"),
+        },
+        Some(("sync", _)) => match (build_sqlite(&matches), build_airtable(&matches)) {
+            (Some(sqlite), Some(airtable)) => cli::sync(&sqlite, &airtable, output),
+            _ => panic!("Syncing requires both a sqlite and an airtable repo"),
+        },
+        _ => match matches.occurrences_of("cli") {
+            0 => {
+                let telemetry = Telemetry {
+                    metrics: Some(Arc::new(Metrics::new())),
+                    ..telemetry
+                };
+                let events = build_events(&matches, &telemetry);
+                let mirrored = build_mirrored(&matches, &telemetry, events.clone());
+                let served = api::serve(
+                    matches.value_of("listen").unwrap_or_default(),
+                    build_backend(&matches, &telemetry, events.clone(), mirrored.clone()),
+                    api::Options {
+                        audit: build_audit(&matches),
+                        history: build_history(&matches, events),
+                        keys: build_keys(&matches),
+                        rate_limiter: build_rate_limiter(&matches),
+                        cors: build_cors(&matches),
+                        logger: telemetry.logger.clone(),
+                        metrics: telemetry.metrics.clone(),
+                        shutdown: Some(build_shutdown(&matches)),
+                    },
+                );
+                if let Some(mirrored) = mirrored {
+                    mirrored.close();
+                }
+                match served {
+                    Ok(()) => cli::Exit::Success,
+                    Err(()) => cli::Exit::Unknown,
+                }
+            }
+            _ => cli::run(build_repo(&matches, &telemetry), output),
+        },
+    };
+
+    exit.into()
+}
+
+fn number_arg() -> Arg<'static> {
+    Arg::new("number")
+        .value_name("NUMBER")
+        .required(true)
+        .validator(|v| v.parse::<u16>())
+}
+
+fn number_of(matches: &ArgMatches) -> u16 {
+    match matches
+        .value_of("number")
+        .map(|number| number.parse::<u16>())
+    {
+        Some(Ok(number)) => number,
+        _ => panic!("The number should have been validated"),
     }
 }
 
-fn build_repo(sqlite_value: Option<&str>, airtable_values: Option<Values>) -> Arc<dyn Repository> {
-    if let Some(values) = airtable_values {
+fn build_airtable_config(matches: &ArgMatches) -> AirtableConfig {
+    let mut config = AirtableConfig::default();
+
+    if let Some(url) = matches.value_of("airtable-url") {
+        config.base_url = String::from(url);
+    }
+
+    if let Some(table) = matches.value_of("airtable-table") {
+        config.table = String::from(table);
+    }
+
+    if let Some(values) = matches.values_of("airtable-fields") {
+        let values = values.collect::<Vec<&str>>();
+        if let [number, name, types, ..] = values[..] {
+            config.fields = AirtableFieldMapping {
+                number: String::from(number),
+                name: String::from(name),
+                types: String::from(types),
+                ..AirtableFieldMapping::default()
+            };
+        }
+        if let Some(version) = values.get(3) {
+            config.fields.version = String::from(*version);
+        }
+        if let Some(deleted_at) = values.get(4) {
+            config.fields.deleted_at = String::from(*deleted_at);
+        }
+    }
+
+    config
+}
+
+fn build_cache_config(matches: &ArgMatches) -> Option<(Duration, usize)> {
+    match (
+        matches.value_of("cache-ttl"),
+        matches.value_of("cache-size"),
+    ) {
+        (Some(ttl), Some(size)) => match (ttl.parse::<u64>(), size.parse::<usize>()) {
+            (Ok(ttl), Ok(size)) => Some((Duration::from_secs(ttl), size)),
+            _ => panic!("Invalid cache configuration"),
+        },
+        _ => None,
+    }
+}
+
+/// Where the repositories report what they do, built once so that they all share it.
+#[derive(Clone)]
+struct Telemetry {
+    logger: Option<Arc<Logger>>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// The repository used by CLI commands, whose changes are audited on behalf of the current user.
+fn build_repo(matches: &ArgMatches, telemetry: &Telemetry) -> Arc<dyn Repository> {
+    let events = build_events(matches, telemetry);
+    let mirrored = build_mirrored(matches, telemetry, events.clone());
+    let repo = build_backend(matches, telemetry, events, mirrored);
+
+    match build_audit(matches) {
+        Some(log) => Arc::new(
+            AuditedRepository::new(repo, log, cli::actor()).with_logger(telemetry.logger.clone()),
+        ),
+        None => repo,
+    }
+}
+
+fn build_backend(
+    matches: &ArgMatches,
+    telemetry: &Telemetry,
+    events: Option<Arc<EventSourcedRepository>>,
+    mirrored: Option<Arc<MirroredRepository>>,
+) -> Arc<dyn Repository> {
+    let repo: Arc<dyn Repository> = match (mirrored, build_airtable(matches)) {
+        (Some(mirrored), _) => mirrored,
+        (None, Some(airtable)) => instrumented(telemetry, Arc::new(airtable), "airtable"),
+        (None, None) => build_local(matches, telemetry, events),
+    };
+
+    match build_cache_config(matches) {
+        Some((ttl, size)) => {
+            let cache = Arc::new(CachedRepository::new(repo, ttl, size));
+            if let Some(metrics) = &telemetry.metrics {
+                metrics.observe_cache(cache.clone());
+            }
+            cache
+        }
+        None => repo,
+    }
+}
+
+/// The local backend mirrored to Airtable, when given a policy for the failures of the latter.
+fn build_mirrored(
+    matches: &ArgMatches,
+    telemetry: &Telemetry,
+    events: Option<Arc<EventSourcedRepository>>,
+) -> Option<Arc<MirroredRepository>> {
+    let policy = matches.value_of("mirror")?;
+    let airtable = build_airtable(matches)?;
+
+    match SecondaryFailurePolicy::try_from(policy) {
+        Ok(policy) => Some(Arc::new(
+            MirroredRepository::new(
+                build_local(matches, telemetry, events),
+                instrumented(telemetry, Arc::new(airtable), "airtable"),
+                policy,
+            )
+            .with_logger(telemetry.logger.clone()),
+        )),
+        _ => panic!("Invalid mirror policy"),
+    }
+}
+
+fn build_logger(matches: &ArgMatches) -> Option<Arc<Logger>> {
+    matches
+        .value_of("log-format")
+        .map(|format| match logging::Format::try_from(format) {
+            Ok(format) => Arc::new(Logger::new(format)),
+            _ => panic!("Invalid log format"),
+        })
+}
+
+/// Logs a span around every call to the backend and measures it, when logging or measuring.
+fn instrumented(
+    telemetry: &Telemetry,
+    repo: Arc<dyn Repository>,
+    backend: &'static str,
+) -> Arc<dyn Repository> {
+    match (&telemetry.logger, &telemetry.metrics) {
+        (None, None) => repo,
+        (logger, metrics) => {
+            let mut repo = InstrumentedRepository::new(repo, backend);
+            if let Some(logger) = logger {
+                repo = repo.with_logger(logger.clone());
+            }
+            if let Some(metrics) = metrics {
+                repo = repo.with_metrics(metrics.clone());
+            }
+            Arc::new(repo)
+        }
+    }
+}
+
+fn build_audit(matches: &ArgMatches) -> Option<Arc<dyn AuditLog>> {
+    let path = matches.value_of("audit")?;
+
+    if path.ends_with(".jsonl") {
+        match JsonLinesAuditLog::try_new(path) {
+            Ok(log) => Some(Arc::new(log)),
+            _ => panic!("Error while opening the audit log"),
+        }
+    } else {
+        match SqliteAuditLog::try_new(path) {
+            Ok(log) => Some(Arc::new(log)),
+            _ => panic!("Error while opening the audit log"),
+        }
+    }
+}
+
+fn build_rate_limiter(matches: &ArgMatches) -> Option<api::RateLimiter> {
+    let quota = |name: &str| {
+        matches
+            .value_of(name)
+            .map(|requests| match requests.parse::<u32>() {
+                Ok(requests) if requests > 0 => api::Quota {
+                    requests,
+                    period: Duration::from_secs(60),
+                },
+                _ => panic!("Invalid rate limit"),
+            })
+    };
+
+    match (quota("read-limit"), quota("write-limit")) {
+        (None, None) => None,
+        (reads, writes) => Some(api::RateLimiter::new(reads, writes)),
+    }
+}
+
+fn build_shutdown(matches: &ArgMatches) -> api::Shutdown {
+    let deadline = match matches
+        .value_of("shutdown-timeout")
+        .map(|seconds| seconds.parse::<u64>())
+    {
+        Some(Ok(seconds)) => Duration::from_secs(seconds),
+        _ => panic!("The shutdown timeout should have been validated"),
+    };
+
+    match api::Shutdown::on_signals(deadline) {
+        Ok(shutdown) => shutdown,
+        _ => panic!("Could not handle the shutdown signals"),
+    }
+}
+
+fn build_cors(matches: &ArgMatches) -> Option<api::Cors> {
+    let mut cors = api::Cors {
+        origins: matches
+            .values_of("cors-origin")?
+            .map(String::from)
+            .collect(),
+        ..api::Cors::default()
+    };
+
+    if let Some(methods) = matches.values_of("cors-method") {
+        cors.methods = methods.map(|method| method.to_uppercase()).collect();
+    }
+
+    if let Some(headers) = matches.values_of("cors-header") {
+        cors.headers = headers.map(String::from).collect();
+    }
+
+    if let Some(max_age) = matches.value_of("cors-max-age") {
+        match max_age.parse::<u64>() {
+            Ok(max_age) => cors.max_age = Duration::from_secs(max_age),
+            _ => panic!("Invalid CORS max age"),
+        }
+    }
+
+    Some(cors)
+}
+
+fn build_keys(matches: &ArgMatches) -> Option<Arc<dyn ApiKeyStore>> {
+    matches
+        .value_of("keys")
+        .map(|path| match SqliteApiKeyStore::try_new(path) {
+            Ok(store) => Arc::new(store) as Arc<dyn ApiKeyStore>,
+            _ => panic!("Error while opening the key store"),
+        })
+}
+
+fn build_airtable(matches: &ArgMatches) -> Option<AirtableRepository> {
+    if let Some(values) = matches.values_of("airtable") {
         if let [api_key, workspace_id] = values.collect::<Vec<&str>>()[..] {
-            match AirtableRepository::try_new(api_key, workspace_id) {
-                Ok(repo) => return Arc::new(repo),
+            match AirtableRepository::try_new(api_key, workspace_id, build_airtable_config(matches))
+            {
+                Ok(repo) => return Some(repo),
                 _ => panic!("Error while creating airtable repo"),
             }
         }
     }
 
-    if let Some(path) = sqlite_value {
-        match SqliteRepository::try_new(path) {
-            Ok(repo) => return Arc::new(repo),
-            _ => panic!("Error while creating sqlite repo"),
+    None
+}
+
+/// `events` is the event log opened from `--events`, shared with the history.
+fn build_local(
+    matches: &ArgMatches,
+    telemetry: &Telemetry,
+    events: Option<Arc<EventSourcedRepository>>,
+) -> Arc<dyn Repository> {
+    if let Some(repo) = build_sqlite(matches) {
+        return instrumented(telemetry, Arc::new(repo), "sqlite");
+    }
+
+    if let Some(repo) = events {
+        return instrumented(telemetry, repo, "events");
+    }
+
+    if let Some(path) = matches.value_of("json") {
+        match JsonFileRepository::try_new(path) {
+            Ok(repo) => return instrumented(telemetry, Arc::new(repo), "json"),
+            _ => panic!("Error while creating json repo"),
         }
     }
 
-    Arc::new(InMemoryRepository::new())
+    instrumented(telemetry, Arc::new(InMemoryRepository::new()), "memory")
+}
+
+/// Reads the Pokedex as of any point through the event log the repository writes to, when there
+/// is one.
+fn build_history(
+    matches: &ArgMatches,
+    events: Option<Arc<EventSourcedRepository>>,
+) -> Option<Arc<dyn History>> {
+    // Airtable is the backend unless mirrored, the event log then receiving none of the writes.
+    match (matches.is_present("airtable"), matches.is_present("mirror")) {
+        (true, false) => None,
+        _ => events.map(|repo| repo as Arc<dyn History>),
+    }
+}
+
+fn build_events(
+    matches: &ArgMatches,
+    telemetry: &Telemetry,
+) -> Option<Arc<EventSourcedRepository>> {
+    matches
+        .value_of("events")
+        .map(|path| match EventSourcedRepository::try_new(path) {
+            Ok(repo) => Arc::new(repo.with_logger(telemetry.logger.clone())),
+            _ => panic!("Error while creating event sourced repo"),
+        })
+}
+
+fn build_sqlite(matches: &ArgMatches) -> Option<SqliteRepository> {
+    matches
+        .value_of("sqlite")
+        .map(|path| match SqliteRepository::try_new(path) {
+            Ok(repo) => repo,
+            _ => panic!("Error while creating sqlite repo"),
+        })
 }
@@ -0,0 +1,323 @@
+use crate::repositories::cached_repository::CachedRepository;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the number of Pokemons is reused for, so that scrapes do not read the whole Pokedex
+/// every time.
+const POKEMONS_TTL: Duration = Duration::from_secs(60);
+
+/// Upper bounds of the latency buckets, in seconds.
+const BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    /// How many observations fell in each bucket, and not in the previous ones.
+    counts: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(index) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.counts[index] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// What the API and the repositories measure about themselves, exposed to Prometheus by
+/// `GET /metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    /// By route, method and status.
+    requests: Mutex<BTreeMap<(&'static str, &'static str, u16), Histogram>>,
+    /// By backend and operation.
+    operations: Mutex<BTreeMap<(&'static str, &'static str), Histogram>>,
+    /// By backend and operation.
+    errors: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    cache: Mutex<Option<Arc<CachedRepository>>>,
+    /// The number of Pokemons, along with when it was counted.
+    pokemons: Mutex<Option<(Instant, usize)>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe_request(
+        &self,
+        route: &'static str,
+        method: &'static str,
+        status: u16,
+        duration: Duration,
+    ) {
+        lock(&self.requests)
+            .entry((route, method, status))
+            .or_default()
+            .observe(duration);
+    }
+
+    pub fn observe_operation(
+        &self,
+        backend: &'static str,
+        operation: &'static str,
+        failed: bool,
+        duration: Duration,
+    ) {
+        lock(&self.operations)
+            .entry((backend, operation))
+            .or_default()
+            .observe(duration);
+
+        if failed {
+            *lock(&self.errors).entry((backend, operation)).or_default() += 1;
+        }
+    }
+
+    /// Exposes the hits and misses of the cache in front of the repository.
+    pub fn observe_cache(&self, cache: Arc<CachedRepository>) {
+        *lock(&self.cache) = Some(cache);
+    }
+
+    /// The number of Pokemons, counted again with `count` once the last count is too old. Failing
+    /// to count is not remembered, so that the next scrape tries again.
+    pub fn pokemons(&self, count: impl FnOnce() -> Option<usize>) -> Option<usize> {
+        let mut pokemons = lock(&self.pokemons);
+
+        match *pokemons {
+            Some((counted_at, count)) if counted_at.elapsed() < POKEMONS_TTL => Some(count),
+            _ => {
+                let count = count()?;
+                *pokemons = Some((Instant::now(), count));
+                Some(count)
+            }
+        }
+    }
+
+    /// Renders every metric in the Prometheus text format, along with the number of Pokemons
+    /// when it could be counted.
+    pub fn render(&self, pokemons: Option<usize>) -> String {
+        let mut out = String::new();
+
+        let requests = lock(&self.requests);
+        header(
+            &mut out,
+            "pokedex_http_requests_total",
+            "counter",
+            "HTTP requests served.",
+        );
+        for ((route, method, status), histogram) in requests.iter() {
+            writeln!(
+                out,
+                "pokedex_http_requests_total{} {}",
+                labels(&[
+                    ("route", route),
+                    ("method", method),
+                    ("status", &status.to_string())
+                ]),
+                histogram.count
+            )
+            .ok();
+        }
+        header(
+            &mut out,
+            "pokedex_http_request_duration_seconds",
+            "histogram",
+            "Time spent serving HTTP requests.",
+        );
+        for ((route, method, status), histogram) in requests.iter() {
+            let status = status.to_string();
+            histogram_lines(
+                &mut out,
+                "pokedex_http_request_duration_seconds",
+                &[("route", route), ("method", method), ("status", &status)],
+                histogram,
+            );
+        }
+        drop(requests);
+
+        header(
+            &mut out,
+            "pokedex_repository_operation_duration_seconds",
+            "histogram",
+            "Time spent in repository operations.",
+        );
+        for ((backend, operation), histogram) in lock(&self.operations).iter() {
+            histogram_lines(
+                &mut out,
+                "pokedex_repository_operation_duration_seconds",
+                &[("backend", backend), ("operation", operation)],
+                histogram,
+            );
+        }
+
+        header(
+            &mut out,
+            "pokedex_repository_errors_total",
+            "counter",
+            "Repository operations which failed.",
+        );
+        for ((backend, operation), errors) in lock(&self.errors).iter() {
+            writeln!(
+                out,
+                "pokedex_repository_errors_total{} {}",
+                labels(&[("backend", backend), ("operation", operation)]),
+                errors
+            )
+            .ok();
+        }
+
+        if let Some(cache) = lock(&self.cache).as_ref() {
+            header(
+                &mut out,
+                "pokedex_cache_hits_total",
+                "counter",
+                "Reads served from the cache.",
+            );
+            writeln!(out, "pokedex_cache_hits_total {}", cache.hits()).ok();
+            header(
+                &mut out,
+                "pokedex_cache_misses_total",
+                "counter",
+                "Reads which went through to the repository.",
+            );
+            writeln!(out, "pokedex_cache_misses_total {}", cache.misses()).ok();
+        }
+
+        if let Some(pokemons) = pokemons {
+            header(
+                &mut out,
+                "pokedex_pokemons",
+                "gauge",
+                "Pokemons in the Pokedex, deleted ones aside.",
+            );
+            writeln!(out, "pokedex_pokemons {}", pokemons).ok();
+        }
+
+        out
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+}
+
+fn histogram_lines(out: &mut String, name: &str, base: &[(&str, &str)], histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (bound, count) in BUCKETS.iter().zip(histogram.counts) {
+        cumulative += count;
+        let le = bound.to_string();
+        let bucket_labels = [base, &[("le", le.as_str())]].concat();
+        writeln!(
+            out,
+            "{}_bucket{} {}",
+            name,
+            labels(&bucket_labels),
+            cumulative
+        )
+        .ok();
+    }
+    let bucket_labels = [base, &[("le", "+Inf")]].concat();
+    writeln!(
+        out,
+        "{}_bucket{} {}",
+        name,
+        labels(&bucket_labels),
+        histogram.count
+    )
+    .ok();
+    writeln!(out, "{}_sum{} {}", name, labels(base), histogram.sum).ok();
+    writeln!(out, "{}_count{} {}", name, labels(base), histogram.count).ok();
+}
+
+fn labels(labels: &[(&str, &str)]) -> String {
+    let labels = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', "\\\"")
+                .replace('\n', r"\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect::<Vec<String>>();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_render_the_requests_as_counters_and_cumulative_histograms() {
+        let metrics = Metrics::new();
+        metrics.observe_request("/{number}", "GET", 200, Duration::from_millis(3));
+        metrics.observe_request("/{number}", "GET", 200, Duration::from_millis(30));
+        metrics.observe_request("/{number}", "DELETE", 404, Duration::from_millis(3));
+
+        let rendered = metrics.render(Some(151));
+
+        for line in [
+            "# TYPE pokedex_http_requests_total counter",
+            r#"pokedex_http_requests_total{route="/{number}",method="GET",status="200"} 2"#,
+            r#"pokedex_http_requests_total{route="/{number}",method="DELETE",status="404"} 1"#,
+            r#"pokedex_http_request_duration_seconds_bucket{route="/{number}",method="GET",status="200",le="0.001"} 0"#,
+            r#"pokedex_http_request_duration_seconds_bucket{route="/{number}",method="GET",status="200",le="0.005"} 1"#,
+            r#"pokedex_http_request_duration_seconds_bucket{route="/{number}",method="GET",status="200",le="0.05"} 2"#,
+            r#"pokedex_http_request_duration_seconds_bucket{route="/{number}",method="GET",status="200",le="+Inf"} 2"#,
+            r#"pokedex_http_request_duration_seconds_count{route="/{number}",method="GET",status="200"} 2"#,
+            "pokedex_pokemons 151",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+
+    #[test]
+    fn it_should_count_the_repository_errors_per_backend_and_operation() {
+        let metrics = Metrics::new();
+        metrics.observe_operation("sqlite", "fetch_one", false, Duration::from_millis(1));
+        metrics.observe_operation("airtable", "fetch_one", true, Duration::from_secs(1));
+
+        let rendered = metrics.render(None);
+
+        assert!(rendered.contains(
+            r#"pokedex_repository_operation_duration_seconds_count{backend="sqlite",operation="fetch_one"} 1"#
+        ));
+        assert!(rendered.contains(
+            r#"pokedex_repository_errors_total{backend="airtable",operation="fetch_one"} 1"#
+        ));
+        assert!(!rendered.contains(r#"pokedex_repository_errors_total{backend="sqlite""#));
+        assert!(!rendered.contains("pokedex_pokemons"));
+    }
+
+    #[test]
+    fn it_should_reuse_the_number_of_pokemons_for_a_while() {
+        let metrics = Metrics::new();
+        let counts = std::cell::Cell::new(0);
+        let count = || {
+            counts.set(counts.get() + 1);
+            Some(151)
+        };
+
+        assert_eq!(metrics.pokemons(|| None), None);
+        assert_eq!(metrics.pokemons(count), Some(151));
+        assert_eq!(metrics.pokemons(count), Some(151));
+        assert_eq!(counts.get(), 1);
+    }
+}
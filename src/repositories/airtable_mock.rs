@@ -0,0 +1,339 @@
+use super::airtable_repository::AirtableConfig;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Local stand-in for `api.airtable.com`, serving a single table from memory.
+///
+/// It supports the subset of the REST API used by `AirtableRepository`: listing records with
+/// `filterByFormula`, `sort`, `pageSize` and `offset`, creating records and deleting them. Failures
+/// can be injected with `fail_next` to simulate rate limiting or outages. Like Airtable, it refuses
+/// to create records with fields outside the columns of its table.
+pub struct AirtableMock {
+    url: String,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+struct State {
+    table: String,
+    records: Vec<Record>,
+    next_id: u32,
+    page_size: usize,
+    failures: Vec<(String, u16)>,
+    competitors: Vec<Map<String, Value>>,
+    requests: Vec<(String, Instant)>,
+    clock: bool,
+    columns: Vec<String>,
+}
+
+#[derive(Clone)]
+struct Record {
+    id: String,
+    created_time: String,
+    fields: Map<String, Value>,
+}
+
+impl AirtableMock {
+    pub const API_KEY: &'static str = "key";
+    pub const BASE_ID: &'static str = "base";
+
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State {
+            table: String::from("pokemons"),
+            records: vec![],
+            next_id: 1,
+            page_size: 100,
+            failures: vec![],
+            competitors: vec![],
+            requests: vec![],
+            clock: false,
+            columns: ["number", "name", "types", "deleted_at", "version"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }));
+
+        let handler_state = state.clone();
+        let server = rouille::Server::new("127.0.0.1:0", move |req| handle(&handler_state, req))
+            .expect("the mock server should start");
+        let url = format!("http://{}", server.server_addr());
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            while !server_stop.load(Ordering::SeqCst) {
+                server.poll_timeout(Duration::from_millis(10));
+            }
+        });
+
+        Self {
+            url,
+            state,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Configuration pointing an `AirtableRepository` at the mock instead of `api.airtable.com`.
+    pub fn config(&self) -> AirtableConfig {
+        AirtableConfig {
+            base_url: format!("{}/v0", self.url),
+            table: self.state.lock().unwrap().table.clone(),
+            ..AirtableConfig::default()
+        }
+    }
+
+    pub fn with_table(self, table: &str) -> Self {
+        self.state.lock().unwrap().table = String::from(table);
+        self
+    }
+
+    pub fn with_columns(self, columns: &[&str]) -> Self {
+        self.state.lock().unwrap().columns = columns.iter().map(|c| String::from(*c)).collect();
+        self
+    }
+
+    pub fn with_page_size(self, page_size: usize) -> Self {
+        self.state.lock().unwrap().page_size = page_size;
+        self
+    }
+
+    /// Makes the next request with the given method fail with `status`.
+    pub fn fail_next(&self, method: &str, status: u16) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .push((String::from(method), status));
+    }
+
+    /// Simulates another client creating a record just before the next create request lands.
+    pub fn race_next_create(&self, fields: Value) {
+        let fields = fields.as_object().cloned().unwrap_or_default();
+        self.state.lock().unwrap().competitors.push(fields);
+    }
+
+    /// Stamps the records created from now on with the current time, as Airtable does.
+    pub fn use_the_clock(&self) {
+        self.state.lock().unwrap().clock = true;
+    }
+
+    pub fn insert(&self, fields: Value) {
+        let mut state = self.state.lock().unwrap();
+        let fields = fields.as_object().cloned().unwrap_or_default();
+        state.create(fields);
+    }
+
+    /// Inserts a record as if another client had created it at `created_time`.
+    pub fn insert_at(&self, created_time: &str, fields: Value) {
+        let mut state = self.state.lock().unwrap();
+        let fields = fields.as_object().cloned().unwrap_or_default();
+        state.create(fields);
+        if let Some(record) = state.records.last_mut() {
+            record.created_time = String::from(created_time);
+        }
+    }
+
+    pub fn records(&self) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .records
+            .iter()
+            .map(|record| Value::Object(record.fields.clone()))
+            .collect()
+    }
+
+    /// Method and arrival time of every request received so far, authorized or not.
+    pub fn requests(&self) -> Vec<(String, Instant)> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for AirtableMock {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+impl State {
+    fn create(&mut self, fields: Map<String, Value>) -> Record {
+        // Unless stamped with the clock, records are created one second apart so that their
+        // creation order is unambiguous.
+        let created_time = match self.clock {
+            true => chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            false => format!(
+                "2022-01-01T{:02}:{:02}:{:02}.000Z",
+                self.next_id / 3600 % 24,
+                self.next_id / 60 % 60,
+                self.next_id % 60
+            ),
+        };
+        let record = Record {
+            id: format!("rec{:014}", self.next_id),
+            created_time,
+            fields,
+        };
+        self.next_id += 1;
+        self.records.push(record.clone());
+        record
+    }
+}
+
+fn handle(state: &Mutex<State>, req: &rouille::Request) -> rouille::Response {
+    let mut state = state.lock().unwrap();
+    state
+        .requests
+        .push((String::from(req.method()), Instant::now()));
+
+    if req.header("Authorization") != Some(&format!("Bearer {}", AirtableMock::API_KEY)) {
+        return error(401);
+    }
+
+    if let Some(index) = state
+        .failures
+        .iter()
+        .position(|(method, _)| method == req.method())
+    {
+        let (_, status) = state.failures.remove(index);
+        return error(status);
+    }
+
+    let table_path = format!("/v0/{}/{}", AirtableMock::BASE_ID, state.table);
+    let url = req.url();
+    let record_id = match url.strip_prefix(&table_path) {
+        Some("") => None,
+        Some(rest) => match rest.strip_prefix('/') {
+            Some(id) => Some(String::from(id)),
+            None => return error(404),
+        },
+        None => return error(404),
+    };
+
+    match (req.method(), record_id) {
+        ("GET", None) => list(&state, req),
+        ("POST", None) => create(&mut state, req),
+        ("DELETE", Some(id)) => delete(&mut state, &id),
+        _ => error(404),
+    }
+}
+
+fn list(state: &State, req: &rouille::Request) -> rouille::Response {
+    let mut records = state.records.clone();
+
+    if let Some(formula) = param(req, "filterByFormula") {
+        let (field, value) = match formula.split_once('=') {
+            Some((field, value)) => (
+                field.trim_matches(|c| c == '{' || c == '}').to_string(),
+                value.trim_matches(|c| c == '\'' || c == '"').to_string(),
+            ),
+            None => return error(422),
+        };
+        records.retain(|record| match record.fields.get(&field) {
+            Some(Value::String(s)) => *s == value,
+            Some(other) => serde_json::from_str::<Value>(&value).ok().as_ref() == Some(other),
+            None => false,
+        });
+    }
+
+    if let Some(field) = param(req, "sort[0][field]") {
+        records.sort_by_key(|record| record.fields.get(&field).and_then(Value::as_u64));
+    }
+
+    let page_size = param(req, "pageSize")
+        .and_then(|size| size.parse::<usize>().ok())
+        .unwrap_or(state.page_size)
+        .min(state.page_size);
+    let start = match param(req, "offset") {
+        Some(offset) => match offset.parse::<usize>() {
+            Ok(start) => start,
+            _ => return error(422),
+        },
+        None => 0,
+    };
+    let end = (start + page_size).min(records.len());
+
+    let mut body = json!({
+        "records": records[start.min(end)..end].iter().map(to_json).collect::<Vec<Value>>(),
+    });
+    if end < records.len() {
+        body["offset"] = Value::String(end.to_string());
+    }
+
+    rouille::Response::json(&body)
+}
+
+fn create(state: &mut State, req: &rouille::Request) -> rouille::Response {
+    let body = match rouille::input::json_input::<Value>(req) {
+        Ok(body) => body,
+        _ => return error(422),
+    };
+
+    let records = match body["records"].as_array() {
+        Some(records) => records.clone(),
+        None => return error(422),
+    };
+
+    let unknown = records.iter().any(|record| {
+        record["fields"]
+            .as_object()
+            .is_some_and(|fields| fields.keys().any(|field| !state.columns.contains(field)))
+    });
+    if unknown {
+        return rouille::Response::json(&json!({ "error": { "type": "UNKNOWN_FIELD_NAME" } }))
+            .with_status_code(422);
+    }
+
+    for fields in std::mem::take(&mut state.competitors) {
+        state.create(fields);
+    }
+
+    let created = records
+        .into_iter()
+        .map(|record| {
+            let fields = record["fields"].as_object().cloned().unwrap_or_default();
+            to_json(&state.create(fields))
+        })
+        .collect::<Vec<Value>>();
+
+    rouille::Response::json(&json!({ "records": created }))
+}
+
+fn delete(state: &mut State, id: &str) -> rouille::Response {
+    match state.records.iter().position(|record| record.id == id) {
+        Some(index) => {
+            state.records.remove(index);
+            rouille::Response::json(&json!({ "id": id, "deleted": true }))
+        }
+        None => error(404),
+    }
+}
+
+/// Same as `rouille::Request::get_param`, except that the parameter name is percent-decoded too.
+fn param(req: &rouille::Request, name: &str) -> Option<String> {
+    let decode = |s: &str| {
+        rouille::percent_encoding::percent_decode(s.replace('+', " ").as_bytes())
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+
+    req.raw_query_string()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| decode(key) == name)
+        .map(|(_, value)| decode(value))
+}
+
+fn to_json(record: &Record) -> Value {
+    json!({ "id": record.id, "createdTime": record.created_time, "fields": record.fields })
+}
+
+fn error(status: u16) -> rouille::Response {
+    rouille::Response::json(&json!({ "error": { "type": status.to_string() } }))
+        .with_status_code(status)
+}
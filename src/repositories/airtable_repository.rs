@@ -1,163 +1,1059 @@
-use super::{DeleteError, FetchAllError, FetchOneError, InsertError, Repository};
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
 use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+const REQUESTS_PER_SECOND: usize = 5;
+
+/// Where the Pokemons live in Airtable and how their columns are named.
+pub struct AirtableConfig {
+    pub base_url: String,
+    pub table: String,
+    pub fields: AirtableFieldMapping,
+}
+
+impl Default for AirtableConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::from("https://api.airtable.com/v0"),
+            table: String::from("pokemons"),
+            fields: AirtableFieldMapping::default(),
+        }
+    }
+}
+
+pub struct AirtableFieldMapping {
+    pub number: String,
+    pub name: String,
+    pub types: String,
+    /// Set on the tombstones left by deleted Pokemons.
+    pub deleted_at: String,
+    /// Records without it hold the first version of their Pokemon.
+    pub version: String,
+}
+
+impl Default for AirtableFieldMapping {
+    fn default() -> Self {
+        Self {
+            number: String::from("number"),
+            name: String::from("name"),
+            types: String::from("types"),
+            deleted_at: String::from("deleted_at"),
+            version: String::from("version"),
+        }
+    }
+}
 
 pub struct AirtableRepository {
     url: String,
     auth_header: String,
+    fields: AirtableFieldMapping,
+    limiter: RateLimiter,
+}
+
+/// The latest write to a number, as a sync sees it.
+pub struct RemoteWrite {
+    pub number: u16,
+    /// `None` when the Pokemon was deleted.
+    pub pokemon: Option<Pokemon>,
+    /// The record holding the Pokemon, or its tombstone.
+    pub record_id: String,
+    /// Records are never updated in place, a change being a delete followed by a create, so the
+    /// creation time Airtable reports for the record is also when the number was last modified.
+    pub modified_at: DateTime<Utc>,
 }
 
 impl AirtableRepository {
-    pub fn try_new(api_key: &str, workspace_id: &str) -> Result<Self, ()> {
-        let url = format!("https://api.airtable.com/v0/{}/pokemons", workspace_id);
-        let auth_header = format!("Bearer {}", api_key);
+    pub fn try_new(api_key: &str, workspace_id: &str, config: AirtableConfig) -> Result<Self, ()> {
+        let mut url = match url::Url::parse(&config.base_url) {
+            Ok(url) => url,
+            _ => return Err(()),
+        };
+
+        match url.path_segments_mut() {
+            Ok(mut segments) => {
+                segments
+                    .pop_if_empty()
+                    .push(workspace_id)
+                    .push(&config.table);
+            }
+            _ => return Err(()),
+        };
 
-        if let Err(_) = ureq::get(&url).set("Authorization", &auth_header).call() {
+        let repo = Self {
+            url: String::from(url),
+            auth_header: format!("Bearer {}", api_key),
+            fields: config.fields,
+            limiter: RateLimiter::new(REQUESTS_PER_SECOND, Duration::from_secs(1)),
+        };
+
+        if repo
+            .send(ureq::get(&repo.url).query("maxRecords", "1"), None, true)
+            .is_err()
+        {
             return Err(());
         }
 
-        Ok(Self { url, auth_header })
+        Ok(repo)
     }
 
-    fn fetch_pokemon_rows(&self, number: Option<u16>) -> Result<AirtableJson, ()> {
-        let url = match number {
-            Some(number) => format!("{}?filterByFormula=number%3D{}", self.url, number),
-            None => format!("{}?sort%5B0%5D%5Bfield%5D=number", self.url),
+    fn fetch_pokemon_rows(&self, number: Option<u16>) -> Result<Vec<AirtableRecord>, ()> {
+        let mut records = vec![];
+        let mut offset: Option<String> = None;
+
+        loop {
+            let mut request = match number {
+                Some(number) => ureq::get(&self.url).query(
+                    "filterByFormula",
+                    &format!("{{{}}}={}", self.fields.number, number),
+                ),
+                None => ureq::get(&self.url).query("sort[0][field]", &self.fields.number),
+            };
+            if let Some(offset) = &offset {
+                request = request.query("offset", offset);
+            }
+
+            let json = match self.send(request, None, true) {
+                Ok(res) => match res.into_json::<AirtableJson>() {
+                    Ok(json) => json,
+                    _ => return Err(()),
+                },
+                _ => return Err(()),
+            };
+
+            records.extend(json.records);
+
+            match json.offset {
+                Some(next) => offset = Some(next),
+                None => return Ok(records),
+            }
+        }
+    }
+
+    /// The latest write to every number, deletions included, ordered by number.
+    pub fn fetch_latest_writes(&self) -> Result<Vec<RemoteWrite>, FetchAllError> {
+        let mut records = match self.fetch_pokemon_rows(None) {
+            Ok(records) => records,
+            _ => return Err(FetchAllError::Unknown),
         };
 
-        let res = match ureq::get(&url)
-            .set("Authorization", &self.auth_header)
-            .call()
+        Self::sort_canonical_first(&mut records);
+
+        let mut tombstones = self.tombstones(records.clone());
+        tombstones.sort_by_key(|record| std::cmp::Reverse(self.deleted_at(record)));
+
+        let mut writes: Vec<RemoteWrite> = vec![];
+
+        // A live record wins over the tombstones of its number, which are only left over from an
+        // interrupted write.
+        for (record, deleted) in self
+            .live(records)
+            .into_iter()
+            .map(|record| (record, false))
+            .chain(tombstones.into_iter().map(|record| (record, true)))
         {
-            Ok(res) => res,
-            _ => return Err(()),
+            let modified_at = match DateTime::parse_from_rfc3339(&record.created_time) {
+                Ok(modified_at) => modified_at.with_timezone(&Utc),
+                _ => return Err(FetchAllError::Unknown),
+            };
+
+            let pokemon = match self.to_pokemon(record.fields) {
+                Ok(pokemon) => pokemon,
+                _ => return Err(FetchAllError::Unknown),
+            };
+            let number = u16::from(pokemon.number.clone());
+
+            if writes.iter().all(|write| write.number != number) {
+                writes.push(RemoteWrite {
+                    number,
+                    pokemon: if deleted { None } else { Some(pokemon) },
+                    record_id: record.id,
+                    modified_at,
+                });
+            }
+        }
+
+        writes.sort_by_key(|write| write.number);
+        Ok(writes)
+    }
+
+    /// Inserts a Pokemon like `insert` does, also returning the id of the record created for it.
+    pub fn insert_with_record_id(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<(Pokemon, String), InsertError> {
+        let records = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
+            Ok(records) => records,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        if !self.live(records.clone()).is_empty() {
+            return Err(InsertError::Conflict);
+        }
+
+        let version = self
+            .tombstones(records)
+            .iter()
+            .map(|tombstone| self.version(&tombstone.fields) + 1)
+            .max()
+            .unwrap_or(1);
+
+        let mut fields = Map::new();
+        fields.insert(
+            self.fields.number.clone(),
+            Value::from(u16::from(number.clone())),
+        );
+        fields.insert(
+            self.fields.name.clone(),
+            Value::from(String::from(name.clone())),
+        );
+        fields.insert(
+            self.fields.types.clone(),
+            Value::from(Vec::<String>::from(types.clone())),
+        );
+        // Tables without a version column keep working until a Pokemon comes back.
+        if version > 1 {
+            fields.insert(self.fields.version.clone(), Value::from(version));
+        }
+
+        let created = match self.create_record(fields) {
+            Ok(created) => created,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let records = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
+            Ok(records) => records,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let mut live = self.live(records.clone());
+        Self::sort_canonical_first(&mut live);
+
+        match live.first() {
+            Some(canonical) if canonical.id != created.id => {
+                return match self.delete_record(&created.id) {
+                    Ok(()) => Err(InsertError::Conflict),
+                    _ => Err(InsertError::Unknown),
+                };
+            }
+            _ => {}
+        };
+
+        // The tombstone of a previously deleted Pokemon with this number gives way to the new one.
+        for tombstone in self.tombstones(records) {
+            if self.delete_record(&tombstone.id).is_err() {
+                return Err(InsertError::Unknown);
+            }
+        }
+
+        Ok((
+            Pokemon::new(number, name, types).with_version(version),
+            created.id,
+        ))
+    }
+
+    /// Deletes a Pokemon like `delete` does, returning the id of the tombstone left in its place.
+    pub fn delete_with_record_id(&self, number: PokemonNumber) -> Result<String, DeleteError> {
+        self.tombstone(number, None)
+    }
+
+    fn tombstone(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<String, DeleteError> {
+        let records = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
+            Ok(records) => records,
+            _ => return Err(DeleteError::Unknown),
         };
 
-        match res.into_json::<AirtableJson>() {
-            Ok(json) => Ok(json),
+        let mut live = self.live(records.clone());
+        if live.is_empty() {
+            return Err(DeleteError::NotFound);
+        }
+
+        Self::sort_canonical_first(&mut live);
+
+        if versions.is_some_and(|versions| !versions.contains(&self.version(&live[0].fields))) {
+            return Err(DeleteError::VersionMismatch);
+        }
+
+        // The tombstone is created first so that a failure halfway leaves the Pokemon visible
+        // rather than lost.
+        let mut fields = live[0].fields.clone();
+        fields.insert(
+            self.fields.deleted_at.clone(),
+            Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        let tombstone = match self.create_record(fields) {
+            Ok(tombstone) => tombstone,
+            _ => return Err(DeleteError::Unknown),
+        };
+
+        // Duplicates left behind by racing clients go away along with the canonical record, and
+        // only the latest tombstone is kept.
+        for record in live.into_iter().chain(self.tombstones(records)) {
+            if self.delete_record(&record.id).is_err() {
+                return Err(DeleteError::Unknown);
+            }
+        }
+
+        Ok(tombstone.id)
+    }
+
+    /// Several records can share a number when clients raced to create it. The oldest one is the
+    /// canonical record, ties being broken by id so that every client picks the same one.
+    fn sort_canonical_first(records: &mut [AirtableRecord]) {
+        records.sort_by(|a, b| (&a.created_time, &a.id).cmp(&(&b.created_time, &b.id)));
+    }
+
+    /// Deleted Pokemons are kept as separate tombstone records, created when the Pokemon was
+    /// deleted so that their creation time still tells when the number was last modified.
+    fn deleted_at(&self, record: &AirtableRecord) -> Option<DateTime<Utc>> {
+        record
+            .fields
+            .get(&self.fields.deleted_at)
+            .and_then(Value::as_str)
+            .and_then(|deleted_at| DateTime::parse_from_rfc3339(deleted_at).ok())
+            .map(|deleted_at| deleted_at.with_timezone(&Utc))
+    }
+
+    fn live(&self, records: Vec<AirtableRecord>) -> Vec<AirtableRecord> {
+        records
+            .into_iter()
+            .filter(|record| !record.fields.contains_key(&self.fields.deleted_at))
+            .collect()
+    }
+
+    fn tombstones(&self, records: Vec<AirtableRecord>) -> Vec<AirtableRecord> {
+        records
+            .into_iter()
+            .filter(|record| record.fields.contains_key(&self.fields.deleted_at))
+            .collect()
+    }
+
+    fn create_record(&self, fields: Map<String, Value>) -> Result<AirtableRecord, ()> {
+        let body = ureq::json!({ "records": [{ "fields": fields }] });
+
+        match self.send(ureq::post(&self.url), Some(&body), false) {
+            Ok(res) => match res.into_json::<AirtableJson>() {
+                Ok(mut json) if !json.records.is_empty() => Ok(json.records.remove(0)),
+                _ => Err(()),
+            },
             _ => Err(()),
         }
     }
+
+    fn delete_record(&self, id: &str) -> Result<(), ()> {
+        match self.send(ureq::delete(&format!("{}/{}", self.url, id)), None, true) {
+            Ok(_) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// Sends a request while staying under Airtable's rate limit.
+    ///
+    /// Rate limited requests are always replayed since Airtable rejects them before processing,
+    /// while server and transport errors are only retried when the request is idempotent.
+    fn send(
+        &self,
+        request: ureq::Request,
+        body: Option<&serde_json::Value>,
+        idempotent: bool,
+    ) -> Result<ureq::Response, ()> {
+        let mut attempt = 0;
+
+        loop {
+            self.limiter.acquire();
+
+            let request = request.clone().set("Authorization", &self.auth_header);
+            let res = match body {
+                Some(body) => request.send_json(body.clone()),
+                None => request.call(),
+            };
+
+            let retry_after = match res {
+                Ok(res) => return Ok(res),
+                Err(ureq::Error::Status(429, res)) => retry_after(&res),
+                Err(ureq::Error::Status(status, res)) if idempotent && status >= 500 => {
+                    retry_after(&res)
+                }
+                Err(ureq::Error::Transport(_)) if idempotent => None,
+                _ => return Err(()),
+            };
+
+            attempt += 1;
+            if attempt >= MAX_ATTEMPTS {
+                return Err(());
+            }
+
+            thread::sleep(retry_after.unwrap_or(BASE_BACKOFF * 2u32.pow(attempt - 1)));
+        }
+    }
+
+    fn to_pokemon(&self, fields: Map<String, Value>) -> Result<Pokemon, ()> {
+        let number = fields
+            .get(&self.fields.number)
+            .and_then(Value::as_u64)
+            .and_then(|number| u16::try_from(number).ok());
+        let name = fields
+            .get(&self.fields.name)
+            .and_then(Value::as_str)
+            .map(String::from);
+        let types = fields
+            .get(&self.fields.types)
+            .and_then(Value::as_array)
+            .and_then(|types| {
+                types
+                    .iter()
+                    .map(|t| t.as_str().map(String::from))
+                    .collect::<Option<Vec<String>>>()
+            });
+
+        match (number, name, types) {
+            (Some(number), Some(name), Some(types)) => match (
+                PokemonNumber::try_from(number),
+                PokemonName::try_from(name),
+                PokemonTypes::try_from(types),
+            ) {
+                (Ok(number), Ok(name), Ok(types)) => {
+                    Ok(Pokemon::new(number, name, types).with_version(self.version(&fields)))
+                }
+                _ => Err(()),
+            },
+            _ => Err(()),
+        }
+    }
+
+    fn version(&self, fields: &Map<String, Value>) -> u64 {
+        fields
+            .get(&self.fields.version)
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+    }
+}
+
+fn retry_after(res: &ureq::Response) -> Option<Duration> {
+    res.header("Retry-After")
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Sliding window limiter allowing at most `max` requests per `window`, shared by all threads.
+struct RateLimiter {
+    max: usize,
+    window: Duration,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut sent = match self.sent.lock() {
+                    Ok(sent) => sent,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                let now = Instant::now();
+
+                while let Some(oldest) = sent.front() {
+                    if now.duration_since(*oldest) >= self.window {
+                        sent.pop_front();
+                    } else {
+                        break;
+                    }
+                }
+
+                match sent.front() {
+                    Some(oldest) if sent.len() >= self.max => {
+                        self.window - now.duration_since(*oldest)
+                    }
+                    _ => {
+                        sent.push_back(now);
+                        return;
+                    }
+                }
+            };
+
+            thread::sleep(wait);
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct AirtableJson {
     records: Vec<AirtableRecord>,
+    offset: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 struct AirtableRecord {
     id: String,
-    fields: AirtableFields,
-}
-
-#[derive(Deserialize)]
-struct AirtableFields {
-    number: u16,
-    name: String,
-    types: Vec<String>,
+    #[serde(rename = "createdTime")]
+    created_time: String,
+    fields: Map<String, Value>,
 }
 
 impl Repository for AirtableRepository {
+    /// Airtable has no unique constraint, so the record is created and then checked against any
+    /// record of the same number created concurrently by another client: only the canonical one
+    /// is kept. `performUpsert` is not an option since it would overwrite the winner's fields.
+    ///
+    /// Versions are checked and bumped by the client too, so a conditional write racing with
+    /// another client can still go through.
     fn insert(
         &self,
         number: PokemonNumber,
         name: PokemonName,
         types: PokemonTypes,
     ) -> Result<Pokemon, InsertError> {
-        let json = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
-            Ok(json) => json,
-            _ => return Err(InsertError::Unknown),
+        match self.insert_with_record_id(number, name, types) {
+            Ok((pokemon, _)) => Ok(pokemon),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        Ok(self
+            .fetch_latest_writes()?
+            .into_iter()
+            .filter_map(|write| write.pokemon)
+            .collect())
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        let mut records = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
+            Ok(records) => self.live(records),
+            _ => return Err(FetchOneError::Unknown),
         };
 
-        if !json.records.is_empty() {
-            return Err(InsertError::Conflict);
+        if records.is_empty() {
+            return Err(FetchOneError::NotFound);
         }
 
-        let body = ureq::json!({
-            "records": [{
-                "fields": {
-                    "number": u16::from(number.clone()),
-                    "name": String::from(name.clone()),
-                    "types": Vec::<String>::from(types.clone()),
-                },
-            }],
-        });
+        Self::sort_canonical_first(&mut records);
+        let record = records.remove(0);
 
-        if let Err(_) = ureq::post(&self.url)
-            .set("Authorization", &self.auth_header)
-            .send_json(body)
-        {
-            return Err(InsertError::Unknown);
+        match self.to_pokemon(record.fields) {
+            Ok(pokemon) => Ok(pokemon),
+            _ => Err(FetchOneError::Unknown),
         }
+    }
 
-        Ok(Pokemon::new(number, name, types))
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        match self.tombstone(number, versions) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        }
     }
 
-    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
-        let json = match self.fetch_pokemon_rows(None) {
-            Ok(json) => json,
-            _ => return Err(FetchAllError::Unknown),
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let records = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
+            Ok(records) => records,
+            _ => return Err(RestoreError::Unknown),
         };
 
-        let mut pokemons = vec![];
+        // A tombstone next to a live record is left over from an interrupted insert.
+        if !self.live(records.clone()).is_empty() {
+            return Err(RestoreError::NotFound);
+        }
 
-        for record in json.records.into_iter() {
-            match (
-                PokemonNumber::try_from(record.fields.number),
-                PokemonName::try_from(record.fields.name),
-                PokemonTypes::try_from(record.fields.types),
-            ) {
-                (Ok(number), Ok(name), Ok(types)) => {
-                    pokemons.push(Pokemon::new(number, name, types))
-                }
-                _ => return Err(FetchAllError::Unknown),
+        let mut tombstones = self.tombstones(records);
+        tombstones.sort_by_key(|record| std::cmp::Reverse(self.deleted_at(record)));
+
+        let mut fields = match tombstones.first() {
+            Some(tombstone) => tombstone.fields.clone(),
+            None => return Err(RestoreError::NotFound),
+        };
+
+        let previous = self.version(&fields);
+        if versions.is_some_and(|versions| !versions.contains(&previous)) {
+            return Err(RestoreError::VersionMismatch);
+        }
+
+        fields.remove(&self.fields.deleted_at);
+        fields.insert(self.fields.version.clone(), Value::from(previous + 1));
+
+        let pokemon = match self.to_pokemon(fields.clone()) {
+            Ok(pokemon) => pokemon,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        if self.create_record(fields).is_err() {
+            return Err(RestoreError::Unknown);
+        }
+
+        for tombstone in tombstones {
+            if self.delete_record(&tombstone.id).is_err() {
+                return Err(RestoreError::Unknown);
             }
         }
 
-        Ok(pokemons)
+        Ok(pokemon)
     }
 
-    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
-        let mut json = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
-            Ok(json) => json,
-            _ => return Err(FetchOneError::Unknown),
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        let records = match self.fetch_pokemon_rows(Some(u16::from(revision.number()))) {
+            Ok(records) => records,
+            _ => return Err(RevertError::Unknown),
         };
 
-        if json.records.is_empty() {
-            return Err(FetchOneError::NotFound);
+        let (pokemon, deleted_at) = match revision {
+            Revision::Absent(_) => (None, None),
+            Revision::Live(pokemon) => (Some(pokemon), None),
+            Revision::Deleted(pokemon) => (
+                Some(pokemon),
+                Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            ),
+        };
+
+        // The record put back is created first so that a failure halfway leaves it visible.
+        if let Some(pokemon) = pokemon {
+            let mut fields = Map::new();
+            fields.insert(
+                self.fields.number.clone(),
+                Value::from(u16::from(pokemon.number)),
+            );
+            fields.insert(
+                self.fields.name.clone(),
+                Value::from(String::from(pokemon.name)),
+            );
+            fields.insert(
+                self.fields.types.clone(),
+                Value::from(Vec::<String>::from(pokemon.types)),
+            );
+            if pokemon.version > 1 {
+                fields.insert(self.fields.version.clone(), Value::from(pokemon.version));
+            }
+            if let Some(deleted_at) = deleted_at {
+                fields.insert(self.fields.deleted_at.clone(), Value::from(deleted_at));
+            }
+            if self.create_record(fields).is_err() {
+                return Err(RevertError::Unknown);
+            }
         }
 
-        let record = json.records.remove(0);
+        for record in records {
+            if self.delete_record(&record.id).is_err() {
+                return Err(RevertError::Unknown);
+            }
+        }
 
-        match (
-            PokemonNumber::try_from(record.fields.number),
-            PokemonName::try_from(record.fields.name),
-            PokemonTypes::try_from(record.fields.types),
-        ) {
-            (Ok(number), Ok(name), Ok(types)) => Ok(Pokemon::new(number, name, types)),
-            _ => Err(FetchOneError::Unknown),
+        Ok(())
+    }
+
+    fn backend(&self) -> String {
+        String::from("airtable")
+    }
+
+    /// Sends a single request rather than retrying, so that a revoked API key or an outage is
+    /// reported right away.
+    fn check_health(&self) -> Result<(), HealthError> {
+        self.limiter.acquire();
+
+        let res = ureq::get(&self.url)
+            .query("maxRecords", "1")
+            .set("Authorization", &self.auth_header)
+            .call();
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => Err(
+                HealthError::Unavailable(String::from("Airtable rejected the API key")),
+            ),
+            Err(ureq::Error::Status(status, _)) => Err(HealthError::Unavailable(format!(
+                "Airtable answered with status {}",
+                status
+            ))),
+            Err(ureq::Error::Transport(_)) => Err(HealthError::Unavailable(String::from(
+                "Airtable could not be reached",
+            ))),
         }
     }
 
-    fn delete(&self, number: PokemonNumber) -> Result<(), DeleteError> {
-        let mut json = match self.fetch_pokemon_rows(Some(u16::from(number.clone()))) {
-            Ok(json) => json,
-            _ => return Err(DeleteError::Unknown),
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let records = match self.fetch_pokemon_rows(None) {
+            Ok(records) => self.tombstones(records),
+            _ => return Err(PurgeError::Unknown),
         };
 
-        if json.records.is_empty() {
-            return Err(DeleteError::NotFound);
+        let mut purged = vec![];
+
+        for record in records {
+            match self.deleted_at(&record) {
+                Some(deleted_at) if deleted_at < deleted_before => {
+                    let pokemon = match self.to_pokemon(record.fields.clone()) {
+                        Ok(pokemon) => pokemon,
+                        _ => return Err(PurgeError::Unknown),
+                    };
+                    if self.delete_record(&record.id).is_err() {
+                        return Err(PurgeError::Unknown);
+                    }
+                    purged.push(pokemon);
+                }
+                Some(_) => {}
+                None => return Err(PurgeError::Unknown),
+            }
         }
 
-        let record = json.records.remove(0);
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::airtable_mock::AirtableMock;
+    use serde_json::json;
 
-        match ureq::delete(&format!("{}/{}", self.url, record.id))
-            .set("Authorization", &self.auth_header)
-            .call()
-        {
-            Ok(_) => Ok(()),
-            _ => Err(DeleteError::Unknown),
+    fn repo(mock: &AirtableMock) -> AirtableRepository {
+        AirtableRepository::try_new(AirtableMock::API_KEY, AirtableMock::BASE_ID, mock.config())
+            .unwrap()
+    }
+
+    fn seed(mock: &AirtableMock, numbers: &[u16]) {
+        for number in numbers {
+            mock.insert(json!({
+                "number": number,
+                "name": format!("Pokemon {}", number),
+                "types": ["Fire"],
+            }));
+        }
+    }
+
+    #[test]
+    fn it_should_fail_to_connect_when_the_api_key_is_rejected() {
+        let mock = AirtableMock::start();
+
+        let res = AirtableRepository::try_new("revoked", AirtableMock::BASE_ID, mock.config());
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn it_should_be_unhealthy_once_the_api_key_is_rejected() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+        mock.fail_next("GET", 401);
+
+        let res = repo.check_health();
+
+        match res {
+            Err(HealthError::Unavailable(detail)) => {
+                assert_eq!(detail, "Airtable rejected the API key")
+            }
+            _ => unreachable!(),
+        };
+        assert!(repo.check_health().is_ok());
+    }
+
+    #[test]
+    fn it_should_follow_the_pagination_until_the_last_page() {
+        let mock = AirtableMock::start().with_page_size(2);
+        seed(&mock, &[5, 1, 4, 2, 3]);
+
+        let res = repo(&mock).fetch_all();
+
+        match res {
+            Ok(pokemons) => assert_eq!(
+                pokemons
+                    .into_iter()
+                    .map(|p| u16::from(p.number))
+                    .collect::<Vec<u16>>(),
+                vec![1, 2, 3, 4, 5]
+            ),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_retry_rate_limited_and_transient_failures() {
+        let mock = AirtableMock::start();
+        seed(&mock, &[25]);
+        let repo = repo(&mock);
+        mock.fail_next("GET", 429);
+        mock.fail_next("GET", 503);
+
+        let res = repo.fetch_one(PokemonNumber::pikachu());
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn it_should_give_up_after_too_many_attempts() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+        for _ in 0..MAX_ATTEMPTS {
+            mock.fail_next("GET", 502);
+        }
+
+        let res = repo.fetch_all();
+
+        match res {
+            Err(FetchAllError::Unknown) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_not_retry_a_create_that_failed_on_the_server() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+        mock.fail_next("POST", 500);
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        match res {
+            Err(InsertError::Unknown) => {}
+            _ => unreachable!(),
+        };
+        let posts = mock
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == "POST")
+            .count();
+        assert_eq!(posts, 1);
+    }
+
+    #[test]
+    fn it_should_retry_a_rate_limited_create() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+        mock.fail_next("POST", 429);
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        assert!(res.is_ok());
+        assert_eq!(mock.records().len(), 1);
+    }
+
+    #[test]
+    fn it_should_send_at_most_five_requests_per_second() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+
+        for _ in 0..7 {
+            repo.fetch_all().ok();
+        }
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 8);
+        // Arrival times are measured on the server, so leave some slack for the network.
+        for window in requests.windows(REQUESTS_PER_SECOND + 1) {
+            assert!(window[REQUESTS_PER_SECOND].1 - window[0].1 >= Duration::from_millis(900));
         }
     }
+
+    #[test]
+    fn it_should_insert_into_tables_without_a_version_column() {
+        let mock = AirtableMock::start().with_columns(&["number", "name", "types"]);
+        let repo = repo(&mock);
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        match res {
+            Ok(pokemon) => assert_eq!(pokemon.version, 1),
+            _ => unreachable!(),
+        };
+        match repo.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(pokemon.version, 1),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_delete_and_restore_through_the_configured_deletion_column() {
+        let mock =
+            AirtableMock::start().with_columns(&["number", "name", "types", "Removed", "Rev"]);
+        let config = AirtableConfig {
+            fields: AirtableFieldMapping {
+                deleted_at: String::from("Removed"),
+                version: String::from("Rev"),
+                ..AirtableFieldMapping::default()
+            },
+            ..mock.config()
+        };
+        let repo =
+            AirtableRepository::try_new(AirtableMock::API_KEY, AirtableMock::BASE_ID, config)
+                .unwrap();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+
+        assert!(repo.delete(PokemonNumber::pikachu()).is_ok());
+        assert!(mock.records()[0].get("Removed").is_some());
+        match repo.restore(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(pokemon.version, 2),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_use_the_configured_table_and_field_names() {
+        let mock = AirtableMock::start()
+            .with_table("My Dex")
+            .with_columns(&["No.", "Pokemon", "Types", "Deleted", "Version"]);
+        mock.insert(json!({ "No.": 4, "Pokemon": "Charmander", "Types": ["Fire"] }));
+        let config = AirtableConfig {
+            fields: AirtableFieldMapping {
+                number: String::from("No."),
+                name: String::from("Pokemon"),
+                types: String::from("Types"),
+                deleted_at: String::from("Deleted"),
+                version: String::from("Version"),
+            },
+            ..mock.config()
+        };
+        let repo =
+            AirtableRepository::try_new(AirtableMock::API_KEY, AirtableMock::BASE_ID, config)
+                .unwrap();
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        assert!(res.is_ok());
+        assert_eq!(
+            mock.records()[1],
+            json!({ "No.": 25, "Pokemon": "Pikachu", "Types": ["Electric"] })
+        );
+        match repo.fetch_one(PokemonNumber::charmander()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Charmander"),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_back_off_when_another_client_created_the_same_number_concurrently() {
+        let mock = AirtableMock::start();
+        let repo = repo(&mock);
+        mock.race_next_create(json!({ "number": 25, "name": "Pika", "types": ["Electric"] }));
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        match res {
+            Err(InsertError::Conflict) => {}
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            mock.records(),
+            vec![json!({ "number": 25, "name": "Pika", "types": ["Electric"] })]
+        );
+    }
+
+    #[test]
+    fn it_should_let_only_one_of_two_racing_clients_insert_a_number() {
+        let mock = AirtableMock::start();
+        let repos = [repo(&mock), repo(&mock)];
+        let barrier = std::sync::Barrier::new(repos.len());
+
+        let results = std::thread::scope(|scope| {
+            let handles = repos
+                .iter()
+                .map(|repo| {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        barrier.wait();
+                        repo.insert(
+                            PokemonNumber::pikachu(),
+                            PokemonName::pikachu(),
+                            PokemonTypes::pikachu(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(results.iter().filter(|res| res.is_ok()).count(), 1);
+        assert_eq!(mock.records().len(), 1);
+    }
+
+    #[test]
+    fn it_should_resolve_existing_duplicates_to_the_oldest_record() {
+        let mock = AirtableMock::start();
+        mock.insert(json!({ "number": 25, "name": "Pikachu", "types": ["Electric"] }));
+        mock.insert(json!({ "number": 25, "name": "Pika", "types": ["Electric"] }));
+        let repo = repo(&mock);
+
+        match repo.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Pikachu"),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => {
+                assert_eq!(pokemons.len(), 1);
+                assert_eq!(String::from(pokemons[0].name.clone()), "Pikachu");
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_delete_every_duplicate_of_a_number() {
+        let mock = AirtableMock::start();
+        mock.insert(json!({ "number": 25, "name": "Pikachu", "types": ["Electric"] }));
+        mock.insert(json!({ "number": 25, "name": "Pika", "types": ["Electric"] }));
+        let repo = repo(&mock);
+
+        let res = repo.delete(PokemonNumber::pikachu());
+
+        assert!(res.is_ok());
+        let records = mock.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["name"], "Pikachu");
+        assert!(records[0]["deleted_at"].is_string());
+    }
 }
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// What an API key allows, each role being allowed everything the previous ones are.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    Reader,
+    Editor,
+    Admin,
+}
+
+impl From<Role> for &'static str {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Reader => "reader",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = ();
+
+    fn try_from(role: &str) -> Result<Self, Self::Error> {
+        match role {
+            "reader" => Ok(Self::Reader),
+            "editor" => Ok(Self::Editor),
+            "admin" => Ok(Self::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ApiKey {
+    pub name: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+pub enum InsertKeyError {
+    Conflict,
+    Unknown,
+}
+
+pub enum RevokeKeyError {
+    NotFound,
+    Unknown,
+}
+
+/// Where the API keys live. Only their hashes are stored, so that the keys themselves are never
+/// shown again once issued.
+pub trait ApiKeyStore: Send + Sync {
+    /// Fails with a conflict when a key with the same name already exists.
+    fn insert(&self, key: ApiKey, hash: String) -> Result<(), InsertKeyError>;
+    fn fetch_by_hash(&self, hash: &str) -> Result<Option<ApiKey>, ()>;
+    fn revoke(&self, name: &str) -> Result<(), RevokeKeyError>;
+}
+
+/// Hex encoded SHA-256 of a key. Keys are random enough for a plain hash to be enough.
+pub fn hash(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
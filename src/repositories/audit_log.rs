@@ -0,0 +1,155 @@
+use crate::domain::entities::Pokemon;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A change made through a `Repository`, along with who made it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub transport: Transport,
+    pub operation: Operation,
+    pub number: u16,
+    pub before: Option<Snapshot>,
+    pub after: Option<Snapshot>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Api,
+    Cli,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Insert,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl From<Transport> for &'static str {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            Transport::Api => "api",
+            Transport::Cli => "cli",
+        }
+    }
+}
+
+impl TryFrom<&str> for Transport {
+    type Error = ();
+
+    fn try_from(transport: &str) -> Result<Self, Self::Error> {
+        match transport {
+            "api" => Ok(Self::Api),
+            "cli" => Ok(Self::Cli),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Operation> for &'static str {
+    fn from(operation: Operation) -> Self {
+        match operation {
+            Operation::Insert => "insert",
+            Operation::Delete => "delete",
+            Operation::Restore => "restore",
+            Operation::Purge => "purge",
+        }
+    }
+}
+
+impl TryFrom<&str> for Operation {
+    type Error = ();
+
+    fn try_from(operation: &str) -> Result<Self, Self::Error> {
+        match operation {
+            "insert" => Ok(Self::Insert),
+            "delete" => Ok(Self::Delete),
+            "restore" => Ok(Self::Restore),
+            "purge" => Ok(Self::Purge),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A Pokemon as it was before or after a change.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub number: u16,
+    pub name: String,
+    pub types: Vec<String>,
+}
+
+impl From<Pokemon> for Snapshot {
+    fn from(pokemon: Pokemon) -> Self {
+        Self {
+            number: u16::from(pokemon.number),
+            name: String::from(pokemon.name),
+            types: Vec::<String>::from(pokemon.types),
+        }
+    }
+}
+
+/// Who is making changes, and through which interface.
+#[derive(Clone)]
+pub struct Actor {
+    pub name: String,
+    pub transport: Transport,
+}
+
+pub trait AuditLog: Send + Sync {
+    fn record(&self, event: &AuditEvent) -> Result<(), ()>;
+    /// Every event, oldest first, only keeping the ones about `number` when it is given.
+    fn events(&self, number: Option<u16>) -> Result<Vec<AuditEvent>, ()>;
+}
+
+#[cfg(test)]
+pub struct InMemoryAuditLog {
+    error: bool,
+    events: std::sync::Mutex<Vec<AuditEvent>>,
+}
+
+#[cfg(test)]
+impl InMemoryAuditLog {
+    pub fn new() -> Self {
+        Self {
+            error: false,
+            events: std::sync::Mutex::new(vec![]),
+        }
+    }
+
+    pub fn with_error(self) -> Self {
+        Self {
+            error: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+impl AuditLog for InMemoryAuditLog {
+    fn record(&self, event: &AuditEvent) -> Result<(), ()> {
+        match (self.error, self.events.lock()) {
+            (false, Ok(mut events)) => {
+                events.push(event.clone());
+                Ok(())
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn events(&self, number: Option<u16>) -> Result<Vec<AuditEvent>, ()> {
+        match (self.error, self.events.lock()) {
+            (false, Ok(events)) => Ok(events
+                .iter()
+                .filter(|event| number.is_none() || Some(event.number) == number)
+                .cloned()
+                .collect()),
+            _ => Err(()),
+        }
+    }
+}
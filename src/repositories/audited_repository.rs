@@ -0,0 +1,275 @@
+use super::audit_log::{Actor, AuditEvent, AuditLog, Operation, Snapshot};
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use crate::logging::{self, Logger};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Records every successful write to an audit log, on behalf of a single actor.
+///
+/// The write has already happened when its event is recorded, so failing to record it is only
+/// logged rather than turned into an error.
+pub struct AuditedRepository {
+    inner: Arc<dyn Repository>,
+    log: Arc<dyn AuditLog>,
+    actor: Actor,
+    logger: Option<Arc<Logger>>,
+}
+
+impl AuditedRepository {
+    pub fn new(inner: Arc<dyn Repository>, log: Arc<dyn AuditLog>, actor: Actor) -> Self {
+        Self {
+            inner,
+            log,
+            actor,
+            logger: None,
+        }
+    }
+
+    pub fn with_logger(self, logger: Option<Arc<Logger>>) -> Self {
+        Self { logger, ..self }
+    }
+
+    fn record(
+        &self,
+        operation: Operation,
+        number: u16,
+        before: Option<Pokemon>,
+        after: Option<Pokemon>,
+    ) {
+        let event = AuditEvent {
+            at: Utc::now(),
+            actor: self.actor.name.clone(),
+            transport: self.actor.transport,
+            operation,
+            number,
+            before: before.map(Snapshot::from),
+            after: after.map(Snapshot::from),
+        };
+
+        if self.log.record(&event).is_err() {
+            logging::report(
+                self.logger.as_deref(),
+                "error",
+                "Failed to record an audit event",
+                vec![
+                    ("operation", Value::from(<&str>::from(operation))),
+                    ("number", Value::from(number)),
+                    ("actor", Value::from(self.actor.name.as_str())),
+                ],
+            );
+        }
+    }
+}
+
+impl Repository for AuditedRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        let pokemon = self.inner.insert(number.clone(), name, types)?;
+        self.record(
+            Operation::Insert,
+            u16::from(number),
+            None,
+            Some(pokemon.clone()),
+        );
+        Ok(pokemon)
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        self.inner.fetch_all()
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        self.inner.fetch_one(number)
+    }
+
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let before = match self.inner.fetch_one(number.clone()) {
+            Ok(pokemon) => pokemon,
+            Err(FetchOneError::NotFound) => return Err(DeleteError::NotFound),
+            Err(FetchOneError::Unknown) => return Err(DeleteError::Unknown),
+        };
+
+        self.inner.conditional_delete(number.clone(), versions)?;
+        self.record(Operation::Delete, u16::from(number), Some(before), None);
+        Ok(())
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let pokemon = self.inner.conditional_restore(number.clone(), versions)?;
+        self.record(
+            Operation::Restore,
+            u16::from(number),
+            None,
+            Some(pokemon.clone()),
+        );
+        Ok(pokemon)
+    }
+
+    /// Reverts undo writes which never went through, so there is nothing to record.
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        self.inner.revert(revision)
+    }
+
+    fn backend(&self) -> String {
+        self.inner.backend()
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        self.inner.check_health()
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let purged = self.inner.purge(deleted_before)?;
+        for pokemon in purged.iter() {
+            self.record(
+                Operation::Purge,
+                u16::from(pokemon.number.clone()),
+                Some(pokemon.clone()),
+                None,
+            );
+        }
+        Ok(purged)
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let inserted = self.inner.insert_batch(pokemons)?;
+        for pokemon in inserted.iter() {
+            self.record(
+                Operation::Insert,
+                u16::from(pokemon.number.clone()),
+                None,
+                Some(pokemon.clone()),
+            );
+        }
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::audit_log::{InMemoryAuditLog, Transport};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    fn audited(log: Arc<InMemoryAuditLog>) -> AuditedRepository {
+        AuditedRepository::new(
+            Arc::new(InMemoryRepository::new()),
+            log,
+            Actor {
+                name: String::from("ash"),
+                transport: Transport::Cli,
+            },
+        )
+    }
+
+    #[test]
+    fn it_should_record_every_write_with_its_actor_and_snapshots() {
+        let log = Arc::new(InMemoryAuditLog::new());
+        let repo = audited(log.clone());
+
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        repo.restore(PokemonNumber::pikachu()).ok();
+
+        let events = log.events(Some(25)).unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.operation).collect::<Vec<_>>(),
+            vec![Operation::Insert, Operation::Delete, Operation::Restore]
+        );
+        assert!(events
+            .iter()
+            .all(|e| e.actor == "ash" && e.transport == Transport::Cli));
+        assert_eq!(
+            events[1].before.as_ref().map(|p| p.name.as_str()),
+            Some("Pikachu")
+        );
+        assert_eq!(events[1].after, None);
+    }
+
+    #[test]
+    fn it_should_record_every_purged_pokemon_as_it_was_when_deleted() {
+        let log = Arc::new(InMemoryAuditLog::new());
+        let repo = audited(log.clone());
+        for (number, name, types) in [
+            (
+                PokemonNumber::pikachu(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            ),
+            (
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            ),
+        ] {
+            repo.insert(number.clone(), name, types).ok();
+            repo.delete(number).ok();
+        }
+
+        repo.purge(Utc::now() + chrono::TimeDelta::seconds(1)).ok();
+
+        let purges = log
+            .events(None)
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.operation == Operation::Purge)
+            .collect::<Vec<AuditEvent>>();
+        assert_eq!(purges.len(), 2);
+        for (number, name) in [(4, "Charmander"), (25, "Pikachu")] {
+            let purge = match purges.iter().find(|e| e.number == number) {
+                Some(purge) => purge,
+                None => unreachable!(),
+            };
+            assert_eq!(purge.before.as_ref().map(|p| p.name.as_str()), Some(name));
+            assert_eq!(purge.after, None);
+            assert_eq!(purge.actor, "ash");
+        }
+    }
+
+    #[test]
+    fn it_should_not_record_failed_writes() {
+        let log = Arc::new(InMemoryAuditLog::new());
+        let repo = audited(log.clone());
+
+        repo.delete(PokemonNumber::pikachu()).ok();
+        repo.restore(PokemonNumber::pikachu()).ok();
+
+        assert!(log.events(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_the_write_when_the_event_cannot_be_recorded() {
+        let repo = audited(Arc::new(InMemoryAuditLog::new().with_error()));
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        assert!(res.is_ok());
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+}
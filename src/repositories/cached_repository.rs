@@ -0,0 +1,341 @@
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Read-through cache in front of a slow repository.
+///
+/// Results of `fetch_one` and `fetch_all` are kept for `ttl`, with at most `capacity` Pokemons
+/// cached individually. Every write through the cache invalidates what it could have changed.
+pub struct CachedRepository {
+    inner: Arc<dyn Repository>,
+    ttl: Duration,
+    capacity: usize,
+    cache: Mutex<Cache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Cache {
+    all: Option<Entry<Vec<Pokemon>>>,
+    one: HashMap<u16, Entry<Pokemon>>,
+    /// Bumped by every write, so that a read which started before it does not fill the cache
+    /// with what it fetched.
+    generation: u64,
+}
+
+struct Entry<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+impl CachedRepository {
+    pub fn new(inner: Arc<dyn Repository>, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            capacity,
+            cache: Mutex::new(Cache {
+                all: None,
+                one: HashMap::new(),
+                generation: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn lookup<T: Clone>(&self, read: impl FnOnce(&Cache) -> Option<&Entry<T>>) -> (Option<T>, u64) {
+        let cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let generation = cache.generation;
+
+        match read(&cache) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                (Some(entry.value.clone()), generation)
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                (None, generation)
+            }
+        }
+    }
+
+    fn store(&self, generation: u64, write: impl FnOnce(&mut Cache, Instant)) {
+        let mut cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if cache.generation == generation {
+            write(&mut cache, Instant::now() + self.ttl);
+        }
+    }
+
+    fn invalidate(&self, numbers: &[PokemonNumber]) {
+        let mut cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        cache.generation += 1;
+        cache.all = None;
+        for number in numbers {
+            cache.one.remove(&u16::from(number.clone()));
+        }
+    }
+}
+
+impl Cache {
+    fn store_one(&mut self, pokemon: Pokemon, expires_at: Instant, capacity: usize) {
+        let now = Instant::now();
+        self.one.retain(|_, entry| entry.expires_at > now);
+
+        while !self.one.is_empty() && self.one.len() >= capacity {
+            let oldest = self
+                .one
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(number, _)| *number);
+            if let Some(number) = oldest {
+                self.one.remove(&number);
+            }
+        }
+
+        if capacity > 0 {
+            self.one.insert(
+                u16::from(pokemon.number.clone()),
+                Entry {
+                    value: pokemon,
+                    expires_at,
+                },
+            );
+        }
+    }
+}
+
+impl Repository for CachedRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        let res = self.inner.insert(number.clone(), name, types);
+        self.invalidate(&[number]);
+        res
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        let (cached, generation) = self.lookup(|cache| cache.all.as_ref());
+        if let Some(pokemons) = cached {
+            return Ok(pokemons);
+        }
+
+        let pokemons = self.inner.fetch_all()?;
+        self.store(generation, |cache, expires_at| {
+            cache.all = Some(Entry {
+                value: pokemons.clone(),
+                expires_at,
+            });
+        });
+        Ok(pokemons)
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        let key = u16::from(number.clone());
+        let (cached, generation) = self.lookup(|cache| cache.one.get(&key));
+        if let Some(pokemon) = cached {
+            return Ok(pokemon);
+        }
+
+        let pokemon = self.inner.fetch_one(number)?;
+        self.store(generation, |cache, expires_at| {
+            cache.store_one(pokemon.clone(), expires_at, self.capacity);
+        });
+        Ok(pokemon)
+    }
+
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let res = self.inner.conditional_delete(number.clone(), versions);
+        self.invalidate(&[number]);
+        res
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let res = self.inner.conditional_restore(number.clone(), versions);
+        self.invalidate(&[number]);
+        res
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        let number = revision.number();
+        let res = self.inner.revert(revision);
+        self.invalidate(&[number]);
+        res
+    }
+
+    fn backend(&self) -> String {
+        format!("cached {}", self.inner.backend())
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        self.inner.check_health()
+    }
+
+    /// Purged Pokemons were already deleted, so nothing cached can refer to them.
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        self.inner.purge(deleted_before)
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let numbers = pokemons
+            .iter()
+            .map(|pokemon| pokemon.number.clone())
+            .collect::<Vec<PokemonNumber>>();
+        let res = self.inner.insert_batch(pokemons);
+        self.invalidate(&numbers);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+    use std::thread;
+
+    fn cached(inner: Arc<InMemoryRepository>, ttl: Duration) -> CachedRepository {
+        CachedRepository::new(inner, ttl, 100)
+    }
+
+    fn insert_pikachu(repo: &dyn Repository) {
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+    }
+
+    #[test]
+    fn it_should_serve_repeated_reads_from_the_cache() {
+        let inner = Arc::new(InMemoryRepository::new());
+        insert_pikachu(&*inner);
+        let repo = cached(inner.clone(), Duration::from_secs(60));
+
+        repo.fetch_one(PokemonNumber::pikachu()).ok();
+        repo.fetch_all().ok();
+        inner.delete(PokemonNumber::pikachu()).ok();
+
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 1),
+            _ => unreachable!(),
+        };
+        assert_eq!(repo.misses(), 2);
+        assert_eq!(repo.hits(), 2);
+    }
+
+    #[test]
+    fn it_should_read_again_once_the_entries_expired() {
+        let inner = Arc::new(InMemoryRepository::new());
+        insert_pikachu(&*inner);
+        let repo = cached(inner.clone(), Duration::from_millis(20));
+
+        repo.fetch_one(PokemonNumber::pikachu()).ok();
+        inner.delete(PokemonNumber::pikachu()).ok();
+        thread::sleep(Duration::from_millis(40));
+
+        match repo.fetch_one(PokemonNumber::pikachu()) {
+            Err(FetchOneError::NotFound) => {}
+            _ => unreachable!(),
+        };
+        assert_eq!(repo.hits(), 0);
+    }
+
+    #[test]
+    fn it_should_invalidate_the_cache_on_writes() {
+        let inner = Arc::new(InMemoryRepository::new());
+        let repo = cached(inner, Duration::from_secs(60));
+        repo.fetch_all().ok();
+
+        insert_pikachu(&repo);
+
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 1),
+            _ => unreachable!(),
+        };
+        repo.fetch_one(PokemonNumber::pikachu()).ok();
+
+        repo.delete(PokemonNumber::pikachu()).ok();
+
+        match repo.fetch_one(PokemonNumber::pikachu()) {
+            Err(FetchOneError::NotFound) => {}
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_not_cache_more_pokemons_than_its_capacity() {
+        let inner = Arc::new(InMemoryRepository::new());
+        for number in 1..=3 {
+            inner
+                .insert(
+                    PokemonNumber::try_from(number).unwrap(),
+                    PokemonName::pikachu(),
+                    PokemonTypes::pikachu(),
+                )
+                .ok();
+        }
+        let repo = CachedRepository::new(inner, Duration::from_secs(60), 2);
+
+        for number in 1..=3 {
+            repo.fetch_one(PokemonNumber::try_from(number).unwrap())
+                .ok();
+        }
+
+        assert_eq!(repo.cache.lock().unwrap().one.len(), 2);
+        assert!(!repo.cache.lock().unwrap().one.contains_key(&1));
+    }
+
+    #[test]
+    fn it_should_not_cache_errors() {
+        let inner = Arc::new(InMemoryRepository::new());
+        let repo = cached(inner.clone(), Duration::from_secs(60));
+        repo.fetch_one(PokemonNumber::pikachu()).ok();
+
+        insert_pikachu(&*inner);
+
+        assert!(repo.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+}
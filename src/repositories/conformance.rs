@@ -0,0 +1,493 @@
+//! Behaviour shared by every `Repository` implementation.
+//!
+//! Each check takes any repository, and `conformance_tests!` runs all of them against a backend
+//! built by a factory returning the repository along with whatever must outlive it (a temporary
+//! directory, a mock server...).
+
+use super::{DeleteError, FetchOneError, InsertError, Repository, RestoreError, Revision};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use chrono::{TimeDelta, Utc};
+
+fn summary(pokemon: Pokemon) -> (u16, String, Vec<String>) {
+    (
+        u16::from(pokemon.number),
+        String::from(pokemon.name),
+        Vec::<String>::from(pokemon.types),
+    )
+}
+
+fn fire_and_electric() -> PokemonTypes {
+    PokemonTypes::try_from(vec![String::from("Fire"), String::from("Electric")]).unwrap()
+}
+
+pub fn it_should_fetch_an_inserted_pokemon(repo: &dyn Repository) {
+    let inserted = repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        fire_and_electric(),
+    );
+
+    let expected = (
+        25,
+        String::from("Pikachu"),
+        vec![String::from("Fire"), String::from("Electric")],
+    );
+    match inserted {
+        Ok(pokemon) => assert_eq!(summary(pokemon), expected),
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(summary(pokemon), expected),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_return_a_conflict_error_when_the_number_already_exists(repo: &dyn Repository) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    )
+    .ok();
+
+    let res = repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::charmander(),
+        PokemonTypes::charmander(),
+    );
+
+    match res {
+        Err(InsertError::Conflict) => {}
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Pikachu"),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_fetch_all_the_pokemons_ordered_by_increasing_number(repo: &dyn Repository) {
+    for number in [25, 4, 150, 1] {
+        repo.insert(
+            PokemonNumber::try_from(number).unwrap(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+    }
+
+    match repo.fetch_all() {
+        Ok(pokemons) => assert_eq!(
+            pokemons
+                .into_iter()
+                .map(|p| u16::from(p.number))
+                .collect::<Vec<u16>>(),
+            vec![1, 4, 25, 150]
+        ),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_fetch_no_pokemons_from_an_empty_repository(repo: &dyn Repository) {
+    match repo.fetch_all() {
+        Ok(pokemons) => assert!(pokemons.is_empty()),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_return_a_not_found_error_when_fetching_a_missing_pokemon(repo: &dyn Repository) {
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Err(FetchOneError::NotFound) => {}
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_return_a_not_found_error_when_deleting_a_missing_pokemon(repo: &dyn Repository) {
+    match repo.delete(PokemonNumber::pikachu()) {
+        Err(DeleteError::NotFound) => {}
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_delete_a_pokemon_along_with_its_types(repo: &dyn Repository) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        fire_and_electric(),
+    )
+    .ok();
+    repo.insert(
+        PokemonNumber::charmander(),
+        PokemonName::charmander(),
+        PokemonTypes::charmander(),
+    )
+    .ok();
+
+    match repo.delete(PokemonNumber::pikachu()) {
+        Ok(()) => {}
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Err(FetchOneError::NotFound) => {}
+        _ => unreachable!(),
+    };
+
+    // Inserting the number again must not resurrect the types of the deleted Pokemon.
+    match repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    ) {
+        Ok(_) => {}
+        _ => unreachable!(),
+    };
+    match repo.fetch_all() {
+        Ok(pokemons) => assert_eq!(
+            pokemons.into_iter().map(summary).collect::<Vec<_>>(),
+            vec![
+                (4, String::from("Charmander"), vec![String::from("Fire")]),
+                (25, String::from("Pikachu"), vec![String::from("Electric")]),
+            ]
+        ),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_insert_a_batch_of_pokemons(repo: &dyn Repository) {
+    let batch = vec![
+        Pokemon::new(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        ),
+        Pokemon::new(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        ),
+    ];
+
+    match repo.insert_batch(batch) {
+        Ok(inserted) => assert_eq!(inserted.len(), 2),
+        _ => unreachable!(),
+    };
+    match repo.fetch_all() {
+        Ok(pokemons) => assert_eq!(pokemons.len(), 2),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_restore_a_deleted_pokemon(repo: &dyn Repository) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        fire_and_electric(),
+    )
+    .ok();
+    repo.delete(PokemonNumber::pikachu()).ok();
+
+    match repo.restore(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Pikachu"),
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(
+            Vec::<String>::from(pokemon.types),
+            vec![String::from("Fire"), String::from("Electric")]
+        ),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_return_a_not_found_error_when_restoring_a_pokemon_which_is_not_deleted(
+    repo: &dyn Repository,
+) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    )
+    .ok();
+
+    for number in [PokemonNumber::pikachu(), PokemonNumber::charmander()] {
+        match repo.restore(number) {
+            Err(RestoreError::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+}
+
+pub fn it_should_only_purge_the_pokemons_deleted_before_the_given_time(repo: &dyn Repository) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    )
+    .ok();
+    repo.delete(PokemonNumber::pikachu()).ok();
+
+    match repo.purge(Utc::now() - TimeDelta::hours(1)) {
+        Ok(purged) => assert!(purged.is_empty()),
+        _ => unreachable!(),
+    };
+    match repo.purge(Utc::now() + TimeDelta::hours(1)) {
+        Ok(purged) => assert_eq!(
+            purged.into_iter().map(summary).collect::<Vec<_>>(),
+            vec![(25, String::from("Pikachu"), vec![String::from("Electric")])]
+        ),
+        _ => unreachable!(),
+    };
+    match repo.restore(PokemonNumber::pikachu()) {
+        Err(RestoreError::NotFound) => {}
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_bump_the_version_of_a_pokemon_on_every_write(repo: &dyn Repository) {
+    let version = |repo: &dyn Repository| match repo.fetch_one(PokemonNumber::pikachu()) {
+        Ok(pokemon) => pokemon.version,
+        _ => unreachable!(),
+    };
+
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    )
+    .ok();
+    assert_eq!(version(repo), 1);
+
+    repo.delete(PokemonNumber::pikachu()).ok();
+    match repo.restore(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 2),
+        _ => unreachable!(),
+    };
+    assert_eq!(version(repo), 2);
+
+    repo.delete(PokemonNumber::pikachu()).ok();
+    match repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    ) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 3),
+        _ => unreachable!(),
+    };
+    assert_eq!(version(repo), 3);
+}
+
+pub fn it_should_only_apply_conditional_writes_at_the_expected_version(repo: &dyn Repository) {
+    repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    )
+    .ok();
+
+    match repo.conditional_delete(PokemonNumber::pikachu(), Some(&[2])) {
+        Err(DeleteError::VersionMismatch) => {}
+        _ => unreachable!(),
+    };
+    match repo.conditional_delete(PokemonNumber::pikachu(), Some(&[3, 1])) {
+        Ok(()) => {}
+        _ => unreachable!(),
+    };
+    match repo.conditional_restore(PokemonNumber::pikachu(), Some(&[2])) {
+        Err(RestoreError::VersionMismatch) => {}
+        _ => unreachable!(),
+    };
+    match repo.conditional_restore(PokemonNumber::pikachu(), Some(&[3, 1])) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 2),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_revert_a_pokemon_to_the_given_revision(repo: &dyn Repository) {
+    let pikachu = repo
+        .insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok()
+        .unwrap();
+
+    repo.delete(PokemonNumber::pikachu()).ok();
+    match repo.revert(Revision::Live(pikachu.clone())) {
+        Ok(()) => {}
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 1),
+        _ => unreachable!(),
+    };
+
+    repo.delete(PokemonNumber::pikachu()).ok();
+    repo.restore(PokemonNumber::pikachu()).ok();
+    match repo.revert(Revision::Deleted(pikachu)) {
+        Ok(()) => {}
+        _ => unreachable!(),
+    };
+    match repo.conditional_restore(PokemonNumber::pikachu(), Some(&[1])) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 2),
+        _ => unreachable!(),
+    };
+
+    match repo.revert(Revision::Absent(PokemonNumber::pikachu())) {
+        Ok(()) => {}
+        _ => unreachable!(),
+    };
+    match repo.fetch_one(PokemonNumber::pikachu()) {
+        Err(FetchOneError::NotFound) => {}
+        _ => unreachable!(),
+    };
+    match repo.restore(PokemonNumber::pikachu()) {
+        Err(RestoreError::NotFound) => {}
+        _ => unreachable!(),
+    };
+    match repo.insert(
+        PokemonNumber::pikachu(),
+        PokemonName::pikachu(),
+        PokemonTypes::pikachu(),
+    ) {
+        Ok(pokemon) => assert_eq!(pokemon.version, 1),
+        _ => unreachable!(),
+    };
+}
+
+pub fn it_should_be_healthy_once_built(repo: &dyn Repository) {
+    assert!(repo.check_health().is_ok());
+    assert!(!repo.backend().is_empty());
+}
+
+macro_rules! conformance_tests {
+    ($backend:ident, $factory:expr) => {
+        mod $backend {
+            use super::*;
+
+            conformance_tests!(
+                @tests $factory,
+                it_should_fetch_an_inserted_pokemon,
+                it_should_return_a_conflict_error_when_the_number_already_exists,
+                it_should_fetch_all_the_pokemons_ordered_by_increasing_number,
+                it_should_fetch_no_pokemons_from_an_empty_repository,
+                it_should_return_a_not_found_error_when_fetching_a_missing_pokemon,
+                it_should_return_a_not_found_error_when_deleting_a_missing_pokemon,
+                it_should_delete_a_pokemon_along_with_its_types,
+                it_should_insert_a_batch_of_pokemons,
+                it_should_restore_a_deleted_pokemon,
+                it_should_return_a_not_found_error_when_restoring_a_pokemon_which_is_not_deleted,
+                it_should_only_purge_the_pokemons_deleted_before_the_given_time,
+                it_should_bump_the_version_of_a_pokemon_on_every_write,
+                it_should_only_apply_conditional_writes_at_the_expected_version,
+                it_should_revert_a_pokemon_to_the_given_revision,
+                it_should_be_healthy_once_built
+            );
+        }
+    };
+    (@tests $factory:expr, $($check:ident),*) => {
+        $(
+            #[test]
+            fn $check() {
+                let (repo, _guard) = $factory();
+                super::$check(&repo);
+            }
+        )*
+    };
+}
+
+mod backends {
+    use super::super::{
+        airtable_mock::AirtableMock,
+        airtable_repository::AirtableRepository,
+        audit_log::{Actor, InMemoryAuditLog, Transport},
+        audited_repository::AuditedRepository,
+        cached_repository::CachedRepository,
+        event_sourced_repository::EventSourcedRepository,
+        in_memory_repository::InMemoryRepository,
+        instrumented_repository::InstrumentedRepository,
+        json_file_repository::JsonFileRepository,
+        mirrored_repository::{MirroredRepository, SecondaryFailurePolicy},
+        sqlite_repository::SqliteRepository,
+    };
+    use crate::logging::{Format, Logger};
+    use crate::metrics::Metrics;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    pub fn in_memory() -> (InMemoryRepository, ()) {
+        (InMemoryRepository::new(), ())
+    }
+
+    pub fn sqlite() -> (SqliteRepository, tempfile::TempDir) {
+        SqliteRepository::temp()
+    }
+
+    pub fn json_file() -> (JsonFileRepository, tempfile::TempDir) {
+        JsonFileRepository::temp()
+    }
+
+    pub fn event_sourced() -> (EventSourcedRepository, tempfile::TempDir) {
+        EventSourcedRepository::temp()
+    }
+
+    pub fn airtable() -> (AirtableRepository, AirtableMock) {
+        let mock = AirtableMock::start();
+        let repo = AirtableRepository::try_new(
+            AirtableMock::API_KEY,
+            AirtableMock::BASE_ID,
+            mock.config(),
+        )
+        .unwrap();
+        (repo, mock)
+    }
+
+    pub fn cached() -> (CachedRepository, ()) {
+        let inner = Arc::new(InMemoryRepository::new());
+        (CachedRepository::new(inner, Duration::from_secs(60), 2), ())
+    }
+
+    pub fn audited() -> (AuditedRepository, ()) {
+        let actor = Actor {
+            name: String::from("conformance"),
+            transport: Transport::Cli,
+        };
+        (
+            AuditedRepository::new(
+                Arc::new(InMemoryRepository::new()),
+                Arc::new(InMemoryAuditLog::new()),
+                actor,
+            ),
+            (),
+        )
+    }
+
+    pub fn instrumented() -> (InstrumentedRepository, ()) {
+        let logger = Logger::with_sink(Format::Logfmt, Box::new(std::io::sink()));
+        (
+            InstrumentedRepository::new(Arc::new(InMemoryRepository::new()), "memory")
+                .with_logger(Arc::new(logger))
+                .with_metrics(Arc::new(Metrics::new())),
+            (),
+        )
+    }
+
+    pub fn mirrored() -> (MirroredRepository, ()) {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+        (
+            MirroredRepository::new(primary, secondary, SecondaryFailurePolicy::Fail),
+            (),
+        )
+    }
+}
+
+conformance_tests!(in_memory, backends::in_memory);
+conformance_tests!(sqlite, backends::sqlite);
+conformance_tests!(json_file, backends::json_file);
+conformance_tests!(event_sourced, backends::event_sourced);
+conformance_tests!(airtable, backends::airtable);
+conformance_tests!(cached, backends::cached);
+conformance_tests!(mirrored, backends::mirrored);
+conformance_tests!(audited, backends::audited);
+conformance_tests!(instrumented, backends::instrumented);
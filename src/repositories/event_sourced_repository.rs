@@ -0,0 +1,960 @@
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use crate::logging::{self, Logger};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const SNAPSHOT_EVERY: u64 = 100;
+
+/// A point in the history of the Pokedex, either a time or the id of the last event to replay.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AsOf {
+    Time(DateTime<Utc>),
+    Event(u64),
+}
+
+/// Repositories able to tell what the Pokedex looked like at any point in its history.
+pub trait History: Send + Sync {
+    fn fetch_all_as_of(&self, as_of: AsOf) -> Result<Vec<Pokemon>, FetchAllError>;
+}
+
+/// Stores every change as an event appended to a JSON lines file, the Pokedex being whatever
+/// replaying them produces.
+///
+/// Every `SNAPSHOT_EVERY` events the replayed state is appended to a companion `.snapshots` file,
+/// so that neither opening the log nor reading it as of some point has to replay it from the
+/// start. Where each snapshot starts in that file is indexed the first time it is read, so that
+/// only the snapshot replayed from is parsed afterwards.
+///
+/// The log is locked for the duration of every operation, and the events appended by other
+/// processes since it was last read are replayed first.
+pub struct EventSourcedRepository {
+    path: PathBuf,
+    snapshots_path: PathBuf,
+    snapshot_every: u64,
+    state: Mutex<State>,
+    snapshots: Mutex<SnapshotIndex>,
+    logger: Option<Arc<Logger>>,
+}
+
+struct State {
+    dex: Dex,
+    last_id: u64,
+    /// How far into the log the events have been replayed.
+    offset: u64,
+}
+
+/// Every Pokemon by number, the deleted ones included until they are purged.
+type Dex = BTreeMap<u16, Row>;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Row {
+    number: u16,
+    name: String,
+    types: Vec<String>,
+    version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: Event,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Event {
+    PokemonCreated {
+        number: u16,
+        name: String,
+        types: Vec<String>,
+    },
+    PokemonDeleted {
+        number: u16,
+    },
+    PokemonRestored {
+        number: u16,
+    },
+    PokemonsPurged {
+        deleted_before: DateTime<Utc>,
+    },
+    /// Undoes a write which could not be completed, leaving the Pokemon as it was before.
+    PokemonReverted {
+        number: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pokemon: Option<Row>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    event_id: u64,
+    at: DateTime<Utc>,
+    offset: u64,
+    pokemons: Vec<Row>,
+}
+
+/// Only what telling snapshots apart needs, the Pokemons being skipped.
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    event_id: u64,
+    at: DateTime<Utc>,
+}
+
+struct SnapshotEntry {
+    event_id: u64,
+    at: DateTime<Utc>,
+    /// Where the snapshot starts in the `.snapshots` file.
+    position: u64,
+}
+
+#[derive(Default)]
+struct SnapshotIndex {
+    entries: Vec<SnapshotEntry>,
+    /// How far into the `.snapshots` file the snapshots have been indexed.
+    indexed: u64,
+}
+
+enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+impl EventSourcedRepository {
+    pub fn try_new(path: &str) -> Result<Self, ()> {
+        let path = PathBuf::from(path);
+        let repo = Self {
+            snapshots_path: with_suffix(&path, ".snapshots"),
+            path,
+            snapshot_every: SNAPSHOT_EVERY,
+            state: Mutex::new(State {
+                dex: Dex::new(),
+                last_id: 0,
+                offset: 0,
+            }),
+            snapshots: Mutex::new(SnapshotIndex::default()),
+            logger: None,
+        };
+
+        if let Some(snapshot) = repo.snapshot(|_, _| true)? {
+            let mut state = match repo.state.lock() {
+                Ok(state) => state,
+                _ => return Err(()),
+            };
+            *state = State::from(snapshot);
+        }
+
+        if repo.open(LockMode::Shared).is_err() {
+            return Err(());
+        }
+
+        Ok(repo)
+    }
+
+    pub fn with_logger(self, logger: Option<Arc<Logger>>) -> Self {
+        Self { logger, ..self }
+    }
+
+    /// Locks the log, which stays locked until the returned file is dropped, and replays the
+    /// events appended since it was last read.
+    fn open(&self, mode: LockMode) -> Result<(File, MutexGuard<'_, State>), ()> {
+        let file = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&self.path)
+        {
+            Ok(file) => file,
+            _ => return Err(()),
+        };
+
+        let res = match mode {
+            LockMode::Shared => file.lock_shared(),
+            LockMode::Exclusive => file.lock(),
+        };
+        if res.is_err() {
+            return Err(());
+        }
+
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            _ => return Err(()),
+        };
+
+        let State {
+            dex,
+            last_id,
+            offset,
+        } = &mut *state;
+        *offset = replay(&file, *offset, dex, last_id, |_| true)?;
+
+        Ok((file, state))
+    }
+
+    /// Appends the events to the log in a single write, then applies them to the state.
+    fn append(&self, file: &mut File, state: &mut State, events: Vec<Event>) -> Result<(), ()> {
+        let at = Utc::now();
+        let records = events
+            .into_iter()
+            .zip(state.last_id + 1..)
+            .map(|(event, id)| Record { id, at, event })
+            .collect::<Vec<Record>>();
+
+        let mut lines = vec![];
+        for record in records.iter() {
+            if serde_json::to_writer(&mut lines, record).is_err() {
+                return Err(());
+            }
+            lines.push(b'\n');
+        }
+
+        if file.write_all(&lines).is_err() || file.sync_data().is_err() {
+            return Err(());
+        }
+
+        let previous_id = state.last_id;
+        for record in records {
+            state.last_id = record.id;
+            apply(&mut state.dex, record);
+        }
+        state.offset = match file.metadata() {
+            Ok(metadata) => metadata.len(),
+            _ => return Err(()),
+        };
+
+        // The events are already in the log, which is all a snapshot saves replaying.
+        if state.last_id / self.snapshot_every > previous_id / self.snapshot_every
+            && self.write_snapshot(state, at).is_err()
+        {
+            logging::report(
+                self.logger.as_deref(),
+                "warn",
+                "Failed to snapshot the event log",
+                vec![("event_id", serde_json::Value::from(state.last_id))],
+            );
+        }
+
+        Ok(())
+    }
+
+    fn write_snapshot(&self, state: &State, at: DateTime<Utc>) -> Result<(), ()> {
+        let snapshot = Snapshot {
+            event_id: state.last_id,
+            at,
+            offset: state.offset,
+            pokemons: state.dex.values().cloned().collect(),
+        };
+
+        let mut line = match serde_json::to_vec(&snapshot) {
+            Ok(line) => line,
+            _ => return Err(()),
+        };
+        line.push(b'\n');
+
+        let mut file = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.snapshots_path)
+        {
+            Ok(file) => file,
+            _ => return Err(()),
+        };
+
+        match file.write_all(&line).and_then(|_| file.sync_data()) {
+            Ok(()) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// The latest snapshot for which `keep` holds of its event id and time, if any.
+    fn snapshot(&self, keep: impl Fn(u64, DateTime<Utc>) -> bool) -> Result<Option<Snapshot>, ()> {
+        let file = match File::open(&self.snapshots_path) {
+            Ok(file) => file,
+            _ if !self.snapshots_path.exists() => return Ok(None),
+            _ => return Err(()),
+        };
+        let mut reader = BufReader::new(file);
+
+        let mut index = match self.snapshots.lock() {
+            Ok(index) => index,
+            _ => return Err(()),
+        };
+
+        // Indexes the snapshots appended since, by this process or another one.
+        if reader.seek(SeekFrom::Start(index.indexed)).is_err() {
+            return Err(());
+        }
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = match reader.read_line(&mut line) {
+                Ok(read) => read,
+                _ => return Err(()),
+            };
+            // A snapshot still being written is indexed once complete.
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+
+            let position = index.indexed;
+            index.indexed += read as u64;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<SnapshotHeader>(&line) {
+                Ok(header) => index.entries.push(SnapshotEntry {
+                    event_id: header.event_id,
+                    at: header.at,
+                    position,
+                }),
+                _ => return Err(()),
+            }
+        }
+
+        let position = match index
+            .entries
+            .iter()
+            .rev()
+            .find(|entry| keep(entry.event_id, entry.at))
+        {
+            Some(entry) => entry.position,
+            None => return Ok(None),
+        };
+        drop(index);
+
+        line.clear();
+        if reader.seek(SeekFrom::Start(position)).is_err() || reader.read_line(&mut line).is_err() {
+            return Err(());
+        }
+        match serde_json::from_str::<Snapshot>(&line) {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Snapshot> for State {
+    fn from(snapshot: Snapshot) -> Self {
+        Self {
+            dex: snapshot
+                .pokemons
+                .into_iter()
+                .map(|row| (row.number, row))
+                .collect(),
+            last_id: snapshot.event_id,
+            offset: snapshot.offset,
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Applies the events found after `offset` in the log until one fails `keep`, returning the
+/// offset reached.
+fn replay(
+    file: &File,
+    offset: u64,
+    dex: &mut Dex,
+    last_id: &mut u64,
+    keep: impl Fn(&Record) -> bool,
+) -> Result<u64, ()> {
+    let mut reader = BufReader::new(file);
+    if reader.seek(SeekFrom::Start(offset)).is_err() {
+        return Err(());
+    }
+
+    let mut offset = offset;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let read = match reader.read_line(&mut line) {
+            Ok(0) => return Ok(offset),
+            Ok(read) => read as u64,
+            _ => return Err(()),
+        };
+
+        if !line.trim().is_empty() {
+            let record = match serde_json::from_str::<Record>(&line) {
+                Ok(record) => record,
+                _ => return Err(()),
+            };
+
+            if !keep(&record) {
+                return Ok(offset);
+            }
+
+            *last_id = record.id;
+            apply(dex, record);
+        }
+
+        offset += read;
+    }
+}
+
+fn apply(dex: &mut Dex, record: Record) {
+    match record.event {
+        Event::PokemonCreated {
+            number,
+            name,
+            types,
+        } => {
+            // Replaying gives the versions back, carrying on from the tombstone if there is one.
+            let version = dex.get(&number).map_or(1, |row| row.version + 1);
+            dex.insert(
+                number,
+                Row {
+                    number,
+                    name,
+                    types,
+                    version,
+                    deleted_at: None,
+                },
+            );
+        }
+        Event::PokemonDeleted { number } => {
+            if let Some(row) = dex.get_mut(&number) {
+                row.deleted_at = Some(record.at);
+            }
+        }
+        Event::PokemonRestored { number } => {
+            if let Some(row) = dex.get_mut(&number) {
+                row.version += 1;
+                row.deleted_at = None;
+            }
+        }
+        Event::PokemonsPurged { deleted_before } => {
+            dex.retain(|_, row| match row.deleted_at {
+                Some(deleted_at) => deleted_at >= deleted_before,
+                None => true,
+            });
+        }
+        Event::PokemonReverted { number, pokemon } => {
+            match pokemon {
+                Some(row) => dex.insert(number, row),
+                None => dex.remove(&number),
+            };
+        }
+    }
+}
+
+fn live(dex: &Dex, number: u16) -> Option<&Row> {
+    dex.get(&number).filter(|row| row.deleted_at.is_none())
+}
+
+fn to_pokemon(row: &Row) -> Result<Pokemon, ()> {
+    match (
+        PokemonNumber::try_from(row.number),
+        PokemonName::try_from(row.name.clone()),
+        PokemonTypes::try_from(row.types.clone()),
+    ) {
+        (Ok(number), Ok(name), Ok(types)) => {
+            Ok(Pokemon::new(number, name, types).with_version(row.version))
+        }
+        _ => Err(()),
+    }
+}
+
+fn to_pokemons(dex: &Dex) -> Result<Vec<Pokemon>, ()> {
+    dex.values()
+        .filter(|row| row.deleted_at.is_none())
+        .map(to_pokemon)
+        .collect()
+}
+
+fn created(pokemon: Pokemon) -> Event {
+    Event::PokemonCreated {
+        number: u16::from(pokemon.number),
+        name: String::from(pokemon.name),
+        types: Vec::<String>::from(pokemon.types),
+    }
+}
+
+impl Repository for EventSourcedRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let key = u16::from(number.clone());
+        if live(&state.dex, key).is_some() {
+            return Err(InsertError::Conflict);
+        }
+
+        let pokemon = Pokemon::new(number, name, types);
+        if self
+            .append(&mut file, &mut state, vec![created(pokemon)])
+            .is_err()
+        {
+            return Err(InsertError::Unknown);
+        }
+
+        match live(&state.dex, key).map(to_pokemon) {
+            Some(Ok(pokemon)) => Ok(pokemon),
+            _ => Err(InsertError::Unknown),
+        }
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        let (_file, state) = match self.open(LockMode::Shared) {
+            Ok(guards) => guards,
+            _ => return Err(FetchAllError::Unknown),
+        };
+
+        match to_pokemons(&state.dex) {
+            Ok(pokemons) => Ok(pokemons),
+            _ => Err(FetchAllError::Unknown),
+        }
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        let (_file, state) = match self.open(LockMode::Shared) {
+            Ok(guards) => guards,
+            _ => return Err(FetchOneError::Unknown),
+        };
+
+        match live(&state.dex, u16::from(number)).map(to_pokemon) {
+            Some(Ok(pokemon)) => Ok(pokemon),
+            Some(Err(())) => Err(FetchOneError::Unknown),
+            None => Err(FetchOneError::NotFound),
+        }
+    }
+
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(DeleteError::Unknown),
+        };
+
+        let number = u16::from(number);
+        match live(&state.dex, number) {
+            Some(row) if versions.is_some_and(|versions| !versions.contains(&row.version)) => {
+                return Err(DeleteError::VersionMismatch)
+            }
+            Some(_) => {}
+            None => return Err(DeleteError::NotFound),
+        };
+
+        match self.append(
+            &mut file,
+            &mut state,
+            vec![Event::PokemonDeleted { number }],
+        ) {
+            Ok(()) => Ok(()),
+            _ => Err(DeleteError::Unknown),
+        }
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        let number = u16::from(number);
+        match state.dex.get(&number) {
+            Some(row) if row.deleted_at.is_none() => return Err(RestoreError::NotFound),
+            Some(row) if versions.is_some_and(|versions| !versions.contains(&row.version)) => {
+                return Err(RestoreError::VersionMismatch)
+            }
+            Some(_) => {}
+            None => return Err(RestoreError::NotFound),
+        };
+
+        if self
+            .append(
+                &mut file,
+                &mut state,
+                vec![Event::PokemonRestored { number }],
+            )
+            .is_err()
+        {
+            return Err(RestoreError::Unknown);
+        }
+
+        match live(&state.dex, number).map(to_pokemon) {
+            Some(Ok(pokemon)) => Ok(pokemon),
+            _ => Err(RestoreError::Unknown),
+        }
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(RevertError::Unknown),
+        };
+
+        let number = u16::from(revision.number());
+        let row = |pokemon: Pokemon, deleted_at| Row {
+            number,
+            name: String::from(pokemon.name),
+            types: Vec::<String>::from(pokemon.types),
+            version: pokemon.version,
+            deleted_at,
+        };
+        let pokemon = match revision {
+            Revision::Absent(_) => None,
+            Revision::Live(pokemon) => Some(row(pokemon, None)),
+            Revision::Deleted(pokemon) => Some(row(pokemon, Some(Utc::now()))),
+        };
+
+        match self.append(
+            &mut file,
+            &mut state,
+            vec![Event::PokemonReverted { number, pokemon }],
+        ) {
+            Ok(()) => Ok(()),
+            _ => Err(RevertError::Unknown),
+        }
+    }
+
+    fn backend(&self) -> String {
+        String::from("events")
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        match self.open(LockMode::Shared) {
+            Ok(_) => Ok(()),
+            _ => Err(HealthError::Unavailable(String::from(
+                "the event log could not be read",
+            ))),
+        }
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        let purged = match state
+            .dex
+            .values()
+            .filter(|row| matches!(row.deleted_at, Some(deleted_at) if deleted_at < deleted_before))
+            .map(to_pokemon)
+            .collect::<Result<Vec<Pokemon>, ()>>()
+        {
+            Ok(purged) => purged,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        if purged.is_empty() {
+            return Ok(purged);
+        }
+
+        match self.append(
+            &mut file,
+            &mut state,
+            vec![Event::PokemonsPurged { deleted_before }],
+        ) {
+            Ok(()) => Ok(purged),
+            _ => Err(PurgeError::Unknown),
+        }
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let (mut file, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let mut numbers = vec![];
+        for pokemon in pokemons.iter() {
+            let number = u16::from(pokemon.number.clone());
+            if live(&state.dex, number).is_some() || numbers.contains(&number) {
+                return Err(InsertError::Conflict);
+            }
+            numbers.push(number);
+        }
+
+        let events = pokemons.into_iter().map(created).collect();
+        if self.append(&mut file, &mut state, events).is_err() {
+            return Err(InsertError::Unknown);
+        }
+
+        match numbers
+            .into_iter()
+            .map(|number| live(&state.dex, number).map(to_pokemon))
+            .collect::<Option<Result<Vec<Pokemon>, ()>>>()
+        {
+            Some(Ok(pokemons)) => Ok(pokemons),
+            _ => Err(InsertError::Unknown),
+        }
+    }
+}
+
+impl History for EventSourcedRepository {
+    fn fetch_all_as_of(&self, as_of: AsOf) -> Result<Vec<Pokemon>, FetchAllError> {
+        // Only locked to keep writers from appending while the log is read.
+        let (file, state) = match self.open(LockMode::Shared) {
+            Ok(guards) => guards,
+            _ => return Err(FetchAllError::Unknown),
+        };
+        drop(state);
+
+        let keep = |id: u64, at: DateTime<Utc>| match as_of {
+            AsOf::Time(time) => at <= time,
+            AsOf::Event(event_id) => id <= event_id,
+        };
+
+        let mut state = match self.snapshot(keep) {
+            Ok(Some(snapshot)) => State::from(snapshot),
+            Ok(None) => State {
+                dex: Dex::new(),
+                last_id: 0,
+                offset: 0,
+            },
+            _ => return Err(FetchAllError::Unknown),
+        };
+
+        let State {
+            dex,
+            last_id,
+            offset,
+        } = &mut state;
+        if replay(&file, *offset, dex, last_id, |r| keep(r.id, r.at)).is_err() {
+            return Err(FetchAllError::Unknown);
+        }
+
+        match to_pokemons(dex) {
+            Ok(pokemons) => Ok(pokemons),
+            _ => Err(FetchAllError::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+impl EventSourcedRepository {
+    pub fn temp() -> (Self, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pokedex.jsonl");
+        (Self::try_new(path.to_str().unwrap()).unwrap(), dir)
+    }
+
+    pub fn with_snapshot_every(self, snapshot_every: u64) -> Self {
+        Self {
+            snapshot_every,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn numbers(pokemons: Vec<Pokemon>) -> Vec<u16> {
+        pokemons.into_iter().map(|p| u16::from(p.number)).collect()
+    }
+
+    #[test]
+    fn it_should_append_an_event_per_change_and_rebuild_the_pokedex_by_replaying_them() {
+        let (repo, _dir) = EventSourcedRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.insert(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+
+        let log = fs::read_to_string(&repo.path).unwrap();
+        assert_eq!(
+            log.lines()
+                .map(
+                    |line| serde_json::from_str::<serde_json::Value>(line).unwrap()["type"]
+                        .as_str()
+                        .map(String::from)
+                        .unwrap()
+                )
+                .collect::<Vec<String>>(),
+            vec!["PokemonCreated", "PokemonCreated", "PokemonDeleted"]
+        );
+
+        let reopened = EventSourcedRepository::try_new(repo.path.to_str().unwrap()).unwrap();
+
+        match reopened.fetch_all() {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![4]),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_fetch_the_pokedex_as_of_an_event_or_a_time() {
+        let (repo, _dir) = EventSourcedRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        let before_charmander = Utc::now();
+        repo.insert(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+
+        match repo.fetch_all_as_of(AsOf::Event(0)) {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all_as_of(AsOf::Event(2)) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![4, 25]),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all_as_of(AsOf::Time(before_charmander)) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![25]),
+            _ => unreachable!(),
+        };
+        match repo.fetch_all_as_of(AsOf::Time(Utc::now())) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![4]),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_start_replaying_from_the_latest_snapshot() {
+        let (repo, _dir) = EventSourcedRepository::temp();
+        let repo = repo.with_snapshot_every(2);
+        for n in 1..=5 {
+            repo.insert(
+                PokemonNumber::try_from(n).unwrap(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        }
+
+        assert_eq!(
+            fs::read_to_string(&repo.snapshots_path)
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+
+        // Rewriting the replayed events proves that they are read from the snapshot instead.
+        let log = fs::read_to_string(&repo.path).unwrap();
+        fs::write(&repo.path, log.replace("\"number\":1,", "\"number\":7,")).unwrap();
+        let reopened = EventSourcedRepository::try_new(repo.path.to_str().unwrap()).unwrap();
+
+        match reopened.fetch_all() {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![1, 2, 3, 4, 5]),
+            _ => unreachable!(),
+        };
+        match reopened.fetch_all_as_of(AsOf::Event(3)) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![1, 2, 3]),
+            _ => unreachable!(),
+        };
+        match reopened.fetch_all_as_of(AsOf::Event(1)) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![7]),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_only_parse_the_snapshot_replayed_from_once_indexed() {
+        let (repo, _dir) = EventSourcedRepository::temp();
+        let repo = repo.with_snapshot_every(2);
+        let other = EventSourcedRepository::try_new(repo.path.to_str().unwrap())
+            .unwrap()
+            .with_snapshot_every(2);
+        for n in 1..=4 {
+            repo.insert(
+                PokemonNumber::try_from(n).unwrap(),
+                PokemonName::pikachu(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        }
+        repo.fetch_all_as_of(AsOf::Event(4)).ok();
+
+        // Corrupting the first snapshot, already indexed, goes unnoticed as long as it is not used.
+        let snapshots = fs::read_to_string(&repo.snapshots_path).unwrap();
+        fs::write(&repo.snapshots_path, snapshots.replacen('{', "x", 1)).unwrap();
+        for n in 5..=6 {
+            other
+                .insert(
+                    PokemonNumber::try_from(n).unwrap(),
+                    PokemonName::pikachu(),
+                    PokemonTypes::pikachu(),
+                )
+                .ok();
+        }
+
+        match repo.fetch_all_as_of(AsOf::Event(5)) {
+            Ok(pokemons) => assert_eq!(numbers(pokemons), vec![1, 2, 3, 4, 5]),
+            _ => unreachable!(),
+        };
+        match repo.snapshots.lock() {
+            Ok(index) => assert_eq!(index.entries.len(), 3),
+            _ => unreachable!(),
+        };
+        assert!(repo.fetch_all_as_of(AsOf::Event(3)).is_err());
+    }
+
+    #[test]
+    fn it_should_replay_the_events_appended_by_another_process() {
+        let (repo, _dir) = EventSourcedRepository::temp();
+        let other = EventSourcedRepository::try_new(repo.path.to_str().unwrap()).unwrap();
+        repo.fetch_all().ok();
+
+        other
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            )
+            .ok();
+
+        match repo.delete(PokemonNumber::charmander()) {
+            Ok(()) => {}
+            _ => unreachable!(),
+        };
+        match other.fetch_one(PokemonNumber::charmander()) {
+            Err(FetchOneError::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -1,18 +1,37 @@
-use super::{DeleteError, FetchAllError, FetchOneError, InsertError, Repository};
+use super::{
+    next_version, DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError,
+    Repository, RestoreError, RevertError, Revision,
+};
 use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
-use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use std::sync::{Mutex, MutexGuard};
 
 pub struct InMemoryRepository {
     error: bool,
-    pokemons: Mutex<Vec<Pokemon>>,
+    state: Mutex<State>,
+}
+
+struct State {
+    pokemons: Vec<Pokemon>,
+    deleted: Vec<(Pokemon, DateTime<Utc>)>,
 }
 
 impl InMemoryRepository {
     pub fn new() -> Self {
-        let pokemons: Mutex<Vec<Pokemon>> = Mutex::new(vec![]);
+        let state = Mutex::new(State {
+            pokemons: vec![],
+            deleted: vec![],
+        });
         Self {
             error: false,
-            pokemons,
+            state,
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, State>, ()> {
+        match self.state.lock() {
+            Ok(state) => Ok(state),
+            _ => Err(()),
         }
     }
 
@@ -36,17 +55,21 @@ impl Repository for InMemoryRepository {
             return Err(InsertError::Unknown);
         }
 
-        let mut lock = match self.pokemons.lock() {
+        let mut lock = match self.lock() {
             Ok(lock) => lock,
             _ => return Err(InsertError::Unknown),
         };
 
-        if lock.iter().any(|pokemon| pokemon.number == number) {
+        if lock.pokemons.iter().any(|pokemon| pokemon.number == number) {
             return Err(InsertError::Conflict);
         }
 
         let pokemon = Pokemon::new(number, name, types);
-        lock.push(pokemon.clone());
+        let pokemon = pokemon
+            .clone()
+            .with_version(next_version(&lock.deleted, &pokemon));
+        lock.deleted.retain(|(p, _)| p.number != pokemon.number);
+        lock.pokemons.push(pokemon.clone());
         Ok(pokemon)
     }
 
@@ -55,12 +78,12 @@ impl Repository for InMemoryRepository {
             return Err(FetchAllError::Unknown);
         }
 
-        let lock = match self.pokemons.lock() {
+        let lock = match self.lock() {
             Ok(lock) => lock,
             _ => return Err(FetchAllError::Unknown),
         };
 
-        let mut pokemons = lock.to_vec();
+        let mut pokemons = lock.pokemons.to_vec();
         pokemons.sort_by(|a, b| a.number.cmp(&b.number));
         Ok(pokemons)
     }
@@ -70,33 +93,166 @@ impl Repository for InMemoryRepository {
             return Err(FetchOneError::Unknown);
         }
 
-        let lock = match self.pokemons.lock() {
+        let lock = match self.lock() {
             Ok(lock) => lock,
             _ => return Err(FetchOneError::Unknown),
         };
 
-        match lock.iter().find(|p| p.number == number) {
+        match lock.pokemons.iter().find(|p| p.number == number) {
             Some(pokemon) => Ok(pokemon.clone()),
             None => Err(FetchOneError::NotFound),
         }
     }
 
-    fn delete(&self, number: PokemonNumber) -> Result<(), DeleteError> {
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
         if self.error {
             return Err(DeleteError::Unknown);
         }
 
-        let mut lock = match self.pokemons.lock() {
+        let mut lock = match self.lock() {
             Ok(lock) => lock,
             _ => return Err(DeleteError::Unknown),
         };
 
-        let index = match lock.iter().position(|p| p.number == number) {
+        let index = match lock.pokemons.iter().position(|p| p.number == number) {
             Some(index) => index,
             None => return Err(DeleteError::NotFound),
         };
 
-        lock.remove(index);
+        if versions.is_some_and(|versions| !versions.contains(&lock.pokemons[index].version)) {
+            return Err(DeleteError::VersionMismatch);
+        }
+
+        let pokemon = lock.pokemons.remove(index);
+        lock.deleted.push((pokemon, Utc::now()));
         Ok(())
     }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        if self.error {
+            return Err(RestoreError::Unknown);
+        }
+
+        let mut lock = match self.lock() {
+            Ok(lock) => lock,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        let index = match lock.deleted.iter().position(|(p, _)| p.number == number) {
+            Some(index) => index,
+            None => return Err(RestoreError::NotFound),
+        };
+
+        if versions.is_some_and(|versions| !versions.contains(&lock.deleted[index].0.version)) {
+            return Err(RestoreError::VersionMismatch);
+        }
+
+        let (pokemon, _) = lock.deleted.remove(index);
+        let pokemon = Pokemon {
+            version: pokemon.version + 1,
+            ..pokemon
+        };
+        lock.pokemons.push(pokemon.clone());
+        Ok(pokemon)
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        if self.error {
+            return Err(RevertError::Unknown);
+        }
+
+        let mut lock = match self.lock() {
+            Ok(lock) => lock,
+            _ => return Err(RevertError::Unknown),
+        };
+
+        let number = revision.number();
+        lock.pokemons.retain(|p| p.number != number);
+        lock.deleted.retain(|(p, _)| p.number != number);
+        match revision {
+            Revision::Absent(_) => {}
+            Revision::Live(pokemon) => lock.pokemons.push(pokemon),
+            Revision::Deleted(pokemon) => lock.deleted.push((pokemon, Utc::now())),
+        };
+        Ok(())
+    }
+
+    fn backend(&self) -> String {
+        String::from("memory")
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        if self.error {
+            return Err(HealthError::Unavailable(String::from(
+                "the repository is failing",
+            )));
+        }
+
+        match self.lock() {
+            Ok(_) => Ok(()),
+            _ => Err(HealthError::Unavailable(String::from(
+                "the repository is poisoned",
+            ))),
+        }
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        if self.error {
+            return Err(PurgeError::Unknown);
+        }
+
+        let mut lock = match self.lock() {
+            Ok(lock) => lock,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        let (kept, purged): (Vec<_>, Vec<_>) = lock
+            .deleted
+            .drain(..)
+            .partition(|(_, deleted_at)| *deleted_at >= deleted_before);
+        lock.deleted = kept;
+        Ok(purged.into_iter().map(|(pokemon, _)| pokemon).collect())
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        if self.error {
+            return Err(InsertError::Unknown);
+        }
+
+        let mut lock = match self.lock() {
+            Ok(lock) => lock,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        for (index, pokemon) in pokemons.iter().enumerate() {
+            if lock
+                .pokemons
+                .iter()
+                .chain(&pokemons[..index])
+                .any(|p| p.number == pokemon.number)
+            {
+                return Err(InsertError::Conflict);
+            }
+        }
+
+        let pokemons = pokemons
+            .into_iter()
+            .map(|pokemon| {
+                let version = next_version(&lock.deleted, &pokemon);
+                pokemon.with_version(version)
+            })
+            .collect::<Vec<Pokemon>>();
+        lock.deleted
+            .retain(|(p, _)| !pokemons.iter().any(|pokemon| pokemon.number == p.number));
+        lock.pokemons.extend(pokemons.iter().cloned());
+        Ok(pokemons)
+    }
 }
@@ -0,0 +1,267 @@
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use crate::logging::{self, Logger};
+use crate::metrics::Metrics;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Measures every call to a backend, logging a span with how long it took and how it ended, and
+/// recording both in the metrics, so that the time spent in each backend can be told apart.
+pub struct InstrumentedRepository {
+    inner: Arc<dyn Repository>,
+    backend: &'static str,
+    logger: Option<Arc<Logger>>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl InstrumentedRepository {
+    pub fn new(inner: Arc<dyn Repository>, backend: &'static str) -> Self {
+        Self {
+            inner,
+            backend,
+            logger: None,
+            metrics: None,
+        }
+    }
+
+    pub fn with_logger(self, logger: Arc<Logger>) -> Self {
+        Self {
+            logger: Some(logger),
+            ..self
+        }
+    }
+
+    pub fn with_metrics(self, metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    fn span<T, E: Outcome>(
+        &self,
+        operation: &'static str,
+        call: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let started_at = Instant::now();
+        let result = call();
+        let duration = started_at.elapsed();
+        let outcome = match &result {
+            Ok(_) => "ok",
+            Err(error) => error.outcome(),
+        };
+
+        if let Some(logger) = &self.logger {
+            logger.log(
+                if outcome == "error" { "warn" } else { "info" },
+                "span",
+                vec![
+                    ("kind", Value::from("repository")),
+                    ("backend", Value::from(self.backend)),
+                    ("name", Value::from(operation)),
+                    ("outcome", Value::from(outcome)),
+                    ("duration_ms", logging::millis(duration)),
+                ],
+            );
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_operation(self.backend, operation, outcome == "error", duration);
+        }
+
+        result
+    }
+}
+
+/// Tells the failures of a backend apart from the answers it gives, such as a missing Pokemon,
+/// only the former being counted as errors.
+trait Outcome {
+    fn outcome(&self) -> &'static str;
+}
+
+impl Outcome for InsertError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            InsertError::Conflict => "conflict",
+            InsertError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for FetchAllError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            FetchAllError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for FetchOneError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            FetchOneError::NotFound => "not_found",
+            FetchOneError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for DeleteError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            DeleteError::NotFound => "not_found",
+            DeleteError::VersionMismatch => "version_mismatch",
+            DeleteError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for RestoreError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            RestoreError::NotFound => "not_found",
+            RestoreError::VersionMismatch => "version_mismatch",
+            RestoreError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for HealthError {
+    fn outcome(&self) -> &'static str {
+        "error"
+    }
+}
+
+impl Outcome for PurgeError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            PurgeError::Unknown => "error",
+        }
+    }
+}
+
+impl Outcome for RevertError {
+    fn outcome(&self) -> &'static str {
+        match self {
+            RevertError::Unknown => "error",
+        }
+    }
+}
+
+impl Repository for InstrumentedRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        self.span("insert", || self.inner.insert(number, name, types))
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        self.span("fetch_all", || self.inner.fetch_all())
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        self.span("fetch_one", || self.inner.fetch_one(number))
+    }
+
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        self.span("delete", || self.inner.conditional_delete(number, versions))
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        self.span("restore", || {
+            self.inner.conditional_restore(number, versions)
+        })
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        self.span("revert", || self.inner.revert(revision))
+    }
+
+    fn backend(&self) -> String {
+        self.inner.backend()
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        self.span("check_health", || self.inner.check_health())
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        self.span("purge", || self.inner.purge(deleted_before))
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        self.span("insert_batch", || self.inner.insert_batch(pokemons))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{tests::Lines, Format};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+
+    #[test]
+    fn it_should_log_and_measure_a_span_per_call_with_its_backend_and_outcome() {
+        let lines = Lines::default();
+        let metrics = Arc::new(Metrics::new());
+        let repo = InstrumentedRepository::new(Arc::new(InMemoryRepository::new()), "memory")
+            .with_logger(Arc::new(Logger::with_sink(
+                Format::Json,
+                Box::new(lines.clone()),
+            )))
+            .with_metrics(metrics.clone());
+
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.fetch_one(PokemonNumber::charmander()).ok();
+
+        let spans = lines
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<Value>>();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0]["backend"], "memory");
+        assert_eq!(spans[0]["name"], "insert");
+        assert_eq!(spans[0]["outcome"], "ok");
+        assert!(spans[0]["duration_ms"].is_f64());
+        assert_eq!(spans[1]["name"], "fetch_one");
+        assert_eq!(spans[1]["outcome"], "not_found");
+        assert!(!metrics
+            .render(None)
+            .contains("pokedex_repository_errors_total{"));
+    }
+
+    #[test]
+    fn it_should_only_count_the_failures_of_the_backend_as_errors() {
+        let metrics = Arc::new(Metrics::new());
+        let repo =
+            InstrumentedRepository::new(Arc::new(InMemoryRepository::new().with_error()), "memory")
+                .with_metrics(metrics.clone());
+
+        repo.fetch_one(PokemonNumber::pikachu()).ok();
+
+        assert!(metrics.render(None).contains(
+            r#"pokedex_repository_errors_total{backend="memory",operation="fetch_one"} 1"#
+        ));
+    }
+}
@@ -0,0 +1,585 @@
+use super::{
+    next_version, DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError,
+    Repository, RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Stores the whole Pokedex in a single JSON file.
+///
+/// Writes go to a temporary file which is then renamed over the previous version, so readers
+/// never see a partially written file. A companion `.lock` file is locked for the duration of
+/// every operation to coordinate with other processes using the same file, and the file is read
+/// again whenever it was modified since it was last loaded. Deleted Pokemons stay in the file with
+/// a `deleted_at` timestamp until they are purged.
+pub struct JsonFileRepository {
+    path: PathBuf,
+    lock_path: PathBuf,
+    tmp_path: PathBuf,
+    state: Mutex<State>,
+}
+
+struct State {
+    pokemons: Vec<Pokemon>,
+    deleted: Vec<(Pokemon, DateTime<Utc>)>,
+    stamp: Option<Stamp>,
+}
+
+#[derive(PartialEq)]
+struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PokemonJson {
+    number: u16,
+    name: String,
+    types: Vec<String>,
+    /// Files written before Pokemons were versioned hold their first version.
+    #[serde(default = "first_version")]
+    version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+fn first_version() -> u64 {
+    1
+}
+
+enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+impl JsonFileRepository {
+    pub fn try_new(path: &str) -> Result<Self, ()> {
+        let path = PathBuf::from(path);
+        let repo = Self {
+            lock_path: with_suffix(&path, ".lock"),
+            tmp_path: with_suffix(&path, ".tmp"),
+            path,
+            state: Mutex::new(State {
+                pokemons: vec![],
+                deleted: vec![],
+                stamp: None,
+            }),
+        };
+
+        let lock = match repo.lock(LockMode::Exclusive) {
+            Ok(lock) => lock,
+            _ => return Err(()),
+        };
+
+        let mut state = match repo.state.lock() {
+            Ok(state) => state,
+            _ => return Err(()),
+        };
+
+        if !repo.path.exists() && repo.write(&mut state, vec![], vec![]).is_err() {
+            return Err(());
+        }
+
+        if repo.reload(&mut state).is_err() {
+            return Err(());
+        }
+
+        drop(state);
+        drop(lock);
+        Ok(repo)
+    }
+
+    /// Locks the companion lock file, which stays locked until the returned file is dropped.
+    fn lock(&self, mode: LockMode) -> Result<File, ()> {
+        let file = match OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+        {
+            Ok(file) => file,
+            _ => return Err(()),
+        };
+
+        let res = match mode {
+            LockMode::Shared => file.lock_shared(),
+            LockMode::Exclusive => file.lock(),
+        };
+
+        match res {
+            Ok(()) => Ok(file),
+            _ => Err(()),
+        }
+    }
+
+    /// Takes both the inter-process lock and the in-process one, reloading the file if another
+    /// process changed it since it was last read.
+    fn open(&self, mode: LockMode) -> Result<(File, MutexGuard<'_, State>), ()> {
+        let lock = self.lock(mode)?;
+
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            _ => return Err(()),
+        };
+
+        if stamp(&self.path)? != state.stamp {
+            self.reload(&mut state)?;
+        }
+
+        Ok((lock, state))
+    }
+
+    fn reload(&self, state: &mut State) -> Result<(), ()> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            _ => return Err(()),
+        };
+
+        let rows = match serde_json::from_reader::<_, Vec<PokemonJson>>(BufReader::new(file)) {
+            Ok(rows) => rows,
+            _ => return Err(()),
+        };
+
+        let mut pokemons = vec![];
+        let mut deleted = vec![];
+
+        for row in rows {
+            let pokemon = match (
+                PokemonNumber::try_from(row.number),
+                PokemonName::try_from(row.name),
+                PokemonTypes::try_from(row.types),
+            ) {
+                (Ok(number), Ok(name), Ok(types)) => {
+                    Pokemon::new(number, name, types).with_version(row.version)
+                }
+                _ => return Err(()),
+            };
+
+            match row.deleted_at {
+                Some(deleted_at) => deleted.push((pokemon, deleted_at)),
+                None => pokemons.push(pokemon),
+            }
+        }
+
+        state.pokemons = pokemons;
+        state.deleted = deleted;
+        state.stamp = stamp(&self.path)?;
+        Ok(())
+    }
+
+    fn write(
+        &self,
+        state: &mut State,
+        pokemons: Vec<Pokemon>,
+        deleted: Vec<(Pokemon, DateTime<Utc>)>,
+    ) -> Result<(), ()> {
+        let rows = pokemons
+            .iter()
+            .cloned()
+            .map(|p| (p, None))
+            .chain(deleted.iter().cloned().map(|(p, at)| (p, Some(at))))
+            .map(|(p, deleted_at)| PokemonJson {
+                number: u16::from(p.number),
+                name: String::from(p.name),
+                types: Vec::<String>::from(p.types),
+                version: p.version,
+                deleted_at,
+            })
+            .collect::<Vec<PokemonJson>>();
+
+        let file = match File::create(&self.tmp_path) {
+            Ok(file) => file,
+            _ => return Err(()),
+        };
+
+        let mut writer = BufWriter::new(file);
+        if serde_json::to_writer_pretty(&mut writer, &rows).is_err() {
+            return Err(());
+        }
+
+        match writer.into_inner() {
+            Ok(mut file) => {
+                if file.flush().is_err() || file.sync_all().is_err() {
+                    return Err(());
+                }
+            }
+            _ => return Err(()),
+        };
+
+        if fs::rename(&self.tmp_path, &self.path).is_err() {
+            return Err(());
+        }
+
+        state.pokemons = pokemons;
+        state.deleted = deleted;
+        state.stamp = stamp(&self.path)?;
+        Ok(())
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn stamp(path: &Path) -> Result<Option<Stamp>, ()> {
+    match fs::metadata(path) {
+        Ok(metadata) => match metadata.modified() {
+            Ok(modified) => Ok(Some(Stamp {
+                modified,
+                len: metadata.len(),
+            })),
+            _ => Err(()),
+        },
+        _ => Err(()),
+    }
+}
+
+impl Repository for JsonFileRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        if state
+            .pokemons
+            .iter()
+            .any(|pokemon| pokemon.number == number)
+        {
+            return Err(InsertError::Conflict);
+        }
+
+        let pokemon = Pokemon::new(number, name, types);
+        let pokemon = pokemon
+            .clone()
+            .with_version(next_version(&state.deleted, &pokemon));
+        let mut pokemons = state.pokemons.clone();
+        pokemons.push(pokemon.clone());
+        let mut deleted = state.deleted.clone();
+        deleted.retain(|(p, _)| p.number != pokemon.number);
+
+        match self.write(&mut state, pokemons, deleted) {
+            Ok(()) => Ok(pokemon),
+            _ => Err(InsertError::Unknown),
+        }
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        let (_lock, state) = match self.open(LockMode::Shared) {
+            Ok(guards) => guards,
+            _ => return Err(FetchAllError::Unknown),
+        };
+
+        let mut pokemons = state.pokemons.to_vec();
+        pokemons.sort_by(|a, b| a.number.cmp(&b.number));
+        Ok(pokemons)
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        let (_lock, state) = match self.open(LockMode::Shared) {
+            Ok(guards) => guards,
+            _ => return Err(FetchOneError::Unknown),
+        };
+
+        match state.pokemons.iter().find(|p| p.number == number) {
+            Some(pokemon) => Ok(pokemon.clone()),
+            None => Err(FetchOneError::NotFound),
+        }
+    }
+
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(DeleteError::Unknown),
+        };
+
+        let mut pokemons = state.pokemons.clone();
+        let index = match pokemons.iter().position(|p| p.number == number) {
+            Some(index) => index,
+            None => return Err(DeleteError::NotFound),
+        };
+
+        if versions.is_some_and(|versions| !versions.contains(&pokemons[index].version)) {
+            return Err(DeleteError::VersionMismatch);
+        }
+
+        let mut deleted = state.deleted.clone();
+        deleted.push((pokemons.remove(index), Utc::now()));
+
+        match self.write(&mut state, pokemons, deleted) {
+            Ok(()) => Ok(()),
+            _ => Err(DeleteError::Unknown),
+        }
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        let mut deleted = state.deleted.clone();
+        let index = match deleted.iter().position(|(p, _)| p.number == number) {
+            Some(index) => index,
+            None => return Err(RestoreError::NotFound),
+        };
+
+        if versions.is_some_and(|versions| !versions.contains(&deleted[index].0.version)) {
+            return Err(RestoreError::VersionMismatch);
+        }
+
+        let (pokemon, _) = deleted.remove(index);
+        let pokemon = Pokemon {
+            version: pokemon.version + 1,
+            ..pokemon
+        };
+        let mut pokemons = state.pokemons.clone();
+        pokemons.push(pokemon.clone());
+
+        match self.write(&mut state, pokemons, deleted) {
+            Ok(()) => Ok(pokemon),
+            _ => Err(RestoreError::Unknown),
+        }
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(RevertError::Unknown),
+        };
+
+        let number = revision.number();
+        let mut pokemons = state.pokemons.clone();
+        pokemons.retain(|p| p.number != number);
+        let mut deleted = state.deleted.clone();
+        deleted.retain(|(p, _)| p.number != number);
+        match revision {
+            Revision::Absent(_) => {}
+            Revision::Live(pokemon) => pokemons.push(pokemon),
+            Revision::Deleted(pokemon) => deleted.push((pokemon, Utc::now())),
+        };
+
+        match self.write(&mut state, pokemons, deleted) {
+            Ok(()) => Ok(()),
+            _ => Err(RevertError::Unknown),
+        }
+    }
+
+    fn backend(&self) -> String {
+        String::from("json")
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        match self.open(LockMode::Shared) {
+            Ok(_) => Ok(()),
+            _ => Err(HealthError::Unavailable(String::from(
+                "the file could not be read",
+            ))),
+        }
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        let (kept, purged): (Vec<_>, Vec<_>) = state
+            .deleted
+            .iter()
+            .cloned()
+            .partition(|(_, deleted_at)| *deleted_at >= deleted_before);
+
+        if purged.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let pokemons = state.pokemons.clone();
+        match self.write(&mut state, pokemons, kept) {
+            Ok(()) => Ok(purged.into_iter().map(|(pokemon, _)| pokemon).collect()),
+            _ => Err(PurgeError::Unknown),
+        }
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let (_lock, mut state) = match self.open(LockMode::Exclusive) {
+            Ok(guards) => guards,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let mut all = state.pokemons.clone();
+        let mut inserted = vec![];
+
+        for pokemon in pokemons {
+            if all.iter().any(|p| p.number == pokemon.number) {
+                return Err(InsertError::Conflict);
+            }
+            let version = next_version(&state.deleted, &pokemon);
+            all.push(pokemon.clone().with_version(version));
+            inserted.push(pokemon.with_version(version));
+        }
+
+        let mut deleted = state.deleted.clone();
+        deleted.retain(|(p, _)| !inserted.iter().any(|pokemon| pokemon.number == p.number));
+
+        match self.write(&mut state, all, deleted) {
+            Ok(()) => Ok(inserted),
+            _ => Err(InsertError::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+impl JsonFileRepository {
+    pub fn temp() -> (Self, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pokedex.json");
+        (Self::try_new(path.to_str().unwrap()).unwrap(), dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_create_an_empty_pokedex_when_the_file_does_not_exist() {
+        let (repo, _dir) = JsonFileRepository::temp();
+
+        assert_eq!(fs::read_to_string(&repo.path).unwrap().trim(), "[]");
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_fail_when_the_file_is_not_a_pokedex() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pokedex.json");
+        fs::write(&path, "{ \"not\": \"a pokedex\" }").unwrap();
+
+        let res = JsonFileRepository::try_new(path.to_str().unwrap());
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn it_should_persist_the_pokemons_across_instances() {
+        let (repo, _dir) = JsonFileRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+
+        let reopened = JsonFileRepository::try_new(repo.path.to_str().unwrap()).unwrap();
+
+        match reopened.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Pikachu"),
+            _ => unreachable!(),
+        };
+        assert!(!repo.tmp_path.exists());
+    }
+
+    #[test]
+    fn it_should_return_a_conflict_error_when_pokemon_number_already_exists() {
+        let (repo, _dir) = JsonFileRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+
+        let res = repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+
+        match res {
+            Err(InsertError::Conflict) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_reload_the_file_when_another_process_changed_it() {
+        let (repo, _dir) = JsonFileRepository::temp();
+        let other = JsonFileRepository::try_new(repo.path.to_str().unwrap()).unwrap();
+        repo.fetch_all().ok();
+
+        other
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            )
+            .ok();
+
+        match repo.fetch_one(PokemonNumber::charmander()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Charmander"),
+            _ => unreachable!(),
+        };
+        match repo.delete(PokemonNumber::charmander()) {
+            Ok(()) => {}
+            _ => unreachable!(),
+        };
+        match other.fetch_one(PokemonNumber::charmander()) {
+            Err(FetchOneError::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_not_lose_writes_from_concurrent_instances() {
+        let (repo, _dir) = JsonFileRepository::temp();
+        let path = String::from(repo.path.to_str().unwrap());
+
+        std::thread::scope(|scope| {
+            for n in 1..=16 {
+                let path = path.clone();
+                scope.spawn(move || {
+                    JsonFileRepository::try_new(&path)
+                        .unwrap()
+                        .insert(
+                            PokemonNumber::try_from(n).unwrap(),
+                            PokemonName::pikachu(),
+                            PokemonTypes::pikachu(),
+                        )
+                        .ok()
+                        .unwrap();
+                });
+            }
+        });
+
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 16),
+            _ => unreachable!(),
+        };
+    }
+}
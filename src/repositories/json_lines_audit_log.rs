@@ -0,0 +1,139 @@
+use super::audit_log::{AuditEvent, AuditLog};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// Appends every event as a line of JSON, a format which is easy to ship to log collectors.
+///
+/// The file is locked while an event is appended so that processes sharing it never interleave
+/// their lines.
+pub struct JsonLinesAuditLog {
+    path: PathBuf,
+}
+
+impl JsonLinesAuditLog {
+    pub fn try_new(path: &str) -> Result<Self, ()> {
+        let log = Self {
+            path: PathBuf::from(path),
+        };
+
+        match log.open() {
+            Ok(_) => Ok(log),
+            _ => Err(()),
+        }
+    }
+
+    fn open(&self) -> Result<File, ()> {
+        match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&self.path)
+        {
+            Ok(file) => Ok(file),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AuditLog for JsonLinesAuditLog {
+    fn record(&self, event: &AuditEvent) -> Result<(), ()> {
+        let mut line = match serde_json::to_vec(event) {
+            Ok(line) => line,
+            _ => return Err(()),
+        };
+        line.push(b'\n');
+
+        let mut file = self.open()?;
+
+        if file.lock().is_err() {
+            return Err(());
+        }
+
+        match file.write_all(&line).and_then(|_| file.sync_data()) {
+            Ok(()) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    fn events(&self, number: Option<u16>) -> Result<Vec<AuditEvent>, ()> {
+        let file = self.open()?;
+
+        if file.lock_shared().is_err() {
+            return Err(());
+        }
+
+        let mut events = vec![];
+
+        for line in BufReader::new(file).lines() {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => line,
+                _ => return Err(()),
+            };
+
+            match serde_json::from_str::<AuditEvent>(&line) {
+                Ok(event) if number.is_none() || Some(event.number) == number => events.push(event),
+                Ok(_) => {}
+                _ => return Err(()),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+impl JsonLinesAuditLog {
+    pub fn temp() -> (Self, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        (Self::try_new(path.to_str().unwrap()).unwrap(), dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::audit_log::{Operation, Snapshot, Transport};
+    use chrono::Utc;
+    use std::fs;
+
+    fn event(number: u16) -> AuditEvent {
+        AuditEvent {
+            at: Utc::now(),
+            actor: String::from("ash"),
+            transport: Transport::Cli,
+            operation: Operation::Insert,
+            number,
+            before: None,
+            after: Some(Snapshot {
+                number,
+                name: String::from("Pikachu"),
+                types: vec![String::from("Electric")],
+            }),
+        }
+    }
+
+    #[test]
+    fn it_should_append_one_line_per_event_and_read_them_back() {
+        let (log, _dir) = JsonLinesAuditLog::temp();
+
+        log.record(&event(25)).unwrap();
+        log.record(&event(4)).unwrap();
+
+        assert_eq!(fs::read_to_string(&log.path).unwrap().lines().count(), 2);
+        match log.events(Some(25)) {
+            Ok(events) => {
+                assert_eq!(events.len(), 1);
+                assert_eq!(events[0].actor, "ash");
+                assert_eq!(events[0].after.as_ref().map(|p| p.number), Some(25));
+            }
+            _ => unreachable!(),
+        };
+        match log.events(None) {
+            Ok(events) => assert_eq!(events.len(), 2),
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,573 @@
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
+use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use crate::logging::{self, Logger};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// What to do when a write succeeded on the primary but not on the secondary.
+#[derive(Clone, Copy)]
+pub enum SecondaryFailurePolicy {
+    /// Undo the write on the primary and report the failure.
+    Fail,
+    /// Log the failure and leave both stores diverging.
+    LogAndContinue,
+    /// Keep the write in memory and replay it on the secondary before the next write, or when
+    /// closed.
+    Queue,
+}
+
+impl TryFrom<&str> for SecondaryFailurePolicy {
+    type Error = ();
+
+    fn try_from(policy: &str) -> Result<Self, Self::Error> {
+        match policy {
+            "fail" => Ok(Self::Fail),
+            "log" => Ok(Self::LogAndContinue),
+            "queue" => Ok(Self::Queue),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Reads from a primary repository and applies every write to both the primary and a secondary.
+pub struct MirroredRepository {
+    primary: Arc<dyn Repository>,
+    secondary: Arc<dyn Repository>,
+    policy: SecondaryFailurePolicy,
+    pending: Mutex<VecDeque<Write>>,
+    logger: Option<Arc<Logger>>,
+}
+
+#[derive(Clone)]
+enum Write {
+    Insert(Pokemon),
+    Delete(PokemonNumber),
+    Restore(Pokemon),
+    Purge(DateTime<Utc>),
+}
+
+impl Write {
+    fn operation(&self) -> &'static str {
+        match self {
+            Write::Insert(_) => "insert",
+            Write::Delete(_) => "delete",
+            Write::Restore(_) => "restore",
+            Write::Purge(_) => "purge",
+        }
+    }
+}
+
+impl MirroredRepository {
+    pub fn new(
+        primary: Arc<dyn Repository>,
+        secondary: Arc<dyn Repository>,
+        policy: SecondaryFailurePolicy,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            policy,
+            pending: Mutex::new(VecDeque::new()),
+            logger: None,
+        }
+    }
+
+    pub fn with_logger(self, logger: Option<Arc<Logger>>) -> Self {
+        Self { logger, ..self }
+    }
+
+    #[cfg(test)]
+    pub fn pending(&self) -> usize {
+        self.lock_pending().len()
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, VecDeque<Write>> {
+        match self.pending.lock() {
+            Ok(pending) => pending,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Replays the writes still queued for the secondary a last time, since they are only kept in
+    /// memory, logging those which could not be as lost.
+    pub fn close(&self) {
+        let mut pending = self.lock_pending();
+        self.replay(&mut pending);
+
+        if !pending.is_empty() {
+            logging::report(
+                self.logger.as_deref(),
+                "error",
+                "Lost the writes queued for the secondary repository",
+                vec![("pending", Value::from(pending.len()))],
+            );
+        }
+        if let Some(logger) = &self.logger {
+            logger.flush();
+        }
+    }
+
+    /// Replays the queued writes in order, stopping at the first one which fails again.
+    fn replay(&self, pending: &mut VecDeque<Write>) {
+        while let Some(queued) = pending.front() {
+            match self.mirror(queued) {
+                Ok(()) => {
+                    pending.pop_front();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Applies a write to the secondary. Replays are expected, so finding the write already
+    /// applied counts as a success.
+    fn mirror(&self, write: &Write) -> Result<(), ()> {
+        match write {
+            Write::Insert(pokemon) => match self.secondary.insert(
+                pokemon.number.clone(),
+                pokemon.name.clone(),
+                pokemon.types.clone(),
+            ) {
+                Ok(_) => Ok(()),
+                Err(InsertError::Conflict) => {
+                    match self.secondary.fetch_one(pokemon.number.clone()) {
+                        Ok(mirrored) if mirrored == *pokemon => Ok(()),
+                        _ => Err(()),
+                    }
+                }
+                Err(InsertError::Unknown) => Err(()),
+            },
+            Write::Delete(number) => match self.secondary.delete(number.clone()) {
+                Ok(()) | Err(DeleteError::NotFound) => Ok(()),
+                Err(DeleteError::VersionMismatch) | Err(DeleteError::Unknown) => Err(()),
+            },
+            Write::Restore(pokemon) => match self.secondary.restore(pokemon.number.clone()) {
+                Ok(_) => Ok(()),
+                Err(RestoreError::NotFound) => {
+                    match self.secondary.fetch_one(pokemon.number.clone()) {
+                        Ok(mirrored) if mirrored == *pokemon => Ok(()),
+                        _ => Err(()),
+                    }
+                }
+                Err(RestoreError::VersionMismatch) | Err(RestoreError::Unknown) => Err(()),
+            },
+            Write::Purge(deleted_before) => match self.secondary.purge(*deleted_before) {
+                Ok(_) => Ok(()),
+                Err(PurgeError::Unknown) => Err(()),
+            },
+        }
+    }
+
+    /// Mirrors a write which was applied on the primary, failing when the policy requires the
+    /// write to be rolled back on the primary.
+    fn mirror_or_apply_policy(&self, write: Write) -> Result<(), ()> {
+        let mut pending = self.lock_pending();
+
+        // Replay what is left from earlier failures first, so that writes reach the secondary in
+        // the order they were applied on the primary.
+        self.replay(&mut pending);
+
+        if pending.is_empty() && self.mirror(&write).is_ok() {
+            return Ok(());
+        }
+
+        match self.policy {
+            SecondaryFailurePolicy::Fail => Err(()),
+            SecondaryFailurePolicy::LogAndContinue => {
+                logging::report(
+                    self.logger.as_deref(),
+                    "error",
+                    "Failed to mirror a write to the secondary repository",
+                    vec![("operation", Value::from(write.operation()))],
+                );
+                Ok(())
+            }
+            SecondaryFailurePolicy::Queue => {
+                pending.push_back(write);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Repository for MirroredRepository {
+    fn insert(
+        &self,
+        number: PokemonNumber,
+        name: PokemonName,
+        types: PokemonTypes,
+    ) -> Result<Pokemon, InsertError> {
+        let pokemon = self.primary.insert(number, name, types)?;
+
+        match self.mirror_or_apply_policy(Write::Insert(pokemon.clone())) {
+            Ok(()) => Ok(pokemon),
+            _ => {
+                self.primary.revert(Revision::Absent(pokemon.number)).ok();
+                Err(InsertError::Unknown)
+            }
+        }
+    }
+
+    fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+        self.primary.fetch_all()
+    }
+
+    fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+        self.primary.fetch_one(number)
+    }
+
+    /// Only the primary is checked against `versions`, since the secondary keeps its own versions.
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let pokemon = match self.primary.fetch_one(number.clone()) {
+            Ok(pokemon) => pokemon,
+            Err(FetchOneError::NotFound) => return Err(DeleteError::NotFound),
+            Err(FetchOneError::Unknown) => return Err(DeleteError::Unknown),
+        };
+
+        self.primary.conditional_delete(number.clone(), versions)?;
+
+        match self.mirror_or_apply_policy(Write::Delete(number)) {
+            Ok(()) => Ok(()),
+            _ => {
+                self.primary.revert(Revision::Live(pokemon)).ok();
+                Err(DeleteError::Unknown)
+            }
+        }
+    }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let pokemon = self.primary.conditional_restore(number, versions)?;
+
+        match self.mirror_or_apply_policy(Write::Restore(pokemon.clone())) {
+            Ok(()) => Ok(pokemon),
+            _ => {
+                let deleted = Pokemon {
+                    version: pokemon.version - 1,
+                    ..pokemon
+                };
+                self.primary.revert(Revision::Deleted(deleted)).ok();
+                Err(RestoreError::Unknown)
+            }
+        }
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        self.primary.revert(revision.clone())?;
+        self.secondary.revert(revision)
+    }
+
+    fn backend(&self) -> String {
+        format!(
+            "{} mirrored to {}",
+            self.primary.backend(),
+            self.secondary.backend()
+        )
+    }
+
+    /// The secondary only matters when failing to write to it fails the write.
+    fn check_health(&self) -> Result<(), HealthError> {
+        self.primary.check_health()?;
+
+        match self.policy {
+            SecondaryFailurePolicy::Fail => self.secondary.check_health(),
+            SecondaryFailurePolicy::LogAndContinue | SecondaryFailurePolicy::Queue => Ok(()),
+        }
+    }
+
+    /// Purging cannot be undone, so the `Fail` policy only reports that the secondary kept its
+    /// tombstones.
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let purged = self.primary.purge(deleted_before)?;
+
+        match self.mirror_or_apply_policy(Write::Purge(deleted_before)) {
+            Ok(()) => Ok(purged),
+            _ => Err(PurgeError::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{tests::Lines, Format};
+    use crate::repositories::in_memory_repository::InMemoryRepository;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Secondary which can be taken offline and brought back.
+    struct Flaky {
+        inner: InMemoryRepository,
+        offline: AtomicBool,
+    }
+
+    impl Flaky {
+        fn new() -> Self {
+            Self {
+                inner: InMemoryRepository::new(),
+                offline: AtomicBool::new(false),
+            }
+        }
+
+        fn set_offline(&self, offline: bool) {
+            self.offline.store(offline, Ordering::SeqCst);
+        }
+
+        fn is_offline(&self) -> bool {
+            self.offline.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Repository for Flaky {
+        fn insert(
+            &self,
+            number: PokemonNumber,
+            name: PokemonName,
+            types: PokemonTypes,
+        ) -> Result<Pokemon, InsertError> {
+            if self.is_offline() {
+                return Err(InsertError::Unknown);
+            }
+            self.inner.insert(number, name, types)
+        }
+
+        fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
+            if self.is_offline() {
+                return Err(FetchAllError::Unknown);
+            }
+            self.inner.fetch_all()
+        }
+
+        fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
+            if self.is_offline() {
+                return Err(FetchOneError::Unknown);
+            }
+            self.inner.fetch_one(number)
+        }
+
+        fn conditional_delete(
+            &self,
+            number: PokemonNumber,
+            versions: Option<&[u64]>,
+        ) -> Result<(), DeleteError> {
+            if self.is_offline() {
+                return Err(DeleteError::Unknown);
+            }
+            self.inner.conditional_delete(number, versions)
+        }
+
+        fn conditional_restore(
+            &self,
+            number: PokemonNumber,
+            versions: Option<&[u64]>,
+        ) -> Result<Pokemon, RestoreError> {
+            if self.is_offline() {
+                return Err(RestoreError::Unknown);
+            }
+            self.inner.conditional_restore(number, versions)
+        }
+
+        fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+            if self.is_offline() {
+                return Err(RevertError::Unknown);
+            }
+            self.inner.revert(revision)
+        }
+
+        fn backend(&self) -> String {
+            String::from("flaky")
+        }
+
+        fn check_health(&self) -> Result<(), HealthError> {
+            if self.is_offline() {
+                return Err(HealthError::Unavailable(String::from("offline")));
+            }
+            self.inner.check_health()
+        }
+
+        fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+            if self.is_offline() {
+                return Err(PurgeError::Unknown);
+            }
+            self.inner.purge(deleted_before)
+        }
+    }
+
+    fn mirrored(
+        policy: SecondaryFailurePolicy,
+    ) -> (MirroredRepository, Arc<InMemoryRepository>, Arc<Flaky>) {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(Flaky::new());
+        let repo = MirroredRepository::new(primary.clone(), secondary.clone(), policy);
+        (repo, primary, secondary)
+    }
+
+    fn insert_pikachu(repo: &dyn Repository) -> Result<Pokemon, InsertError> {
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+    }
+
+    #[test]
+    fn it_should_apply_writes_to_both_repositories() {
+        let (repo, primary, secondary) = mirrored(SecondaryFailurePolicy::Fail);
+
+        assert!(insert_pikachu(&repo).is_ok());
+        assert!(primary.fetch_one(PokemonNumber::pikachu()).is_ok());
+        assert!(secondary.fetch_one(PokemonNumber::pikachu()).is_ok());
+
+        assert!(repo.delete(PokemonNumber::pikachu()).is_ok());
+        assert!(primary.fetch_one(PokemonNumber::pikachu()).is_err());
+        assert!(secondary.fetch_one(PokemonNumber::pikachu()).is_err());
+    }
+
+    #[test]
+    fn it_should_only_read_from_the_primary() {
+        let (repo, _primary, secondary) = mirrored(SecondaryFailurePolicy::Fail);
+        insert_pikachu(&*secondary).ok();
+
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_roll_back_the_primary_when_failing_on_secondary_failures() {
+        let (repo, primary, secondary) = mirrored(SecondaryFailurePolicy::Fail);
+        secondary.set_offline(true);
+
+        match insert_pikachu(&repo) {
+            Err(InsertError::Unknown) => {}
+            _ => unreachable!(),
+        };
+        assert!(primary.fetch_one(PokemonNumber::pikachu()).is_err());
+        match primary.restore(PokemonNumber::pikachu()) {
+            Err(RestoreError::NotFound) => {}
+            _ => unreachable!(),
+        };
+
+        secondary.set_offline(false);
+        insert_pikachu(&repo).ok();
+        secondary.set_offline(true);
+
+        match repo.delete(PokemonNumber::pikachu()) {
+            Err(DeleteError::Unknown) => {}
+            _ => unreachable!(),
+        };
+        match primary.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(pokemon.version, 1),
+            _ => unreachable!(),
+        };
+
+        secondary.set_offline(false);
+        repo.delete(PokemonNumber::pikachu()).ok();
+        secondary.set_offline(true);
+
+        match repo.restore(PokemonNumber::pikachu()) {
+            Err(RestoreError::Unknown) => {}
+            _ => unreachable!(),
+        };
+        match primary.conditional_restore(PokemonNumber::pikachu(), Some(&[1])) {
+            Ok(pokemon) => assert_eq!(pokemon.version, 2),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_keep_the_primary_write_when_logging_secondary_failures() {
+        let (repo, primary, secondary) = mirrored(SecondaryFailurePolicy::LogAndContinue);
+        let lines = Lines::default();
+        let repo = repo.with_logger(Some(Arc::new(Logger::with_sink(
+            Format::Json,
+            Box::new(lines.clone()),
+        ))));
+        secondary.set_offline(true);
+
+        assert!(insert_pikachu(&repo).is_ok());
+        assert!(primary.fetch_one(PokemonNumber::pikachu()).is_ok());
+        assert_eq!(repo.pending(), 0);
+
+        let logged = lines.lines();
+        assert_eq!(logged.len(), 1);
+        let line = serde_json::from_str::<Value>(&logged[0]).unwrap();
+        assert_eq!(line["level"], "error");
+        assert_eq!(line["operation"], "insert");
+    }
+
+    #[test]
+    fn it_should_only_be_unhealthy_with_the_secondary_when_its_failures_fail_writes() {
+        let (repo, _primary, secondary) = mirrored(SecondaryFailurePolicy::Fail);
+        secondary.set_offline(true);
+        assert!(repo.check_health().is_err());
+        assert_eq!(repo.backend(), "memory mirrored to flaky");
+
+        let (repo, _primary, secondary) = mirrored(SecondaryFailurePolicy::Queue);
+        secondary.set_offline(true);
+        assert!(repo.check_health().is_ok());
+    }
+
+    #[test]
+    fn it_should_replay_queued_writes_in_order_once_the_secondary_is_back() {
+        let (repo, _primary, secondary) = mirrored(SecondaryFailurePolicy::Queue);
+        secondary.set_offline(true);
+
+        insert_pikachu(&repo).ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        insert_pikachu(&repo).ok();
+        assert_eq!(repo.pending(), 3);
+
+        secondary.set_offline(false);
+        repo.insert(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        )
+        .ok();
+
+        assert_eq!(repo.pending(), 0);
+        match secondary.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), 2),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_replay_queued_writes_when_closed_and_log_those_lost() {
+        let (repo, _primary, secondary) = mirrored(SecondaryFailurePolicy::Queue);
+        let lines = Lines::default();
+        let repo = repo.with_logger(Some(Arc::new(Logger::with_sink(
+            Format::Json,
+            Box::new(lines.clone()),
+        ))));
+        secondary.set_offline(true);
+        insert_pikachu(&repo).ok();
+
+        repo.close();
+        assert_eq!(repo.pending(), 1);
+        let logged = lines.lines();
+        assert_eq!(logged.len(), 1);
+        let line = serde_json::from_str::<Value>(&logged[0]).unwrap();
+        assert_eq!(line["level"], "error");
+        assert_eq!(line["pending"], 1);
+
+        secondary.set_offline(false);
+        repo.close();
+        assert_eq!(repo.pending(), 0);
+        assert_eq!(lines.lines().len(), 1);
+        assert!(secondary.fetch_one(PokemonNumber::pikachu()).is_ok());
+    }
+}
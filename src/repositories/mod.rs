@@ -1,8 +1,25 @@
 use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
+use chrono::{DateTime, Utc};
 
+#[cfg(test)]
+pub mod airtable_mock;
 pub mod airtable_repository;
+pub mod api_key_store;
+pub mod audit_log;
+pub mod audited_repository;
+pub mod cached_repository;
+#[cfg(test)]
+mod conformance;
+pub mod event_sourced_repository;
 pub mod in_memory_repository;
+pub mod instrumented_repository;
+pub mod json_file_repository;
+pub mod json_lines_audit_log;
+pub mod mirrored_repository;
+pub mod sqlite_api_key_store;
+pub mod sqlite_audit_log;
 pub mod sqlite_repository;
+pub mod sync;
 
 pub enum InsertError {
     Conflict,
@@ -20,9 +37,48 @@ pub enum FetchOneError {
 
 pub enum DeleteError {
     NotFound,
+    VersionMismatch,
     Unknown,
 }
 
+pub enum RestoreError {
+    NotFound,
+    VersionMismatch,
+    Unknown,
+}
+
+pub enum PurgeError {
+    Unknown,
+}
+
+pub enum RevertError {
+    Unknown,
+}
+
+/// What a Pokemon is put back to when a write to it which could not be completed is undone.
+#[derive(Clone)]
+pub enum Revision {
+    /// Nothing is left under the number, not even a tombstone.
+    Absent(PokemonNumber),
+    Live(Pokemon),
+    /// A tombstone at the version of the Pokemon.
+    Deleted(Pokemon),
+}
+
+impl Revision {
+    pub fn number(&self) -> PokemonNumber {
+        match self {
+            Revision::Absent(number) => number.clone(),
+            Revision::Live(pokemon) | Revision::Deleted(pokemon) => pokemon.number.clone(),
+        }
+    }
+}
+
+pub enum HealthError {
+    /// Why the backend cannot serve requests.
+    Unavailable(String),
+}
+
 pub trait Repository: Send + Sync {
     fn insert(
         &self,
@@ -32,5 +88,54 @@ pub trait Repository: Send + Sync {
     ) -> Result<Pokemon, InsertError>;
     fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError>;
     fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError>;
-    fn delete(&self, number: PokemonNumber) -> Result<(), DeleteError>;
+    /// Hides the Pokemon behind a tombstone recording when it was deleted, until it is restored
+    /// or purged. Inserting the same number again replaces the tombstone.
+    fn delete(&self, number: PokemonNumber) -> Result<(), DeleteError> {
+        self.conditional_delete(number, None)
+    }
+    /// Brings a deleted Pokemon back under its next version.
+    fn restore(&self, number: PokemonNumber) -> Result<Pokemon, RestoreError> {
+        self.conditional_restore(number, None)
+    }
+    /// Only deletes the Pokemon while it is still at one of `versions`, when they are given.
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError>;
+    /// Only restores the Pokemon while its tombstone is still at one of `versions`, the version it
+    /// had when it was deleted being the one to match, when they are given.
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError>;
+    /// Puts the Pokemon back to `revision` at the version it carries, rather than writing a new
+    /// version, to undo a write which a decorator could not complete.
+    fn revert(&self, revision: Revision) -> Result<(), RevertError>;
+    /// Permanently removes the Pokemons deleted before `deleted_before`, returning them as they
+    /// were when deleted.
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError>;
+    /// Names the backend, such as `sqlite`, decorators naming the backends they wrap.
+    fn backend(&self) -> String;
+    /// Checks that the backend can serve requests right now, going through any cache.
+    fn check_health(&self) -> Result<(), HealthError>;
+
+    /// Inserts every Pokemon, or none of them on backends which support transactions. Elsewhere
+    /// the Pokemons inserted before a failure are kept.
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let mut inserted = vec![];
+        for pokemon in pokemons {
+            inserted.push(self.insert(pokemon.number, pokemon.name, pokemon.types)?);
+        }
+        Ok(inserted)
+    }
+}
+
+/// A Pokemon inserted over a tombstone carries on from the version it was deleted at.
+fn next_version(deleted: &[(Pokemon, DateTime<Utc>)], pokemon: &Pokemon) -> u64 {
+    match deleted.iter().find(|(p, _)| p.number == pokemon.number) {
+        Some((deleted, _)) => deleted.version + 1,
+        None => 1,
+    }
 }
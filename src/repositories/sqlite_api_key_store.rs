@@ -0,0 +1,174 @@
+use super::api_key_store::{ApiKey, ApiKeyStore, InsertKeyError, RevokeKeyError, Role};
+use chrono::{DateTime, SecondsFormat, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Error::SqliteFailure, OptionalExtension};
+use std::time::Duration;
+
+const POOL_SIZE: u32 = 4;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keeps the keys in a dedicated `api_keys` table, which can live in the same database as the
+/// Pokemons.
+pub struct SqliteApiKeyStore {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteApiKeyStore {
+    pub fn try_new(path: &str) -> Result<Self, ()> {
+        let manager = SqliteConnectionManager::file(path)
+            .with_init(|connection| connection.busy_timeout(BUSY_TIMEOUT));
+
+        let pool = match Pool::builder().max_size(POOL_SIZE).build(manager) {
+            Ok(pool) => pool,
+            _ => return Err(()),
+        };
+
+        let connection = match pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        if connection
+            .execute_batch(
+                "create table if not exists api_keys (
+                    name text primary key,
+                    role text not null,
+                    hash text not null unique,
+                    created_at text not null
+                );",
+            )
+            .is_err()
+        {
+            return Err(());
+        }
+
+        drop(connection);
+        Ok(Self { pool })
+    }
+}
+
+impl ApiKeyStore for SqliteApiKeyStore {
+    fn insert(&self, key: ApiKey, hash: String) -> Result<(), InsertKeyError> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(InsertKeyError::Unknown),
+        };
+
+        match connection.execute(
+            "insert into api_keys (name, role, hash, created_at) values (?, ?, ?, ?)",
+            params![
+                key.name,
+                <&str>::from(key.role),
+                hash,
+                key.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            ],
+        ) {
+            Ok(_) => Ok(()),
+            Err(SqliteFailure(_, Some(message)))
+                if message == "UNIQUE constraint failed: api_keys.name" =>
+            {
+                Err(InsertKeyError::Conflict)
+            }
+            _ => Err(InsertKeyError::Unknown),
+        }
+    }
+
+    fn fetch_by_hash(&self, hash: &str) -> Result<Option<ApiKey>, ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let row = connection
+            .query_row(
+                "select name, role, created_at from api_keys where hash = ?",
+                params![hash],
+                |row| {
+                    Ok((
+                        row.get::<usize, String>(0)?,
+                        row.get::<usize, String>(1)?,
+                        row.get::<usize, String>(2)?,
+                    ))
+                },
+            )
+            .optional();
+
+        match row {
+            Ok(Some((name, role, created_at))) => match (
+                Role::try_from(role.as_str()),
+                DateTime::parse_from_rfc3339(&created_at),
+            ) {
+                (Ok(role), Ok(created_at)) => Ok(Some(ApiKey {
+                    name,
+                    role,
+                    created_at: created_at.with_timezone(&Utc),
+                })),
+                _ => Err(()),
+            },
+            Ok(None) => Ok(None),
+            _ => Err(()),
+        }
+    }
+
+    fn revoke(&self, name: &str) -> Result<(), RevokeKeyError> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(RevokeKeyError::Unknown),
+        };
+
+        match connection.execute("delete from api_keys where name = ?", params![name]) {
+            Ok(0) => Err(RevokeKeyError::NotFound),
+            Ok(_) => Ok(()),
+            _ => Err(RevokeKeyError::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+impl SqliteApiKeyStore {
+    pub fn temp() -> (Self, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.sqlite");
+        (Self::try_new(path.to_str().unwrap()).unwrap(), dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::api_key_store::hash;
+
+    #[test]
+    fn it_should_find_the_keys_by_hash_until_they_are_revoked() {
+        let (store, _dir) = SqliteApiKeyStore::temp();
+        let key = ApiKey {
+            name: String::from("misty"),
+            role: Role::Editor,
+            created_at: Utc::now(),
+        };
+
+        assert!(store.insert(key.clone(), hash("secret")).is_ok());
+        match store.insert(key.clone(), hash("other secret")) {
+            Err(InsertKeyError::Conflict) => {}
+            _ => unreachable!(),
+        };
+        match store.fetch_by_hash(&hash("secret")) {
+            Ok(Some(found)) => {
+                assert_eq!(found.name, key.name);
+                assert_eq!(found.role, Role::Editor);
+            }
+            _ => unreachable!(),
+        };
+
+        assert!(store.revoke("misty").is_ok());
+        match store.fetch_by_hash(&hash("secret")) {
+            Ok(None) => {}
+            _ => unreachable!(),
+        };
+        match store.revoke("misty") {
+            Err(RevokeKeyError::NotFound) => {}
+            _ => unreachable!(),
+        };
+    }
+}
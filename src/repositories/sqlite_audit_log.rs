@@ -0,0 +1,236 @@
+use super::audit_log::{AuditEvent, AuditLog, Operation, Snapshot, Transport};
+use chrono::{DateTime, SecondsFormat, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, params_from_iter, Row};
+use std::time::Duration;
+
+const POOL_SIZE: u32 = 4;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keeps the events in a dedicated `audit_events` table, which can live in the same database as
+/// the Pokemons.
+pub struct SqliteAuditLog {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteAuditLog {
+    pub fn try_new(path: &str) -> Result<Self, ()> {
+        let manager = SqliteConnectionManager::file(path)
+            .with_init(|connection| connection.busy_timeout(BUSY_TIMEOUT));
+
+        let pool = match Pool::builder().max_size(POOL_SIZE).build(manager) {
+            Ok(pool) => pool,
+            _ => return Err(()),
+        };
+
+        let connection = match pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        // Snapshots are stored as JSON, since they are only ever read back whole.
+        if connection
+            .execute_batch(
+                "create table if not exists audit_events (
+                    id integer primary key autoincrement,
+                    at text not null,
+                    actor text not null,
+                    transport text not null,
+                    operation text not null,
+                    pokemon_number integer not null,
+                    before text,
+                    after text
+                );
+                create index if not exists audit_events_pokemon_number
+                    on audit_events (pokemon_number);",
+            )
+            .is_err()
+        {
+            return Err(());
+        }
+
+        drop(connection);
+        Ok(Self { pool })
+    }
+
+    fn to_event(row: &Row) -> Result<AuditEvent, ()> {
+        let at = match row.get::<usize, String>(0) {
+            Ok(at) => match DateTime::parse_from_rfc3339(&at) {
+                Ok(at) => at.with_timezone(&Utc),
+                _ => return Err(()),
+            },
+            _ => return Err(()),
+        };
+
+        match (
+            row.get::<usize, String>(1),
+            row.get::<usize, String>(2),
+            row.get::<usize, String>(3),
+            row.get::<usize, u16>(4),
+            row.get::<usize, Option<String>>(5),
+            row.get::<usize, Option<String>>(6),
+        ) {
+            (Ok(actor), Ok(transport), Ok(operation), Ok(number), Ok(before), Ok(after)) => {
+                match (
+                    Transport::try_from(transport.as_str()),
+                    Operation::try_from(operation.as_str()),
+                    from_json(before),
+                    from_json(after),
+                ) {
+                    (Ok(transport), Ok(operation), Ok(before), Ok(after)) => Ok(AuditEvent {
+                        at,
+                        actor,
+                        transport,
+                        operation,
+                        number,
+                        before,
+                        after,
+                    }),
+                    _ => Err(()),
+                }
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+fn to_json(snapshot: &Option<Snapshot>) -> Result<Option<String>, ()> {
+    match snapshot {
+        Some(snapshot) => match serde_json::to_string(snapshot) {
+            Ok(json) => Ok(Some(json)),
+            _ => Err(()),
+        },
+        None => Ok(None),
+    }
+}
+
+fn from_json(json: Option<String>) -> Result<Option<Snapshot>, ()> {
+    match json {
+        Some(json) => match serde_json::from_str(&json) {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            _ => Err(()),
+        },
+        None => Ok(None),
+    }
+}
+
+impl AuditLog for SqliteAuditLog {
+    fn record(&self, event: &AuditEvent) -> Result<(), ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let (before, after) = match (to_json(&event.before), to_json(&event.after)) {
+            (Ok(before), Ok(after)) => (before, after),
+            _ => return Err(()),
+        };
+
+        match connection.execute(
+            "insert into audit_events (at, actor, transport, operation, pokemon_number, before, after)
+            values (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                event.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                event.actor,
+                <&str>::from(event.transport),
+                <&str>::from(event.operation),
+                event.number,
+                before,
+                after,
+            ],
+        ) {
+            Ok(_) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    fn events(&self, number: Option<u16>) -> Result<Vec<AuditEvent>, ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let (query, params) = match number {
+            Some(number) => (
+                "select at, actor, transport, operation, pokemon_number, before, after
+                from audit_events where pokemon_number = ? order by id",
+                vec![number],
+            ),
+            None => (
+                "select at, actor, transport, operation, pokemon_number, before, after
+                from audit_events order by id",
+                vec![],
+            ),
+        };
+
+        let mut stmt = match connection.prepare(query) {
+            Ok(stmt) => stmt,
+            _ => return Err(()),
+        };
+
+        let mut rows = match stmt.query(params_from_iter(params)) {
+            Ok(rows) => rows,
+            _ => return Err(()),
+        };
+
+        let mut events = vec![];
+
+        while let Ok(Some(row)) = rows.next() {
+            events.push(Self::to_event(row)?);
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_store_the_events_and_filter_them_by_number() {
+        let dir = tempfile::tempdir().unwrap();
+        let log =
+            SqliteAuditLog::try_new(dir.path().join("audit.sqlite").to_str().unwrap()).unwrap();
+        let pikachu = Snapshot {
+            number: 25,
+            name: String::from("Pikachu"),
+            types: vec![String::from("Electric")],
+        };
+        let event = AuditEvent {
+            at: Utc::now(),
+            actor: String::from("misty"),
+            transport: Transport::Api,
+            operation: Operation::Delete,
+            number: 25,
+            before: Some(pikachu.clone()),
+            after: None,
+        };
+
+        log.record(&event).unwrap();
+        log.record(&AuditEvent {
+            operation: Operation::Purge,
+            number: 4,
+            before: None,
+            ..event.clone()
+        })
+        .unwrap();
+
+        match log.events(Some(25)) {
+            Ok(events) => {
+                assert_eq!(events.len(), 1);
+                assert_eq!(events[0].actor, "misty");
+                assert_eq!(events[0].transport, Transport::Api);
+                assert_eq!(events[0].operation, Operation::Delete);
+                assert_eq!(events[0].before, Some(pikachu));
+                assert_eq!(events[0].after, None);
+            }
+            _ => unreachable!(),
+        };
+        match log.events(None) {
+            Ok(events) => assert_eq!(events.len(), 2),
+            _ => unreachable!(),
+        };
+    }
+}
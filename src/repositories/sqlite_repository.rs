@@ -1,41 +1,421 @@
-use super::{DeleteError, FetchAllError, FetchOneError, InsertError, Repository};
+use super::{
+    DeleteError, FetchAllError, FetchOneError, HealthError, InsertError, PurgeError, Repository,
+    RestoreError, RevertError, Revision,
+};
 use crate::domain::entities::{Pokemon, PokemonName, PokemonNumber, PokemonTypes};
-use rusqlite::{params, params_from_iter, Connection, Error::SqliteFailure, OpenFlags};
-use std::sync::{Mutex, MutexGuard};
+use chrono::{DateTime, SecondsFormat, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{
+    params, params_from_iter, Connection, Error::SqliteFailure, OpenFlags, OptionalExtension,
+    TransactionBehavior,
+};
+use std::collections::HashSet;
+use std::time::Duration;
+
+const POOL_SIZE: u32 = 8;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct SqliteRepository {
-    connection: Mutex<Connection>,
+    pool: Pool<SqliteConnectionManager>,
+}
+
+/// A local write which has not been pushed to the remote repository yet. The journal also
+/// records whether it was an insert or a delete, but whether the Pokemon is still stored
+/// locally is what matters when pushing it.
+pub struct Change {
+    pub id: i64,
+    pub number: u16,
+    pub changed_at: DateTime<Utc>,
+}
+
+enum Operation {
+    Insert,
+    Delete,
 }
 
 impl SqliteRepository {
     pub fn try_new(path: &str) -> Result<Self, ()> {
-        let connection = match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        let manager = SqliteConnectionManager::file(path)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .with_init(|connection| {
+                connection.busy_timeout(BUSY_TIMEOUT)?;
+                connection.execute_batch("pragma foreign_keys = 1;")
+            });
+
+        let pool = match Pool::builder().max_size(POOL_SIZE).build(manager) {
+            Ok(pool) => pool,
+            _ => return Err(()),
+        };
+
+        // The journal mode is persisted in the database file, so switching it once is enough
+        // for every connection of the pool to let readers proceed alongside a writer.
+        let connection = match pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        match connection.query_row("pragma journal_mode = wal", [], |row| {
+            row.get::<usize, String>(0)
+        }) {
+            Ok(mode) if mode.eq_ignore_ascii_case("wal") => {}
+            _ => return Err(()),
+        };
+
+        // Every local write is journaled so that it can be pushed later by `sync`.
+        if connection
+            .execute_batch(
+                "create table if not exists changes (
+                    id integer primary key autoincrement,
+                    pokemon_number integer not null,
+                    operation text not null,
+                    changed_at text not null
+                );
+                create table if not exists sync_state (
+                    name text primary key,
+                    value text not null
+                );
+                create table if not exists pushed_records (
+                    id text primary key
+                );",
+            )
+            .is_err()
+        {
+            return Err(());
+        }
+
+        // Deleted Pokemons are kept as tombstones and every Pokemon carries a version, columns
+        // which databases created before they existed lack.
+        for (column, definition) in [
+            ("deleted_at", "deleted_at text"),
+            ("version", "version integer not null default 1"),
+        ] {
+            match connection.query_row(
+                "select count(*) from pragma_table_info('pokemons') where name = ?",
+                [column],
+                |row| row.get::<usize, u32>(0),
+            ) {
+                Ok(0) => {
+                    if connection
+                        .execute_batch(&format!("alter table pokemons add column {}", definition))
+                        .is_err()
+                    {
+                        return Err(());
+                    }
+                }
+                Ok(_) => {}
+                _ => return Err(()),
+            };
+        }
+
+        drop(connection);
+        Ok(Self { pool })
+    }
+
+    /// Every journaled write, oldest first.
+    pub fn pending_changes(&self) -> Result<Vec<Change>, ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let mut stmt = match connection
+            .prepare("select id, pokemon_number, changed_at from changes order by id")
+        {
+            Ok(stmt) => stmt,
+            _ => return Err(()),
+        };
+
+        let mut rows = match stmt.query([]) {
+            Ok(rows) => rows,
+            _ => return Err(()),
+        };
+
+        let mut changes = vec![];
+
+        while let Ok(Some(row)) = rows.next() {
+            let change = match (
+                row.get::<usize, i64>(0),
+                row.get::<usize, u16>(1),
+                row.get::<usize, String>(2),
+            ) {
+                (Ok(id), Ok(number), Ok(changed_at)) => {
+                    match DateTime::parse_from_rfc3339(&changed_at) {
+                        Ok(changed_at) => Change {
+                            id,
+                            number,
+                            changed_at: changed_at.with_timezone(&Utc),
+                        },
+                        _ => return Err(()),
+                    }
+                }
+                _ => return Err(()),
+            };
+
+            changes.push(change);
+        }
+
+        Ok(changes)
+    }
+
+    /// When the last successful sync fetched the remote Pokemons, if there was one.
+    pub fn last_synced_at(&self) -> Result<Option<DateTime<Utc>>, ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        match connection
+            .query_row(
+                "select value from sync_state where name = 'last_synced_at'",
+                [],
+                |row| row.get::<usize, String>(0),
+            )
+            .optional()
+        {
+            Ok(Some(value)) => match DateTime::parse_from_rfc3339(&value) {
+                Ok(synced_at) => Ok(Some(synced_at.with_timezone(&Utc))),
+                _ => Err(()),
+            },
+            Ok(None) => Ok(None),
+            _ => Err(()),
+        }
+    }
+
+    /// The ids of the remote records created by the last successful sync.
+    pub fn pushed_records(&self) -> Result<HashSet<String>, ()> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let mut statement = match connection.prepare("select id from pushed_records") {
+            Ok(statement) => statement,
+            _ => return Err(()),
+        };
+
+        let ids = match statement.query_map([], |row| row.get::<usize, String>(0)) {
+            Ok(ids) => ids.collect::<Result<HashSet<String>, _>>(),
+            _ => return Err(()),
+        };
+
+        match ids {
+            Ok(ids) => Ok(ids),
+            _ => Err(()),
+        }
+    }
+
+    /// Forgets the changes up to `last_change` included, which were pushed by a sync which fetched
+    /// the remote Pokemons at `synced_at` and created the `pushed` records.
+    pub fn mark_synced(
+        &self,
+        last_change: Option<i64>,
+        synced_at: DateTime<Utc>,
+        pushed: &[String],
+    ) -> Result<(), ()> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(()),
+        };
+
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
         {
+            Ok(transaction) => transaction,
+            _ => return Err(()),
+        };
+
+        if let Some(id) = last_change {
+            if transaction
+                .execute("delete from changes where id <= ?", [id])
+                .is_err()
+            {
+                return Err(());
+            }
+        }
+
+        if transaction
+            .execute(
+                "insert into sync_state (name, value) values ('last_synced_at', ?)
+                on conflict (name) do update set value = excluded.value",
+                [timestamp(synced_at)],
+            )
+            .is_err()
+        {
+            return Err(());
+        }
+
+        if transaction
+            .execute("delete from pushed_records", [])
+            .is_err()
+        {
+            return Err(());
+        }
+
+        for id in pushed {
+            if transaction
+                .execute("insert or ignore into pushed_records (id) values (?)", [id])
+                .is_err()
+            {
+                return Err(());
+            }
+        }
+
+        match transaction.commit() {
+            Ok(_) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// Replaces the Pokemon with the given number by what was pulled from the remote repository,
+    /// or deletes it, without journaling the write.
+    pub fn apply_remote(&self, number: PokemonNumber, pokemon: Option<Pokemon>) -> Result<(), ()> {
+        let mut connection = match self.pool.get() {
             Ok(connection) => connection,
             _ => return Err(()),
         };
 
-        match connection.execute("pragma foreign_keys = 1", []) {
-            Ok(_) => Ok(Self {
-                connection: Mutex::new(connection),
-            }),
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(()),
+        };
+
+        match pokemon {
+            // The local Pokemon is tombstoned first, so that the pulled one carries on from its
+            // version when replacing it.
+            Some(pokemon) => {
+                if transaction
+                    .execute(
+                        "update pokemons set deleted_at = ? where number = ? and deleted_at is null",
+                        params![timestamp(Utc::now()), u16::from(number)],
+                    )
+                    .is_err()
+                    || Self::insert_rows(
+                        &transaction,
+                        &pokemon.number,
+                        &pokemon.name,
+                        &pokemon.types,
+                    )
+                    .is_err()
+                {
+                    return Err(());
+                }
+            }
+            // Pulled deletions leave a tombstone too, so that they can be restored.
+            None => {
+                if transaction
+                    .execute(
+                        "update pokemons set deleted_at = ? where number = ? and deleted_at is null",
+                        params![timestamp(Utc::now()), u16::from(number)],
+                    )
+                    .is_err()
+                {
+                    return Err(());
+                }
+            }
+        };
+
+        match transaction.commit() {
+            Ok(_) => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// Returns the version of the inserted Pokemon.
+    fn insert_rows(
+        connection: &Connection,
+        number: &PokemonNumber,
+        name: &PokemonName,
+        types: &PokemonTypes,
+    ) -> Result<u64, InsertError> {
+        // The tombstone of a deleted Pokemon gives way to the new one, along with its types, which
+        // carries on from its version.
+        let version = match connection.query_row(
+            "select coalesce(max(version), 0) + 1 from pokemons where number = ? and deleted_at is not null",
+            params![u16::from(number.clone())],
+            |row| row.get::<usize, u64>(0),
+        ) {
+            Ok(version) => version,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        if connection
+            .execute(
+                "delete from pokemons where number = ? and deleted_at is not null",
+                params![u16::from(number.clone())],
+            )
+            .is_err()
+        {
+            return Err(InsertError::Unknown);
+        }
+
+        match connection.execute(
+            "insert into pokemons (number, name, version) values (?, ?, ?)",
+            params![
+                u16::from(number.clone()),
+                String::from(name.clone()),
+                version
+            ],
+        ) {
+            Ok(_) => {}
+            Err(SqliteFailure(_, Some(message)))
+                if message == "UNIQUE constraint failed: pokemons.number" =>
+            {
+                return Err(InsertError::Conflict);
+            }
+            _ => return Err(InsertError::Unknown),
+        };
+
+        for _type in Vec::<String>::from(types.clone()) {
+            if connection
+                .execute(
+                    "insert into types (pokemon_number, name) values (?, ?)",
+                    params![u16::from(number.clone()), _type],
+                )
+                .is_err()
+            {
+                return Err(InsertError::Unknown);
+            }
+        }
+
+        Ok(version)
+    }
+
+    fn journal(
+        connection: &Connection,
+        number: &PokemonNumber,
+        operation: Operation,
+    ) -> Result<(), ()> {
+        let operation = match operation {
+            Operation::Insert => "insert",
+            Operation::Delete => "delete",
+        };
+
+        match connection.execute(
+            "insert into changes (pokemon_number, operation, changed_at) values (?, ?, ?)",
+            params![u16::from(number.clone()), operation, timestamp(Utc::now())],
+        ) {
+            Ok(_) => Ok(()),
             _ => Err(()),
         }
     }
 
     fn fetch_pokemon_rows(
-        lock: &MutexGuard<'_, Connection>,
+        connection: &Connection,
         number: Option<u16>,
-    ) -> Result<Vec<(u16, String)>, ()> {
+    ) -> Result<Vec<(u16, String, u64)>, ()> {
         let (query, params) = match number {
             Some(number) => (
-                "select number, name from pokemons where number = ?",
+                "select number, name, version from pokemons where number = ? and deleted_at is null",
                 vec![number],
             ),
-            _ => ("select number, name from pokemons", vec![]),
+            _ => (
+                "select number, name, version from pokemons where deleted_at is null order by number",
+                vec![],
+            ),
         };
 
-        let mut stmt = match lock.prepare(query) {
+        let mut stmt = match connection.prepare(query) {
             Ok(stmt) => stmt,
             _ => return Err(()),
         };
@@ -48,8 +428,44 @@ impl SqliteRepository {
         let mut pokemon_rows = vec![];
 
         while let Ok(Some(row)) = rows.next() {
-            match (row.get::<usize, u16>(0), row.get::<usize, String>(1)) {
-                (Ok(number), Ok(name)) => pokemon_rows.push((number, name)),
+            match (
+                row.get::<usize, u16>(0),
+                row.get::<usize, String>(1),
+                row.get::<usize, u64>(2),
+            ) {
+                (Ok(number), Ok(name), Ok(version)) => pokemon_rows.push((number, name, version)),
+                _ => return Err(()),
+            };
+        }
+
+        Ok(pokemon_rows)
+    }
+
+    fn fetch_deleted_rows(
+        connection: &Connection,
+        deleted_before: &str,
+    ) -> Result<Vec<(u16, String, u64)>, ()> {
+        let mut stmt = match connection.prepare(
+            "select number, name, version from pokemons where deleted_at < ? order by number",
+        ) {
+            Ok(stmt) => stmt,
+            _ => return Err(()),
+        };
+
+        let mut rows = match stmt.query([deleted_before]) {
+            Ok(rows) => rows,
+            _ => return Err(()),
+        };
+
+        let mut pokemon_rows = vec![];
+
+        while let Ok(Some(row)) = rows.next() {
+            match (
+                row.get::<usize, u16>(0),
+                row.get::<usize, String>(1),
+                row.get::<usize, u64>(2),
+            ) {
+                (Ok(number), Ok(name), Ok(version)) => pokemon_rows.push((number, name, version)),
                 _ => return Err(()),
             };
         }
@@ -57,8 +473,26 @@ impl SqliteRepository {
         Ok(pokemon_rows)
     }
 
-    fn fetch_type_rows(lock: &MutexGuard<'_, Connection>, number: u16) -> Result<Vec<String>, ()> {
-        let mut stmt = match lock.prepare("select name from types where pokemon_number = ?") {
+    /// How many rows of the Pokemon match `condition`, telling apart a missing Pokemon from one
+    /// at another version.
+    fn count(connection: &Connection, number: &PokemonNumber, condition: &str) -> Result<u32, ()> {
+        match connection.query_row(
+            &format!(
+                "select count(*) from pokemons where number = ? and {}",
+                condition
+            ),
+            params![u16::from(number.clone())],
+            |row| row.get::<usize, u32>(0),
+        ) {
+            Ok(count) => Ok(count),
+            _ => Err(()),
+        }
+    }
+
+    fn fetch_type_rows(connection: &Connection, number: u16) -> Result<Vec<String>, ()> {
+        let mut stmt = match connection
+            .prepare("select name from types where pokemon_number = ? order by rowid")
+        {
             Ok(stmt) => stmt,
             _ => return Err(()),
         };
@@ -88,53 +522,43 @@ impl Repository for SqliteRepository {
         name: PokemonName,
         types: PokemonTypes,
     ) -> Result<Pokemon, InsertError> {
-        let mut lock = match self.connection.lock() {
-            Ok(lock) => lock,
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
             _ => return Err(InsertError::Unknown),
         };
 
-        let transaction = match lock.transaction() {
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
             Ok(transaction) => transaction,
             _ => return Err(InsertError::Unknown),
         };
 
-        match transaction.execute(
-            "insert into pokemons (number, name) values (?, ?)",
-            params![u16::from(number.clone()), String::from(name.clone())],
-        ) {
-            Ok(_) => {}
-            Err(SqliteFailure(_, Some(message))) => {
-                if message == "UNIQUE constraint failed: pokemons.number" {
-                    return Err(InsertError::Conflict);
-                } else {
-                    return Err(InsertError::Unknown);
-                }
-            }
-            _ => return Err(InsertError::Unknown),
-        };
+        let version = Self::insert_rows(&transaction, &number, &name, &types)?;
 
-        for _type in Vec::<String>::from(types.clone()) {
-            if let Err(_) = transaction.execute(
-                "insert into types (pokemon_number, name) values (?, ?)",
-                params![u16::from(number.clone()), _type],
-            ) {
-                return Err(InsertError::Unknown);
-            }
+        if Self::journal(&transaction, &number, Operation::Insert).is_err() {
+            return Err(InsertError::Unknown);
         }
 
         match transaction.commit() {
-            Ok(_) => Ok(Pokemon::new(number, name, types)),
+            Ok(_) => Ok(Pokemon::new(number, name, types).with_version(version)),
             _ => Err(InsertError::Unknown),
         }
     }
 
     fn fetch_all(&self) -> Result<Vec<Pokemon>, FetchAllError> {
-        let lock = match self.connection.lock() {
-            Ok(lock) => lock,
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(FetchAllError::Unknown),
+        };
+
+        // Reading pokemons and their types within one transaction gives a consistent snapshot
+        // even if a writer commits in between.
+        let transaction = match connection.transaction() {
+            Ok(transaction) => transaction,
             _ => return Err(FetchAllError::Unknown),
         };
 
-        let pokemon_rows = match Self::fetch_pokemon_rows(&lock, None) {
+        let pokemon_rows = match Self::fetch_pokemon_rows(&transaction, None) {
             Ok(pokemon_rows) => pokemon_rows,
             _ => return Err(FetchAllError::Unknown),
         };
@@ -142,7 +566,7 @@ impl Repository for SqliteRepository {
         let mut pokemons = vec![];
 
         for pokemon_row in pokemon_rows {
-            let type_rows = match Self::fetch_type_rows(&lock, pokemon_row.0) {
+            let type_rows = match Self::fetch_type_rows(&transaction, pokemon_row.0) {
                 Ok(type_rows) => type_rows,
                 _ => return Err(FetchAllError::Unknown),
             };
@@ -152,7 +576,9 @@ impl Repository for SqliteRepository {
                 PokemonName::try_from(pokemon_row.1),
                 PokemonTypes::try_from(type_rows),
             ) {
-                (Ok(number), Ok(name), Ok(types)) => Pokemon::new(number, name, types),
+                (Ok(number), Ok(name), Ok(types)) => {
+                    Pokemon::new(number, name, types).with_version(pokemon_row.2)
+                }
                 _ => return Err(FetchAllError::Unknown),
             };
 
@@ -163,13 +589,18 @@ impl Repository for SqliteRepository {
     }
 
     fn fetch_one(&self, number: PokemonNumber) -> Result<Pokemon, FetchOneError> {
-        let lock = match self.connection.lock() {
-            Ok(lock) => lock,
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(FetchOneError::Unknown),
+        };
+
+        let transaction = match connection.transaction() {
+            Ok(transaction) => transaction,
             _ => return Err(FetchOneError::Unknown),
         };
 
         let mut pokemon_rows =
-            match Self::fetch_pokemon_rows(&lock, Some(u16::from(number.clone()))) {
+            match Self::fetch_pokemon_rows(&transaction, Some(u16::from(number.clone()))) {
                 Ok(pokemon_rows) => pokemon_rows,
                 _ => return Err(FetchOneError::Unknown),
             };
@@ -180,7 +611,7 @@ impl Repository for SqliteRepository {
 
         let pokemon_row = pokemon_rows.remove(0);
 
-        let type_rows = match Self::fetch_type_rows(&lock, pokemon_row.0) {
+        let type_rows = match Self::fetch_type_rows(&transaction, pokemon_row.0) {
             Ok(type_rows) => type_rows,
             _ => return Err(FetchOneError::Unknown),
         };
@@ -190,24 +621,515 @@ impl Repository for SqliteRepository {
             PokemonName::try_from(pokemon_row.1),
             PokemonTypes::try_from(type_rows),
         ) {
-            (Ok(number), Ok(name), Ok(types)) => Ok(Pokemon::new(number, name, types)),
+            (Ok(number), Ok(name), Ok(types)) => {
+                Ok(Pokemon::new(number, name, types).with_version(pokemon_row.2))
+            }
             _ => Err(FetchOneError::Unknown),
         }
     }
 
-    fn delete(&self, number: PokemonNumber) -> Result<(), DeleteError> {
-        let lock = match self.connection.lock() {
-            Ok(lock) => lock,
+    fn conditional_delete(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<(), DeleteError> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
             _ => return Err(DeleteError::Unknown),
         };
 
-        match lock.execute(
-            "delete from pokemons where number = ?",
-            params![u16::from(number)],
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(DeleteError::Unknown),
+        };
+
+        match transaction.execute(
+            "update pokemons set deleted_at = ?1
+            where number = ?2 and deleted_at is null
+            and (?3 is null or version in (select value from json_each(?3)))",
+            params![
+                timestamp(Utc::now()),
+                u16::from(number.clone()),
+                versions.map(|versions| serde_json::json!(versions).to_string())
+            ],
         ) {
-            Ok(0) => Err(DeleteError::NotFound),
+            Ok(0) => {
+                return match Self::count(&transaction, &number, "deleted_at is null") {
+                    Ok(0) => Err(DeleteError::NotFound),
+                    Ok(_) => Err(DeleteError::VersionMismatch),
+                    _ => Err(DeleteError::Unknown),
+                }
+            }
+            Ok(_) => {}
+            _ => return Err(DeleteError::Unknown),
+        };
+
+        if Self::journal(&transaction, &number, Operation::Delete).is_err() {
+            return Err(DeleteError::Unknown);
+        }
+
+        match transaction.commit() {
             Ok(_) => Ok(()),
             _ => Err(DeleteError::Unknown),
         }
     }
+
+    fn conditional_restore(
+        &self,
+        number: PokemonNumber,
+        versions: Option<&[u64]>,
+    ) -> Result<Pokemon, RestoreError> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        match transaction.execute(
+            "update pokemons set deleted_at = null, version = version + 1
+            where number = ?1 and deleted_at is not null
+            and (?2 is null or version in (select value from json_each(?2)))",
+            params![
+                u16::from(number.clone()),
+                versions.map(|versions| serde_json::json!(versions).to_string())
+            ],
+        ) {
+            Ok(0) => {
+                return match Self::count(&transaction, &number, "deleted_at is not null") {
+                    Ok(0) => Err(RestoreError::NotFound),
+                    Ok(_) => Err(RestoreError::VersionMismatch),
+                    _ => Err(RestoreError::Unknown),
+                }
+            }
+            Ok(_) => {}
+            _ => return Err(RestoreError::Unknown),
+        };
+
+        // Restoring is pushed like an insert, the Pokemon being stored locally again.
+        if Self::journal(&transaction, &number, Operation::Insert).is_err() {
+            return Err(RestoreError::Unknown);
+        }
+
+        if transaction.commit().is_err() {
+            return Err(RestoreError::Unknown);
+        }
+
+        match self.fetch_one(number) {
+            Ok(pokemon) => Ok(pokemon),
+            _ => Err(RestoreError::Unknown),
+        }
+    }
+
+    fn revert(&self, revision: Revision) -> Result<(), RevertError> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(RevertError::Unknown),
+        };
+
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(RevertError::Unknown),
+        };
+
+        let number = revision.number();
+        if transaction
+            .execute(
+                "delete from pokemons where number = ?",
+                params![u16::from(number.clone())],
+            )
+            .is_err()
+        {
+            return Err(RevertError::Unknown);
+        }
+
+        let (pokemon, deleted_at, operation) = match revision {
+            Revision::Absent(_) => (None, None, Operation::Delete),
+            Revision::Live(pokemon) => (Some(pokemon), None, Operation::Insert),
+            Revision::Deleted(pokemon) => (
+                Some(pokemon),
+                Some(timestamp(Utc::now())),
+                Operation::Delete,
+            ),
+        };
+
+        if let Some(pokemon) = pokemon {
+            if transaction
+                .execute(
+                    "insert into pokemons (number, name, version, deleted_at) values (?, ?, ?, ?)",
+                    params![
+                        u16::from(pokemon.number.clone()),
+                        String::from(pokemon.name),
+                        pokemon.version,
+                        deleted_at
+                    ],
+                )
+                .is_err()
+            {
+                return Err(RevertError::Unknown);
+            }
+
+            for _type in Vec::<String>::from(pokemon.types) {
+                if transaction
+                    .execute(
+                        "insert into types (pokemon_number, name) values (?, ?)",
+                        params![u16::from(pokemon.number.clone()), _type],
+                    )
+                    .is_err()
+                {
+                    return Err(RevertError::Unknown);
+                }
+            }
+        }
+
+        // The sync pushes whatever the Pokemon ends up being, which the journal only has to flag.
+        if Self::journal(&transaction, &number, operation).is_err() {
+            return Err(RevertError::Unknown);
+        }
+
+        match transaction.commit() {
+            Ok(_) => Ok(()),
+            _ => Err(RevertError::Unknown),
+        }
+    }
+
+    fn backend(&self) -> String {
+        String::from("sqlite")
+    }
+
+    fn check_health(&self) -> Result<(), HealthError> {
+        let connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => {
+                return Err(HealthError::Unavailable(String::from(
+                    "no connection to the database could be opened",
+                )))
+            }
+        };
+
+        match connection.execute_batch("select 1 from pokemons limit 1") {
+            Ok(()) => Ok(()),
+            Err(error) => Err(HealthError::Unavailable(format!(
+                "the database could not be read: {}",
+                error
+            ))),
+        }
+    }
+
+    fn purge(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Pokemon>, PurgeError> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        // Taking the write lock upfront, so that a write committed between reading the tombstones
+        // and deleting them waits rather than failing the purge.
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        // Timestamps share a single format, so comparing them as text orders them in time.
+        let pokemon_rows = match Self::fetch_deleted_rows(&transaction, &timestamp(deleted_before))
+        {
+            Ok(pokemon_rows) => pokemon_rows,
+            _ => return Err(PurgeError::Unknown),
+        };
+
+        let mut purged = vec![];
+
+        for pokemon_row in pokemon_rows {
+            let type_rows = match Self::fetch_type_rows(&transaction, pokemon_row.0) {
+                Ok(type_rows) => type_rows,
+                _ => return Err(PurgeError::Unknown),
+            };
+
+            match (
+                PokemonNumber::try_from(pokemon_row.0),
+                PokemonName::try_from(pokemon_row.1),
+                PokemonTypes::try_from(type_rows),
+            ) {
+                (Ok(number), Ok(name), Ok(types)) => {
+                    purged.push(Pokemon::new(number, name, types).with_version(pokemon_row.2))
+                }
+                _ => return Err(PurgeError::Unknown),
+            };
+        }
+
+        if transaction
+            .execute(
+                "delete from pokemons where deleted_at < ?",
+                [timestamp(deleted_before)],
+            )
+            .is_err()
+        {
+            return Err(PurgeError::Unknown);
+        }
+
+        match transaction.commit() {
+            Ok(()) => Ok(purged),
+            _ => Err(PurgeError::Unknown),
+        }
+    }
+
+    fn insert_batch(&self, pokemons: Vec<Pokemon>) -> Result<Vec<Pokemon>, InsertError> {
+        let mut connection = match self.pool.get() {
+            Ok(connection) => connection,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let transaction = match connection.transaction_with_behavior(TransactionBehavior::Immediate)
+        {
+            Ok(transaction) => transaction,
+            _ => return Err(InsertError::Unknown),
+        };
+
+        let mut inserted = vec![];
+
+        for pokemon in pokemons {
+            let version =
+                Self::insert_rows(&transaction, &pokemon.number, &pokemon.name, &pokemon.types)?;
+
+            if Self::journal(&transaction, &pokemon.number, Operation::Insert).is_err() {
+                return Err(InsertError::Unknown);
+            }
+
+            inserted.push(pokemon.with_version(version));
+        }
+
+        match transaction.commit() {
+            Ok(_) => Ok(inserted),
+            _ => Err(InsertError::Unknown),
+        }
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+impl SqliteRepository {
+    pub fn temp() -> (Self, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("database.sqlite");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "create table pokemons (
+                    number integer primary key,
+                    name text
+                );
+                create table types (
+                    pokemon_number integer,
+                    name text,
+                    foreign key (pokemon_number) references pokemons (number) on delete cascade,
+                    primary key (pokemon_number, name)
+                );",
+            )
+            .unwrap();
+
+        (Self::try_new(path.to_str().unwrap()).unwrap(), dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    const THREADS: u16 = 32;
+
+    #[test]
+    fn it_should_switch_the_database_to_wal_mode() {
+        let (repo, _dir) = SqliteRepository::temp();
+
+        let mode = repo
+            .pool
+            .get()
+            .unwrap()
+            .query_row("pragma journal_mode", [], |row| row.get::<usize, String>(0))
+            .unwrap();
+
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn it_should_serve_concurrent_reads_and_writes_without_errors() {
+        let (repo, _dir) = SqliteRepository::temp();
+        let repo = Arc::new(repo);
+        let barrier = Arc::new(Barrier::new(THREADS as usize));
+
+        let handles = (1..=THREADS)
+            .map(|n| {
+                let repo = repo.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..10 {
+                        assert!(repo.fetch_all().is_ok());
+                    }
+                    assert!(repo
+                        .insert(
+                            PokemonNumber::try_from(n).unwrap(),
+                            PokemonName::pikachu(),
+                            PokemonTypes::pikachu(),
+                        )
+                        .is_ok());
+                    assert!(repo.fetch_one(PokemonNumber::try_from(n).unwrap()).is_ok());
+                    assert!(repo.fetch_all().is_ok());
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        match repo.fetch_all() {
+            Ok(pokemons) => assert_eq!(pokemons.len(), THREADS as usize),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_let_only_one_of_concurrent_inserts_of_the_same_number_succeed() {
+        let (repo, _dir) = SqliteRepository::temp();
+        let repo = Arc::new(repo);
+        let barrier = Arc::new(Barrier::new(THREADS as usize));
+
+        let handles = (0..THREADS)
+            .map(|_| {
+                let repo = repo.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    repo.insert(
+                        PokemonNumber::pikachu(),
+                        PokemonName::pikachu(),
+                        PokemonTypes::pikachu(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut inserted = 0;
+        for handle in handles {
+            match handle.join().unwrap() {
+                Ok(_) => inserted += 1,
+                Err(InsertError::Conflict) => {}
+                Err(InsertError::Unknown) => unreachable!(),
+            }
+        }
+
+        assert_eq!(inserted, 1);
+    }
+
+    #[test]
+    fn it_should_cascade_the_purge_to_the_types() {
+        let (repo, _dir) = SqliteRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+
+        assert!(repo.delete(PokemonNumber::pikachu()).is_ok());
+        assert!(matches!(
+            repo.purge(Utc::now() + TimeDelta::seconds(1)),
+            Ok(purged) if purged.len() == 1
+        ));
+
+        let count = repo
+            .pool
+            .get()
+            .unwrap()
+            .query_row("select count(*) from types", [], |row| {
+                row.get::<usize, u32>(0)
+            })
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn it_should_insert_nothing_from_a_batch_with_a_conflict() {
+        let (repo, _dir) = SqliteRepository::temp();
+        let pikachu = Pokemon::new(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        );
+        let charmander = Pokemon::new(
+            PokemonNumber::charmander(),
+            PokemonName::charmander(),
+            PokemonTypes::charmander(),
+        );
+
+        let res = repo.insert_batch(vec![charmander, pikachu.clone(), pikachu]);
+
+        match res {
+            Err(InsertError::Conflict) => {}
+            _ => unreachable!(),
+        };
+        match repo.fetch_all() {
+            Ok(pokemons) => assert!(pokemons.is_empty()),
+            _ => unreachable!(),
+        };
+        assert!(repo.pending_changes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_should_journal_local_writes_but_not_remote_ones() {
+        let (repo, _dir) = SqliteRepository::temp();
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+        repo.delete(PokemonNumber::pikachu()).ok();
+        repo.apply_remote(
+            PokemonNumber::charmander(),
+            Some(Pokemon::new(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            )),
+        )
+        .ok();
+
+        let changes = repo.pending_changes().unwrap();
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| change.number)
+                .collect::<Vec<u16>>(),
+            vec![25, 25]
+        );
+        assert!(repo.fetch_one(PokemonNumber::charmander()).is_ok());
+
+        let synced_at = Utc::now();
+        repo.mark_synced(Some(changes[1].id), synced_at, &[String::from("rec1")])
+            .ok();
+
+        assert!(repo.pending_changes().unwrap().is_empty());
+        assert_eq!(
+            repo.last_synced_at().unwrap().map(timestamp),
+            Some(timestamp(synced_at))
+        );
+        assert_eq!(
+            repo.pushed_records().unwrap(),
+            HashSet::from([String::from("rec1")])
+        );
+    }
 }
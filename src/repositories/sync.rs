@@ -0,0 +1,438 @@
+use super::airtable_repository::{AirtableRepository, RemoteWrite};
+use super::sqlite_repository::{Change, SqliteRepository};
+use super::{DeleteError, Repository};
+use crate::domain::entities::{Pokemon, PokemonNumber};
+use chrono::Utc;
+use std::collections::{BTreeSet, HashMap};
+
+/// What a sync changed on each side.
+#[derive(Default)]
+pub struct SyncReport {
+    pub pushed: Vec<u16>,
+    pub deleted_remotely: Vec<u16>,
+    pub pulled: Vec<u16>,
+    pub deleted_locally: Vec<u16>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A Pokemon changed on both sides since the last sync, the most recent change winning.
+pub struct Conflict {
+    pub number: u16,
+    pub winner: Side,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+pub enum SyncError {
+    Unknown,
+}
+
+/// Pushes the writes journaled by the local repository to the remote one, then pulls whatever
+/// changed remotely.
+///
+/// A Pokemon is in conflict when it was written locally and its remote record, or the tombstone
+/// left by a remote delete, was created after the previous sync fetched the remote Pokemons
+/// without being one of the records it pushed; the write with the latest timestamp wins. Both
+/// clocks are compared directly, so a skew between this machine and Airtable shifts which side
+/// wins close calls.
+///
+/// The journal is only cleared once every change went through, so a sync which failed halfway can
+/// simply be run again.
+pub fn sync(
+    local: &SqliteRepository,
+    remote: &AirtableRepository,
+) -> Result<SyncReport, SyncError> {
+    let (changes, last_synced_at, pushed_before) = match (
+        local.pending_changes(),
+        local.last_synced_at(),
+        local.pushed_records(),
+    ) {
+        (Ok(changes), Ok(last_synced_at), Ok(pushed_before)) => {
+            (changes, last_synced_at, pushed_before)
+        }
+        _ => return Err(SyncError::Unknown),
+    };
+
+    let local_pokemons = match local.fetch_all() {
+        Ok(pokemons) => by_number(pokemons.into_iter().map(|p| (p, ()))),
+        _ => return Err(SyncError::Unknown),
+    };
+
+    // Taken before fetching, so that a record created meanwhile is looked at again next time
+    // rather than missed.
+    let synced_at = Utc::now();
+    let remote_writes = match remote.fetch_latest_writes() {
+        Ok(writes) => writes
+            .into_iter()
+            .map(|write| (write.number, write))
+            .collect::<HashMap<u16, RemoteWrite>>(),
+        _ => return Err(SyncError::Unknown),
+    };
+
+    let last_change = changes.last().map(|change| change.id);
+    let latest_changes = latest_by_number(changes);
+
+    let numbers = local_pokemons
+        .keys()
+        .chain(remote_writes.keys())
+        .chain(latest_changes.keys())
+        .cloned()
+        .collect::<BTreeSet<u16>>();
+
+    let changed_remotely = |write: &RemoteWrite| {
+        !pushed_before.contains(&write.record_id)
+            && match last_synced_at {
+                Some(synced_at) => write.modified_at > synced_at,
+                None => true,
+            }
+    };
+
+    let mut report = SyncReport::default();
+    let mut pushed = vec![];
+
+    for number in numbers {
+        let local_pokemon = local_pokemons.get(&number).map(|(p, _)| p);
+        let remote_write = remote_writes.get(&number);
+        let remote_pokemon = remote_write.and_then(|write| write.pokemon.as_ref());
+
+        match (latest_changes.get(&number), remote_write) {
+            _ if local_pokemon == remote_pokemon => {}
+            (Some(_), None) => {
+                push(remote, number, local_pokemon, &mut report, &mut pushed)?;
+            }
+            (Some(_), Some(write)) if !changed_remotely(write) => {
+                push(remote, number, local_pokemon, &mut report, &mut pushed)?;
+            }
+            (Some(change), Some(write)) if change.changed_at >= write.modified_at => {
+                push(remote, number, local_pokemon, &mut report, &mut pushed)?;
+                report.conflicts.push(Conflict {
+                    number,
+                    winner: Side::Local,
+                });
+            }
+            (Some(_), Some(_)) => {
+                pull(local, number, remote_pokemon, &mut report)?;
+                report.conflicts.push(Conflict {
+                    number,
+                    winner: Side::Remote,
+                });
+            }
+            // Pokemons stored before the journal existed are pushed by the first sync.
+            (None, None) if last_synced_at.is_none() => {
+                push(remote, number, local_pokemon, &mut report, &mut pushed)?;
+            }
+            (None, _) => {
+                pull(local, number, remote_pokemon, &mut report)?;
+            }
+        }
+    }
+
+    match local.mark_synced(last_change, synced_at, &pushed) {
+        Ok(()) => Ok(report),
+        _ => Err(SyncError::Unknown),
+    }
+}
+
+fn by_number<T>(pokemons: impl Iterator<Item = (Pokemon, T)>) -> HashMap<u16, (Pokemon, T)> {
+    pokemons
+        .map(|(pokemon, extra)| (u16::from(pokemon.number.clone()), (pokemon, extra)))
+        .collect()
+}
+
+/// Only the latest write to a Pokemon matters, as it supersedes the ones before.
+fn latest_by_number(changes: Vec<Change>) -> HashMap<u16, Change> {
+    let mut latest = HashMap::new();
+    for change in changes {
+        latest.insert(change.number, change);
+    }
+    latest
+}
+
+/// Makes the remote repository hold `pokemon` under `number`, or nothing when it is `None`,
+/// adding the records created to `pushed`.
+fn push(
+    remote: &AirtableRepository,
+    number: u16,
+    pokemon: Option<&Pokemon>,
+    report: &mut SyncReport,
+    pushed: &mut Vec<String>,
+) -> Result<(), SyncError> {
+    let pokemon_number = match PokemonNumber::try_from(number) {
+        Ok(number) => number,
+        _ => return Err(SyncError::Unknown),
+    };
+
+    match remote.delete_with_record_id(pokemon_number) {
+        Ok(tombstone) => pushed.push(tombstone),
+        Err(DeleteError::NotFound) => {}
+        Err(DeleteError::VersionMismatch) | Err(DeleteError::Unknown) => {
+            return Err(SyncError::Unknown)
+        }
+    };
+
+    match pokemon {
+        Some(pokemon) => match remote.insert_with_record_id(
+            pokemon.number.clone(),
+            pokemon.name.clone(),
+            pokemon.types.clone(),
+        ) {
+            Ok((_, record)) => {
+                pushed.push(record);
+                report.pushed.push(number);
+            }
+            _ => return Err(SyncError::Unknown),
+        },
+        None => report.deleted_remotely.push(number),
+    };
+
+    Ok(())
+}
+
+/// Makes the local repository hold `pokemon` under `number`, or nothing when it is `None`.
+fn pull(
+    local: &SqliteRepository,
+    number: u16,
+    pokemon: Option<&Pokemon>,
+    report: &mut SyncReport,
+) -> Result<(), SyncError> {
+    let pokemon_number = match PokemonNumber::try_from(number) {
+        Ok(number) => number,
+        _ => return Err(SyncError::Unknown),
+    };
+
+    if local
+        .apply_remote(pokemon_number, pokemon.cloned())
+        .is_err()
+    {
+        return Err(SyncError::Unknown);
+    }
+
+    match pokemon {
+        Some(_) => report.pulled.push(number),
+        None => report.deleted_locally.push(number),
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PokemonName, PokemonTypes};
+    use crate::repositories::airtable_mock::AirtableMock;
+    use serde_json::json;
+    use std::thread;
+    use std::time::Duration;
+
+    fn remote(mock: &AirtableMock) -> AirtableRepository {
+        AirtableRepository::try_new(AirtableMock::API_KEY, AirtableMock::BASE_ID, mock.config())
+            .unwrap()
+    }
+
+    fn insert_pikachu(repo: &dyn Repository) {
+        repo.insert(
+            PokemonNumber::pikachu(),
+            PokemonName::pikachu(),
+            PokemonTypes::pikachu(),
+        )
+        .ok();
+    }
+
+    fn numbers(repo: &dyn Repository) -> Vec<u16> {
+        match repo.fetch_all() {
+            Ok(pokemons) => pokemons.into_iter().map(|p| u16::from(p.number)).collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn sync_ok(local: &SqliteRepository, remote: &AirtableRepository) -> SyncReport {
+        match sync(local, remote) {
+            Ok(report) => report,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_should_push_local_writes_and_clear_the_journal() {
+        let mock = AirtableMock::start();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        local
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::charmander(),
+                PokemonTypes::charmander(),
+            )
+            .ok();
+        sync_ok(&local, &remote);
+
+        local.delete(PokemonNumber::charmander()).ok();
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.deleted_remotely, vec![4]);
+        assert_eq!(numbers(&remote), vec![25]);
+        assert!(local.pending_changes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_should_pull_remote_writes() {
+        let mock = AirtableMock::start();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        sync_ok(&local, &remote);
+
+        remote.delete(PokemonNumber::pikachu()).ok();
+        mock.insert(json!({ "number": 4, "name": "Charmander", "types": ["Fire"] }));
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.pulled, vec![4]);
+        assert_eq!(report.deleted_locally, vec![25]);
+        assert_eq!(numbers(&local), vec![4]);
+        assert!(local.pending_changes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_should_not_take_the_records_it_pushed_for_remote_changes() {
+        let mock = AirtableMock::start();
+        mock.use_the_clock();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        sync_ok(&local, &remote);
+
+        let report = sync_ok(&local, &remote);
+        assert!(report.pushed.is_empty());
+        assert!(report.pulled.is_empty());
+        assert!(report.conflicts.is_empty());
+
+        local.delete(PokemonNumber::pikachu()).ok();
+        let report = sync_ok(&local, &remote);
+        assert_eq!(report.deleted_remotely, vec![25]);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_the_local_write_when_it_is_the_most_recent() {
+        let mock = AirtableMock::start();
+        mock.insert(json!({ "number": 25, "name": "Raichu", "types": ["Electric"] }));
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].winner, Side::Local);
+        match remote.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Pikachu"),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn it_should_keep_the_remote_write_when_it_is_the_most_recent() {
+        let mock = AirtableMock::start();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        sync_ok(&local, &remote);
+
+        local.delete(PokemonNumber::pikachu()).ok();
+        remote.delete(PokemonNumber::pikachu()).ok();
+        mock.insert_at(
+            "2999-01-01T00:00:00.000Z",
+            json!({ "number": 25, "name": "Raichu", "types": ["Electric"] }),
+        );
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].winner, Side::Remote);
+        match local.fetch_one(PokemonNumber::pikachu()) {
+            Ok(pokemon) => assert_eq!(String::from(pokemon.name), "Raichu"),
+            _ => unreachable!(),
+        };
+        // Only the tombstone left by the remote delete sits next to the winning record.
+        assert_eq!(
+            mock.records()
+                .iter()
+                .filter(|record| record.get("deleted_at").is_none())
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn it_should_detect_a_conflict_with_a_remote_delete() {
+        let mock = AirtableMock::start();
+        mock.use_the_clock();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        sync_ok(&local, &remote);
+
+        local.delete(PokemonNumber::pikachu()).ok();
+        local
+            .insert(
+                PokemonNumber::pikachu(),
+                PokemonName::try_from(String::from("Raichu")).unwrap(),
+                PokemonTypes::pikachu(),
+            )
+            .ok();
+        thread::sleep(Duration::from_millis(10));
+        remote.delete(PokemonNumber::pikachu()).ok();
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].winner, Side::Remote);
+        assert_eq!(report.deleted_locally, vec![25]);
+        assert!(numbers(&local).is_empty());
+    }
+
+    #[test]
+    fn it_should_detect_a_conflict_with_a_remote_write_made_while_pushing() {
+        let mock = AirtableMock::start();
+        mock.use_the_clock();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        mock.race_next_create(json!({ "number": 4, "name": "Charmander", "types": ["Fire"] }));
+        sync_ok(&local, &remote);
+
+        thread::sleep(Duration::from_millis(10));
+        local
+            .insert(
+                PokemonNumber::charmander(),
+                PokemonName::try_from(String::from("Charmeleon")).unwrap(),
+                PokemonTypes::charmander(),
+            )
+            .ok();
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].number, 4);
+        assert_eq!(report.conflicts[0].winner, Side::Local);
+    }
+
+    #[test]
+    fn it_should_keep_the_journal_when_the_push_fails() {
+        let mock = AirtableMock::start();
+        let remote = remote(&mock);
+        let (local, _dir) = SqliteRepository::temp();
+        insert_pikachu(&local);
+        mock.fail_next("POST", 500);
+
+        assert!(sync(&local, &remote).is_err());
+        assert_eq!(local.pending_changes().unwrap().len(), 1);
+
+        let report = sync_ok(&local, &remote);
+
+        assert_eq!(report.pushed, vec![25]);
+        assert!(local.pending_changes().unwrap().is_empty());
+    }
+}
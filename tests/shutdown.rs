@@ -0,0 +1,182 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Smaller bodies would be read in full before the request is routed.
+const PADDING: usize = 2048;
+
+fn body() -> String {
+    format!(
+        r#"{{"number":25,"name":"Pikachu","types":["Electric"]}}{}"#,
+        " ".repeat(PADDING)
+    )
+}
+
+/// The API served from a copy of the SQLite database, on a port of its own.
+struct Server {
+    child: Child,
+    address: String,
+    dir: TempDir,
+}
+
+impl Server {
+    fn start(shutdown_timeout: u64) -> Self {
+        let dir = TempDir::new().unwrap();
+        let database = dir.path().join("database.sqlite");
+        std::fs::copy(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/database.sqlite"),
+            &database,
+        )
+        .unwrap();
+
+        // The port is released right away for the server to listen on.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let address = format!("127.0.0.1:{}", port);
+
+        let child = Command::new(env!("CARGO_BIN_EXE_pokedex"))
+            .arg("--sqlite")
+            .arg(&database)
+            .arg("--listen")
+            .arg(&address)
+            .arg("--shutdown-timeout")
+            .arg(shutdown_timeout.to_string())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let started_at = Instant::now();
+        while TcpStream::connect(&address).is_err() {
+            assert!(started_at.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        Self {
+            child,
+            address,
+            dir,
+        }
+    }
+
+    /// Sends the headers of a request creating Pikachu along with half its body, so that the
+    /// request stays in flight until the rest is sent.
+    fn start_creating_pikachu(&self) -> (TcpStream, String) {
+        let body = body();
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.address,
+            body.len(),
+            &body[..body.len() / 2]
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        // Leaves the server the time to hand the request over to the router.
+        thread::sleep(Duration::from_millis(300));
+        let rest = String::from(&body[body.len() / 2..]);
+        (stream, rest)
+    }
+
+    fn terminate(&self) {
+        let status = Command::new("kill")
+            .arg("-TERM")
+            .arg(self.child.id().to_string())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn is_listening(&self) -> bool {
+        TcpStream::connect(&self.address).is_ok()
+    }
+
+    fn wait(&mut self, timeout: Duration) -> ExitStatus {
+        let started_at = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+            if started_at.elapsed() > timeout {
+                self.child.kill().ok();
+                panic!("The server did not shut down in time");
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn has_pikachu(&self) -> bool {
+        rusqlite::Connection::open(self.dir.path().join("database.sqlite"))
+            .unwrap()
+            .query_row(
+                "select count(*) from pokemons where number = 25",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            == 1
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+    }
+}
+
+#[test]
+fn it_should_stop_accepting_connections_but_finish_the_requests_in_flight_when_terminated() {
+    let mut server = Server::start(10);
+    let (mut stream, rest) = server.start_creating_pikachu();
+
+    server.terminate();
+    let started_at = Instant::now();
+    while server.is_listening() {
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    stream.write_all(rest.as_bytes()).unwrap();
+    let mut res = String::new();
+    stream.read_to_string(&mut res).unwrap();
+
+    assert!(
+        res.starts_with("HTTP/1.1 200"),
+        "unexpected response {}",
+        res
+    );
+    assert!(server.wait(Duration::from_secs(5)).success());
+    assert!(server.has_pikachu());
+}
+
+#[test]
+fn it_should_give_up_on_the_requests_still_in_flight_at_the_deadline() {
+    let mut server = Server::start(1);
+    let _request = server.start_creating_pikachu();
+
+    server.terminate();
+
+    let status = server.wait(Duration::from_secs(5));
+    assert_eq!(status.code(), Some(1));
+    assert!(!server.has_pikachu());
+}
+
+#[test]
+fn it_should_exit_right_away_when_terminated_twice() {
+    let mut server = Server::start(30);
+    let _request = server.start_creating_pikachu();
+
+    server.terminate();
+    thread::sleep(Duration::from_millis(300));
+    server.terminate();
+
+    let status = server.wait(Duration::from_secs(2));
+    assert_eq!(status.code(), Some(1));
+    assert!(!server.has_pikachu());
+}